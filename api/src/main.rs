@@ -1,6 +1,9 @@
+use std::net::SocketAddr;
+
 use axum::Router;
-use auth::router as auth_router;
+use auth::{router as auth_router, AppState as AuthState};
 use common::database::{create_pool, run_migrations};
+use common::Scheduler;
 
 #[tokio::main]
 async fn main() {
@@ -17,9 +20,19 @@ async fn main() {
         .await
         .expect("Failed to run migrations");
 
+    // Build auth service state from environment configuration
+    let auth_state = AuthState::from_env(pool.clone())
+        .await
+        .expect("Failed to load auth configuration");
+
+    // Expired tokens and sessions are cleaned up by whichever instance gets there first
+    Scheduler::new(pool.clone())
+        .with_jobs(auth::maintenance::jobs(&auth_state))
+        .start();
+
     // Create the main application router
     let app = Router::new()
-        .nest("/auth", auth_router(pool));
+        .nest("/auth", auth_router(auth_state));
 
     // Start the server
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
@@ -28,7 +41,8 @@ async fn main() {
 
     println!("Server running on http://0.0.0.0:3000");
     
-    axum::serve(listener, app)
+    // Peer addresses let the auth service record where sessions come from
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("Failed to start server");
 }
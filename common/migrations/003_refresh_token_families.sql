@@ -0,0 +1,14 @@
+-- Refresh token families: every sign-in starts a family, rotations stay in it
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS family_id UUID NOT NULL DEFAULT gen_random_uuid();
+
+CREATE INDEX IF NOT EXISTS idx_sessions_family_id ON sessions(family_id);
+
+-- Superseded refresh tokens, kept to detect replay of a rotated token
+CREATE TABLE IF NOT EXISTS rotated_refresh_tokens (
+    refresh_token TEXT PRIMARY KEY,
+    session_id VARCHAR(255) NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    family_id UUID NOT NULL,
+    rotated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_rotated_refresh_tokens_session_id ON rotated_refresh_tokens(session_id);
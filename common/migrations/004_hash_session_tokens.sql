@@ -0,0 +1,14 @@
+-- Session tokens are bearer credentials: keep only their SHA-256 digests
+ALTER TABLE sessions RENAME COLUMN access_token TO access_token_hash;
+ALTER TABLE sessions RENAME COLUMN refresh_token TO refresh_token_hash;
+
+UPDATE sessions SET
+    access_token_hash = encode(sha256(convert_to(access_token_hash, 'UTF8')), 'hex'),
+    refresh_token_hash = encode(sha256(convert_to(refresh_token_hash, 'UTF8')), 'hex');
+
+ALTER INDEX idx_sessions_refresh_token RENAME TO idx_sessions_refresh_token_hash;
+
+ALTER TABLE rotated_refresh_tokens RENAME COLUMN refresh_token TO refresh_token_hash;
+
+UPDATE rotated_refresh_tokens SET
+    refresh_token_hash = encode(sha256(convert_to(refresh_token_hash, 'UTF8')), 'hex');
@@ -0,0 +1,12 @@
+-- Access token signing keys created through rotation. A key signs from activates_at
+-- until a newer key activates, and verifies tokens until it is retired.
+CREATE TABLE IF NOT EXISTS signing_keys (
+    kid VARCHAR(255) PRIMARY KEY,
+    algorithm VARCHAR(16) NOT NULL,
+    private_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    activates_at TIMESTAMPTZ NOT NULL,
+    retired_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_signing_keys_activates_at ON signing_keys(activates_at);
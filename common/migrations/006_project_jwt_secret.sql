@@ -0,0 +1,2 @@
+-- Optional per-project HMAC secret for access tokens, kept out of the settings JSON
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS jwt_secret TEXT;
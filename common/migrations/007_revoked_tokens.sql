@@ -0,0 +1,14 @@
+-- Access tokens revoked before they expire, keyed by their `jti` claim.
+-- Rows are only needed until expires_at, after which the token is rejected anyway.
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
+-- The latest access token minted for a session, revoked along with it
+ALTER TABLE sessions ADD COLUMN access_token_jti VARCHAR(64);
@@ -0,0 +1,4 @@
+-- Device details parsed from the user agent, so users can tell their sessions apart
+ALTER TABLE sessions ADD COLUMN device VARCHAR(32);
+ALTER TABLE sessions ADD COLUMN browser VARCHAR(64);
+ALTER TABLE sessions ADD COLUMN os VARCHAR(64);
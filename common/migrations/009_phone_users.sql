@@ -0,0 +1,6 @@
+-- Users who sign in with an SMS code may have no email address
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_email_or_phone CHECK (email IS NOT NULL OR phone IS NOT NULL);
+
+-- A phone number identifies at most one user per project
+CREATE UNIQUE INDEX idx_users_project_phone ON users(project_id, phone) WHERE phone IS NOT NULL;
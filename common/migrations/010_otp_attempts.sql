@@ -0,0 +1,9 @@
+-- Codes are stored hashed, and burned after too many wrong guesses
+ALTER TABLE otp_codes RENAME COLUMN code TO code_hash;
+ALTER TABLE otp_codes ALTER COLUMN code_hash TYPE VARCHAR(64);
+ALTER TABLE otp_codes ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+
+-- Plaintext codes issued before this migration can no longer be checked
+UPDATE otp_codes SET used = true WHERE used = false;
+
+CREATE INDEX idx_otp_codes_project_identifier ON otp_codes(project_id, identifier, created_at DESC);
@@ -0,0 +1,11 @@
+-- Links are stored hashed, may carry a validated redirect and a PKCE challenge
+-- binding them to the browser that asked for them
+ALTER TABLE magic_links RENAME COLUMN token TO token_hash;
+ALTER TABLE magic_links ALTER COLUMN token_hash TYPE VARCHAR(64);
+ALTER TABLE magic_links ADD COLUMN code_challenge VARCHAR(128);
+ALTER TABLE magic_links ADD COLUMN redirect_to TEXT;
+
+-- Plaintext tokens issued before this migration can no longer be looked up
+UPDATE magic_links SET used = true WHERE used = false;
+
+ALTER INDEX idx_magic_links_token RENAME TO idx_magic_links_token_hash;
@@ -0,0 +1,7 @@
+-- Cross-device links: the requesting client polls with its own token while the
+-- link is opened elsewhere, which only approves the request
+ALTER TABLE magic_links ADD COLUMN poll_token_hash VARCHAR(64);
+ALTER TABLE magic_links ADD COLUMN approved_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX idx_magic_links_poll_token_hash ON magic_links(poll_token_hash)
+    WHERE poll_token_hash IS NOT NULL;
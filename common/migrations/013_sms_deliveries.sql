@@ -0,0 +1,14 @@
+-- Messages handed to an SMS provider, kept up to date by its delivery status callbacks
+CREATE TABLE IF NOT EXISTS sms_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    provider VARCHAR(32) NOT NULL,
+    provider_message_id VARCHAR(64) NOT NULL,
+    recipient VARCHAR(32) NOT NULL,
+    status VARCHAR(32) NOT NULL,
+    error_code VARCHAR(16),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, provider_message_id)
+);
+
+CREATE INDEX idx_sms_deliveries_created_at ON sms_deliveries(created_at);
@@ -0,0 +1,21 @@
+-- Accounts at social providers that sign in as a user. A user can have several;
+-- each provider account belongs to at most one user per project.
+CREATE TABLE IF NOT EXISTS identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(64) NOT NULL,
+    provider_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    -- Latest normalised profile the provider returned
+    identity_data JSONB NOT NULL DEFAULT '{}',
+    last_signin_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (project_id, provider, provider_id)
+);
+
+CREATE INDEX idx_identities_user_id ON identities(user_id);
+
+-- Set when a signed-in user started the flow to link another provider account
+ALTER TABLE oauth_states ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
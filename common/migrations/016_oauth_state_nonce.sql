@@ -0,0 +1,2 @@
+-- Sent to OpenID Connect providers and checked against the id_token they return
+ALTER TABLE oauth_states ADD COLUMN nonce VARCHAR(128);
@@ -0,0 +1,5 @@
+-- The signer a project used before its secret last changed, accepted until
+-- previous_jwt_secret_expires_at. A NULL secret with an expiry means the service
+-- keyring signed before.
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS previous_jwt_secret TEXT;
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS previous_jwt_secret_expires_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- Lets the maintenance job prune superseded refresh tokens by age
+CREATE INDEX IF NOT EXISTS idx_rotated_refresh_tokens_rotated_at ON rotated_refresh_tokens(rotated_at);
@@ -0,0 +1,3 @@
+-- Cross-device links: keyed hash of the code shown on the requesting device, which
+-- whoever opens the link must enter to approve it
+ALTER TABLE magic_links ADD COLUMN match_code_hash VARCHAR(64);
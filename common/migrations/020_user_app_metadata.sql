@@ -0,0 +1,3 @@
+-- Metadata only the admin API can write, unlike the client-set `metadata`, so token
+-- claims templates can trust it
+ALTER TABLE users ADD COLUMN IF NOT EXISTS app_metadata JSONB NOT NULL DEFAULT '{}';
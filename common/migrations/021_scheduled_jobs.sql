@@ -0,0 +1,6 @@
+-- When each maintenance job last started, so that across all replicas a job runs
+-- at most once per interval
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    name VARCHAR(255) PRIMARY KEY,
+    last_run_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Key material is written encrypted with KEY_ENCRYPTION_KEY ("enc:v1:" prefix), which
+-- never enters the database. Rows written before are still read; rotate them out.
+COMMENT ON COLUMN signing_keys.private_key IS 'Encrypted with KEY_ENCRYPTION_KEY';
+COMMENT ON COLUMN projects.jwt_secret IS 'Encrypted with KEY_ENCRYPTION_KEY';
+COMMENT ON COLUMN projects.previous_jwt_secret IS 'Encrypted with KEY_ENCRYPTION_KEY';
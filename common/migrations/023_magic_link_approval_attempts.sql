@@ -0,0 +1,2 @@
+-- Cross-device links are burned after too many approvals with the wrong match code
+ALTER TABLE magic_links ADD COLUMN IF NOT EXISTS approval_attempts INTEGER NOT NULL DEFAULT 0;
@@ -1,5 +1,7 @@
 pub mod models;
 pub mod database;
+pub mod scheduler;
 
 pub use models::*;
 pub use database::*;
+pub use scheduler::*;
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// An account at a social provider that signs in as a user
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Identity {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+    /// Provider name as configured in the project's settings, e.g. `github`
+    pub provider: String,
+    /// The provider's id for the account
+    pub provider_id: String,
+    pub email: Option<String>,
+    /// Latest normalised profile the provider returned
+    pub identity_data: serde_json::Value,
+    pub last_signin_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Identity {
+    /// Link a provider account to a user
+    pub async fn create(pool: &PgPool, identity: &Identity) -> Result<Identity, sqlx::Error> {
+        sqlx::query_as::<_, Identity>(
+            r#"
+            INSERT INTO identities (
+                id, project_id, user_id, provider, provider_id, email, identity_data,
+                last_signin_at, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(identity.id)
+        .bind(identity.project_id)
+        .bind(identity.user_id)
+        .bind(&identity.provider)
+        .bind(&identity.provider_id)
+        .bind(&identity.email)
+        .bind(&identity.identity_data)
+        .bind(identity.last_signin_at)
+        .bind(identity.created_at)
+        .bind(identity.updated_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Find the identity for a provider account
+    pub async fn find_by_provider_id(
+        pool: &PgPool,
+        project_id: Uuid,
+        provider: &str,
+        provider_id: &str,
+    ) -> Result<Option<Identity>, sqlx::Error> {
+        sqlx::query_as::<_, Identity>(
+            "SELECT * FROM identities WHERE project_id = $1 AND provider = $2 AND provider_id = $3",
+        )
+        .bind(project_id)
+        .bind(provider)
+        .bind(provider_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// All identities linked to a user, oldest first
+    pub async fn find_by_user_id(pool: &PgPool, user_id: Uuid) -> Result<Vec<Identity>, sqlx::Error> {
+        sqlx::query_as::<_, Identity>("SELECT * FROM identities WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Unlink an identity from its user
+    pub async fn delete(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM identities WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
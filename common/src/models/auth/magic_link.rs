@@ -9,7 +9,11 @@ pub struct MagicLink {
     pub project_id: Uuid,
     pub user_id: Option<Uuid>,
     pub email: String,
-    pub token: String,
+    pub token_hash: String,
+    pub code_challenge: Option<String>,
+    pub redirect_to: Option<String>,
+    pub poll_token_hash: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
     pub expires_at: DateTime<Utc>,
     pub used: bool,
     pub created_at: DateTime<Utc>,
@@ -21,8 +25,9 @@ impl MagicLink {
         sqlx::query_as::<_, MagicLink>(
             r#"
             INSERT INTO magic_links (
-                id, project_id, user_id, email, token, expires_at, used, created_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                id, project_id, user_id, email, token_hash, code_challenge, redirect_to,
+                poll_token_hash, approved_at, expires_at, used, created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING *
             "#,
         )
@@ -30,7 +35,11 @@ impl MagicLink {
         .bind(magic_link.project_id)
         .bind(magic_link.user_id)
         .bind(&magic_link.email)
-        .bind(&magic_link.token)
+        .bind(&magic_link.token_hash)
+        .bind(&magic_link.code_challenge)
+        .bind(&magic_link.redirect_to)
+        .bind(&magic_link.poll_token_hash)
+        .bind(magic_link.approved_at)
         .bind(magic_link.expires_at)
         .bind(magic_link.used)
         .bind(magic_link.created_at)
@@ -38,20 +47,20 @@ impl MagicLink {
         .await
     }
 
-    /// Find magic link by token
-    pub async fn find_by_token(
+    /// Find an unused, unexpired magic link by the hash of its token
+    pub async fn find_by_token_hash(
         pool: &PgPool,
-        token: &str,
+        token_hash: &str,
     ) -> Result<Option<MagicLink>, sqlx::Error> {
         sqlx::query_as::<_, MagicLink>(
             r#"
             SELECT * FROM magic_links
-            WHERE token = $1 AND used = false AND expires_at > NOW()
+            WHERE token_hash = $1 AND used = false AND expires_at > NOW()
             ORDER BY created_at DESC
             LIMIT 1
             "#,
         )
-        .bind(token)
+        .bind(token_hash)
         .fetch_optional(pool)
         .await
     }
@@ -5,6 +5,7 @@ pub mod otp;
 pub mod magic_link;
 pub mod password_reset;
 pub mod api_key;
+pub mod identity;
 
 pub use user::*;
 pub use session::*;
@@ -13,3 +14,4 @@ pub use otp::*;
 pub use magic_link::*;
 pub use password_reset::*;
 pub use api_key::*;
+pub use identity::*;
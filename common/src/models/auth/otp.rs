@@ -8,11 +8,13 @@ pub struct OtpCode {
     pub id: Uuid,
     pub project_id: Uuid,
     pub identifier: String, // email or phone
-    pub code: String,
+    pub code_hash: String,
     pub channel: String, // "email" or "sms"
     pub expires_at: DateTime<Utc>,
     pub used: bool,
     pub created_at: DateTime<Utc>,
+    /// Verification attempts made against this code
+    pub attempts: i32,
 }
 
 impl OtpCode {
@@ -21,41 +23,41 @@ impl OtpCode {
         sqlx::query_as::<_, OtpCode>(
             r#"
             INSERT INTO otp_codes (
-                id, project_id, identifier, code, channel, expires_at, used, created_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                id, project_id, identifier, code_hash, channel, expires_at, used, created_at, attempts
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING *
             "#,
         )
         .bind(otp.id)
         .bind(otp.project_id)
         .bind(&otp.identifier)
-        .bind(&otp.code)
+        .bind(&otp.code_hash)
         .bind(&otp.channel)
         .bind(otp.expires_at)
         .bind(otp.used)
         .bind(otp.created_at)
+        .bind(otp.attempts)
         .fetch_one(pool)
         .await
     }
 
-    /// Find OTP code by identifier and code
-    pub async fn find_by_identifier_and_code(
+    /// Find the code most recently sent to an identifier. Codes are stored hashed,
+    /// so the caller checks the guess and counts the attempt.
+    pub async fn find_latest(
         pool: &PgPool,
         project_id: Uuid,
         identifier: &str,
-        code: &str,
     ) -> Result<Option<OtpCode>, sqlx::Error> {
         sqlx::query_as::<_, OtpCode>(
             r#"
             SELECT * FROM otp_codes
-            WHERE project_id = $1 AND identifier = $2 AND code = $3 AND used = false AND expires_at > NOW()
+            WHERE project_id = $1 AND identifier = $2
             ORDER BY created_at DESC
             LIMIT 1
             "#,
         )
         .bind(project_id)
         .bind(identifier)
-        .bind(code)
         .fetch_optional(pool)
         .await
     }
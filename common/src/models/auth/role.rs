@@ -113,6 +113,12 @@ pub struct Permission {
     pub action: String,
 }
 
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.resource, self.action)
+    }
+}
+
 impl Permission {
     pub fn new(resource: impl Into<String>, action: impl Into<String>) -> Self {
         Self {
@@ -121,10 +127,6 @@ impl Permission {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{}:{}", self.resource, self.action)
-    }
-
     pub fn from_string(s: &str) -> Option<Self> {
         let parts: Vec<&str> = s.split(':').collect();
         if parts.len() == 2 {
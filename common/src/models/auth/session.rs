@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
@@ -8,10 +9,17 @@ pub struct Session {
     pub id: String,
     pub user_id: Uuid,
     pub project_id: Uuid,
-    pub access_token: String,
-    pub refresh_token: String,
+    pub family_id: Uuid,
+    pub access_token_hash: String,
+    pub refresh_token_hash: String,
+    /// `jti` of the latest access token, denied when the session is revoked
+    pub access_token_jti: Option<String>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// `desktop`, `mobile`, `bot` or `other`, parsed from the user agent
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub last_active_at: DateTime<Utc>,
@@ -19,28 +27,41 @@ pub struct Session {
 }
 
 impl Session {
+    /// Hash a session token using SHA-256; tokens are never stored in plaintext
+    pub fn hash_token(token: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(token.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
     /// Create a new session
     pub async fn create(pool: &PgPool, session: &Session) -> Result<Session, sqlx::Error> {
         sqlx::query_as::<_, Session>(
             r#"
             INSERT INTO sessions (
-                id, user_id, project_id, access_token, refresh_token,
-                ip_address, user_agent, created_at, expires_at, last_active_at, revoked
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                id, user_id, project_id, family_id, access_token_hash, refresh_token_hash,
+                ip_address, user_agent, device, browser, os,
+                created_at, expires_at, last_active_at, revoked, access_token_jti
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING *
             "#,
         )
         .bind(&session.id)
         .bind(session.user_id)
         .bind(session.project_id)
-        .bind(&session.access_token)
-        .bind(&session.refresh_token)
+        .bind(session.family_id)
+        .bind(&session.access_token_hash)
+        .bind(&session.refresh_token_hash)
         .bind(&session.ip_address)
         .bind(&session.user_agent)
+        .bind(&session.device)
+        .bind(&session.browser)
+        .bind(&session.os)
         .bind(session.created_at)
         .bind(session.expires_at)
         .bind(session.last_active_at)
         .bind(session.revoked)
+        .bind(&session.access_token_jti)
         .fetch_one(pool)
         .await
     }
@@ -58,8 +79,8 @@ impl Session {
         pool: &PgPool,
         refresh_token: &str,
     ) -> Result<Option<Session>, sqlx::Error> {
-        sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE refresh_token = $1")
-            .bind(refresh_token)
+        sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE refresh_token_hash = $1")
+            .bind(Self::hash_token(refresh_token))
             .fetch_optional(pool)
             .await
     }
@@ -79,21 +100,34 @@ impl Session {
         sqlx::query_as::<_, Session>(
             r#"
             UPDATE sessions SET
-                access_token = $2, refresh_token = $3, last_active_at = $4, revoked = $5, expires_at = $6
+                access_token_hash = $2, refresh_token_hash = $3, last_active_at = $4, revoked = $5, expires_at = $6,
+                access_token_jti = $7
             WHERE id = $1
             RETURNING *
             "#,
         )
         .bind(&session.id)
-        .bind(&session.access_token)
-        .bind(&session.refresh_token)
+        .bind(&session.access_token_hash)
+        .bind(&session.refresh_token_hash)
         .bind(session.last_active_at)
         .bind(session.revoked)
         .bind(session.expires_at)
+        .bind(&session.access_token_jti)
         .fetch_one(pool)
         .await
     }
 
+    /// Revoke every session in a refresh token family
+    pub async fn revoke_family(pool: &PgPool, family_id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked = true WHERE family_id = $1 AND revoked = false",
+        )
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
     /// Delete session by ID
     pub async fn delete(pool: &PgPool, id: &str) -> Result<(), sqlx::Error> {
         sqlx::query("DELETE FROM sessions WHERE id = $1")
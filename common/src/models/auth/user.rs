@@ -7,7 +7,7 @@ use uuid::Uuid;
 pub struct User {
     pub id: Uuid,
     pub project_id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
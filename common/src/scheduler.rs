@@ -0,0 +1,147 @@
+use sqlx::PgPool;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+pub type JobError = Box<dyn std::error::Error + Send + Sync>;
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<u64, JobError>> + Send>>;
+
+/// A periodic maintenance task that reports how many rows each run touched
+#[derive(Clone)]
+pub struct Job {
+    pub name: &'static str,
+    pub interval: Duration,
+    run: Arc<dyn Fn() -> JobFuture + Send + Sync>,
+}
+
+impl Job {
+    pub fn new<F, Fut, E>(name: &'static str, interval: Duration, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<u64, E>> + Send + 'static,
+        E: Into<JobError>,
+    {
+        Self {
+            name,
+            interval,
+            run: Arc::new(move || {
+                let run = run();
+                Box::pin(async move { run.await.map_err(Into::into) })
+            }),
+        }
+    }
+
+    /// Run the job unless some instance already started it within the last interval,
+    /// in which case `None` is returned. The run is claimed in a single statement, so
+    /// no connection is held for it beyond those the job itself uses.
+    pub async fn run_exclusive(&self, pool: &PgPool) -> Result<Option<u64>, JobError> {
+        if !self.claim(pool).await? {
+            return Ok(None);
+        }
+
+        Ok(Some((self.run)().await?))
+    }
+
+    /// Record a run starting now, unless one already started within the interval
+    async fn claim(&self, pool: &PgPool) -> Result<bool, sqlx::Error> {
+        let claimed: Option<String> = sqlx::query_scalar(
+            r#"
+            INSERT INTO scheduled_jobs (name, last_run_at) VALUES ($1, NOW())
+            ON CONFLICT (name) DO UPDATE SET last_run_at = NOW()
+            WHERE scheduled_jobs.last_run_at <= NOW() - make_interval(secs => $2)
+            RETURNING name
+            "#,
+        )
+        .bind(self.name)
+        .bind(self.interval.as_secs_f64())
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(claimed.is_some())
+    }
+}
+
+/// Runs maintenance jobs on their intervals. Every instance can start one; the
+/// `scheduled_jobs` table makes sure each job runs on only one of them per interval.
+pub struct Scheduler {
+    pool: PgPool,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, jobs: Vec::new() }
+    }
+
+    /// Add jobs to run; those with a zero interval are disabled
+    pub fn with_jobs(mut self, jobs: impl IntoIterator<Item = Job>) -> Self {
+        self.jobs.extend(jobs.into_iter().filter(|job| !job.interval.is_zero()));
+        self
+    }
+
+    /// Spawn one task per job. The first run of each happens right away.
+    pub fn start(self) -> Vec<JoinHandle<()>> {
+        self.jobs
+            .into_iter()
+            .map(|job| {
+                let pool = self.pool.clone();
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(job.interval);
+                    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    loop {
+                        interval.tick().await;
+                        match job.run_exclusive(&pool).await {
+                            Ok(Some(rows)) => {
+                                tracing::info!("Maintenance job {} touched {} row(s)", job.name, rows)
+                            }
+                            Ok(None) => {
+                                tracing::debug!("Maintenance job {} is running elsewhere", job.name)
+                            }
+                            Err(e) => tracing::error!("Maintenance job {} failed: {}", job.name, e),
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_job(pool: PgPool) -> Job {
+        Job::new("test.count", Duration::from_secs(60), move || {
+            let pool = pool.clone();
+            async move {
+                let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+                    .fetch_one(&pool)
+                    .await?;
+                Ok::<_, sqlx::Error>(count as u64)
+            }
+        })
+    }
+
+    #[sqlx::test]
+    async fn test_job_runs_once_per_interval_across_instances(pool: PgPool) {
+        let job = count_job(pool.clone());
+        let other_instance = count_job(pool.clone());
+
+        assert_eq!(job.run_exclusive(&pool).await.unwrap(), Some(0));
+        assert_eq!(other_instance.run_exclusive(&pool).await.unwrap(), None);
+        assert_eq!(job.run_exclusive(&pool).await.unwrap(), None);
+
+        // Once the interval has passed, whichever instance gets there first runs it
+        sqlx::query("UPDATE scheduled_jobs SET last_run_at = NOW() - INTERVAL '61 seconds' WHERE name = $1")
+            .bind(job.name)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(other_instance.run_exclusive(&pool).await.unwrap(), Some(0));
+        assert_eq!(job.run_exclusive(&pool).await.unwrap(), None);
+    }
+}
@@ -1,22 +1,79 @@
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
+    pub jwt_algorithm: String,
+    pub jwt_issuer: String,
+    pub jwt_private_key_path: Option<String>,
+    pub jwt_key_id: Option<String>,
+    /// Encrypts rotated signing keys and project secrets in the database, so reading
+    /// the tables is not enough to mint tokens; defaults to `JWT_SECRET`
+    pub key_encryption_key: String,
+    pub jwt_key_propagation_seconds: u64,
     pub jwt_expiry_seconds: u64,
+    pub jwt_max_permissions: usize,
     pub refresh_token_expiry_seconds: u64,
     pub smtp_host: Option<String>,
     pub smtp_port: Option<u16>,
     pub smtp_username: Option<String>,
     pub smtp_password: Option<String>,
     pub smtp_from: Option<String>,
+    /// Development only: with no SMTP server configured, log emails, codes and links
+    /// included, instead of failing to send them
+    pub email_dev_log: bool,
     pub twilio_account_sid: Option<String>,
     pub twilio_auth_token: Option<String>,
+    /// Sender number, or the SID of a Messaging Service (`MG...`)
     pub twilio_from: Option<String>,
+    /// Twilio REST API root; overridable to test against a stub server
+    pub twilio_base_url: String,
+    /// Public URL of `/sms/status/twilio`. Messages ask Twilio to report delivery there,
+    /// and callback signatures are checked against it.
+    pub twilio_status_callback_url: Option<String>,
+    pub vonage_api_key: Option<String>,
+    pub vonage_api_secret: Option<String>,
+    pub vonage_from: Option<String>,
+    pub vonage_base_url: String,
+    pub messagebird_access_key: Option<String>,
+    pub messagebird_originator: Option<String>,
+    pub messagebird_base_url: String,
+    /// Endpoint receiving `{"to", "message"}` for delivery through a gateway of our own
+    pub sms_webhook_url: Option<String>,
+    /// Key for the `x-merco-signature` HMAC sent with each webhook delivery
+    pub sms_webhook_secret: Option<String>,
+    /// Development only: with no SMS provider configured, log messages, codes included,
+    /// instead of failing to send them
+    pub sms_dev_log: bool,
+    /// Public URL of this router, used to build OAuth callback URLs
+    pub public_url: String,
     pub allowed_origins: Vec<String>,
+    /// Proxies whose `Forwarded`/`X-Forwarded-For` headers are believed; empty trusts none
+    pub trusted_proxies: Vec<IpNet>,
     pub rate_limit_per_minute: u32,
+    /// HMAC key for stored OTP codes, kept out of the database so a copy of the table
+    /// cannot be brute-forced; defaults to `JWT_SECRET`
+    pub otp_pepper: String,
+    /// Wrong guesses allowed before an OTP code, or a cross-device magic link's match
+    /// code, is burned
+    pub otp_max_attempts: i32,
+    /// Minimum wait before another OTP code is sent to the same address or number
+    pub otp_resend_cooldown_seconds: u64,
+    /// Longest a client may hold a cross-device magic link poll open
+    pub magic_link_max_poll_wait_seconds: u64,
+    pub admin_api_token: Option<String>,
+    /// How often expired sessions are revoked; 0 disables the job
+    pub session_cleanup_interval_seconds: u64,
+    /// How often expired OTP codes, magic links, reset tokens, OAuth states and denylist entries,
+    /// and history older than `history_retention_seconds`, are deleted
+    pub token_cleanup_interval_seconds: u64,
+    /// How long ended sessions, superseded refresh tokens and SMS delivery records are
+    /// kept; never less than the refresh token lifetime, so reuse is still detected
+    pub history_retention_seconds: u64,
 }
 
 impl Config {
@@ -26,10 +83,25 @@ impl Config {
                 .unwrap_or_else(|_| "postgres://localhost/merco_auth".to_string()),
             jwt_secret: env::var("JWT_SECRET")
                 .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
+            jwt_algorithm: env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string()),
+            jwt_issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "merco-auth".to_string()),
+            jwt_private_key_path: env::var("JWT_PRIVATE_KEY_PATH").ok(),
+            jwt_key_id: env::var("JWT_KEY_ID").ok(),
+            key_encryption_key: env::var("KEY_ENCRYPTION_KEY")
+                .or_else(|_| env::var("JWT_SECRET"))
+                .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
+            jwt_key_propagation_seconds: env::var("JWT_KEY_PROPAGATION_SECONDS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
             jwt_expiry_seconds: env::var("JWT_EXPIRY_SECONDS")
                 .unwrap_or_else(|_| "3600".to_string())
                 .parse()
                 .unwrap_or(3600),
+            jwt_max_permissions: env::var("JWT_MAX_PERMISSIONS")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .unwrap_or(100),
             refresh_token_expiry_seconds: env::var("REFRESH_TOKEN_EXPIRY_SECONDS")
                 .unwrap_or_else(|_| "2592000".to_string()) // 30 days
                 .parse()
@@ -41,18 +113,158 @@ impl Config {
             smtp_username: env::var("SMTP_USERNAME").ok(),
             smtp_password: env::var("SMTP_PASSWORD").ok(),
             smtp_from: env::var("SMTP_FROM").ok(),
+            email_dev_log: env::var("EMAIL_DEV_LOG")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
             twilio_account_sid: env::var("TWILIO_ACCOUNT_SID").ok(),
             twilio_auth_token: env::var("TWILIO_AUTH_TOKEN").ok(),
             twilio_from: env::var("TWILIO_FROM").ok(),
+            twilio_base_url: env::var("TWILIO_BASE_URL")
+                .unwrap_or_else(|_| "https://api.twilio.com".to_string()),
+            twilio_status_callback_url: env::var("TWILIO_STATUS_CALLBACK_URL").ok(),
+            vonage_api_key: env::var("VONAGE_API_KEY").ok(),
+            vonage_api_secret: env::var("VONAGE_API_SECRET").ok(),
+            vonage_from: env::var("VONAGE_FROM").ok(),
+            vonage_base_url: env::var("VONAGE_BASE_URL")
+                .unwrap_or_else(|_| "https://rest.nexmo.com".to_string()),
+            messagebird_access_key: env::var("MESSAGEBIRD_ACCESS_KEY").ok(),
+            messagebird_originator: env::var("MESSAGEBIRD_ORIGINATOR").ok(),
+            messagebird_base_url: env::var("MESSAGEBIRD_BASE_URL")
+                .unwrap_or_else(|_| "https://rest.messagebird.com".to_string()),
+            sms_webhook_url: env::var("SMS_WEBHOOK_URL").ok(),
+            sms_webhook_secret: env::var("SMS_WEBHOOK_SECRET").ok(),
+            sms_dev_log: env::var("SMS_DEV_LOG")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            public_url: env::var("AUTH_PUBLIC_URL")
+                .unwrap_or_else(|_| "http://localhost:3000/auth".to_string()),
             allowed_origins: env::var("ALLOWED_ORIGINS")
                 .unwrap_or_else(|_| "*".to_string())
                 .split(',')
                 .map(|s| s.trim().to_string())
                 .collect(),
+            trusted_proxies: parse_trusted_proxies(
+                &env::var("TRUSTED_PROXIES").unwrap_or_default(),
+            )?,
             rate_limit_per_minute: env::var("RATE_LIMIT_PER_MINUTE")
                 .unwrap_or_else(|_| "60".to_string())
                 .parse()
                 .unwrap_or(60),
+            otp_pepper: env::var("OTP_PEPPER")
+                .or_else(|_| env::var("JWT_SECRET"))
+                .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
+            otp_max_attempts: env::var("OTP_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            otp_resend_cooldown_seconds: env::var("OTP_RESEND_COOLDOWN_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            magic_link_max_poll_wait_seconds: env::var("MAGIC_LINK_MAX_POLL_WAIT_SECONDS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            admin_api_token: env::var("ADMIN_API_TOKEN").ok(),
+            session_cleanup_interval_seconds: env::var("SESSION_CLEANUP_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            token_cleanup_interval_seconds: env::var("TOKEN_CLEANUP_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            history_retention_seconds: env::var("HISTORY_RETENTION_SECONDS")
+                .unwrap_or_else(|_| "7776000".to_string()) // 90 days
+                .parse()
+                .unwrap_or(7776000),
         })
     }
 }
+
+/// Comma-separated CIDR ranges; a bare address trusts just that host
+fn parse_trusted_proxies(value: &str) -> Result<Vec<IpNet>, ipnet::AddrParseError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<IpNet>()
+                .or_else(|e| s.parse::<IpAddr>().map(IpNet::from).map_err(|_| e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+impl Config {
+    /// Deterministic configuration for unit tests
+    pub(crate) fn for_tests() -> Self {
+        Config {
+            database_url: "postgres://test".to_string(),
+            jwt_secret: "test-secret-key-for-testing-purposes-only".to_string(),
+            jwt_algorithm: "HS256".to_string(),
+            jwt_issuer: "merco-auth".to_string(),
+            jwt_private_key_path: None,
+            jwt_key_id: None,
+            key_encryption_key: "test-key-encryption-key".to_string(),
+            jwt_key_propagation_seconds: 0,
+            jwt_expiry_seconds: 3600,
+            jwt_max_permissions: 100,
+            refresh_token_expiry_seconds: 2592000,
+            smtp_host: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_from: None,
+            email_dev_log: false,
+            twilio_account_sid: None,
+            twilio_auth_token: None,
+            twilio_from: None,
+            twilio_base_url: "https://api.twilio.com".to_string(),
+            twilio_status_callback_url: None,
+            vonage_api_key: None,
+            vonage_api_secret: None,
+            vonage_from: None,
+            vonage_base_url: "https://rest.nexmo.com".to_string(),
+            messagebird_access_key: None,
+            messagebird_originator: None,
+            messagebird_base_url: "https://rest.messagebird.com".to_string(),
+            sms_webhook_url: None,
+            sms_webhook_secret: None,
+            sms_dev_log: false,
+            public_url: "http://localhost:3000/auth".to_string(),
+            allowed_origins: vec!["*".to_string()],
+            trusted_proxies: Vec::new(),
+            rate_limit_per_minute: 60,
+            otp_pepper: "test-otp-pepper".to_string(),
+            otp_max_attempts: 5,
+            otp_resend_cooldown_seconds: 60,
+            magic_link_max_poll_wait_seconds: 30,
+            admin_api_token: None,
+            session_cleanup_interval_seconds: 300,
+            token_cleanup_interval_seconds: 3600,
+            history_retention_seconds: 7776000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trusted_proxies() {
+        let proxies = parse_trusted_proxies("10.0.0.0/8, 192.168.1.1,,::1").unwrap();
+        assert_eq!(
+            proxies,
+            vec![
+                "10.0.0.0/8".parse::<IpNet>().unwrap(),
+                "192.168.1.1/32".parse().unwrap(),
+                "::1/128".parse().unwrap(),
+            ]
+        );
+
+        assert!(parse_trusted_proxies("").unwrap().is_empty());
+        assert!(parse_trusted_proxies("proxy.internal").is_err());
+    }
+}
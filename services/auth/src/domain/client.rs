@@ -0,0 +1,92 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+const UNKNOWN: &str = "UNKNOWN";
+
+/// Where a request came from, recorded on the sessions it creates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// `desktop`, `mobile`, `bot` or `other`
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+}
+
+impl ClientInfo {
+    /// Device, browser and OS are parsed from the user agent when it is recognised
+    pub fn new(ip_address: Option<IpAddr>, user_agent: Option<&str>) -> Self {
+        let mut info = Self {
+            ip_address: ip_address.map(|ip| ip.to_string()),
+            user_agent: user_agent.map(str::to_string),
+            ..Default::default()
+        };
+
+        let Some(parsed) = user_agent.and_then(|ua| woothee::parser::Parser::new().parse(ua)) else {
+            return info;
+        };
+
+        info.device = match parsed.category {
+            "pc" => Some("desktop"),
+            "smartphone" | "mobilephone" => Some("mobile"),
+            "crawler" => Some("bot"),
+            UNKNOWN => None,
+            _ => Some("other"),
+        }
+        .map(str::to_string);
+
+        if parsed.name != UNKNOWN {
+            info.browser = Some(match parsed.version.split('.').next() {
+                Some(major) if major.starts_with(|c: char| c.is_ascii_digit()) => {
+                    format!("{} {}", parsed.name, major)
+                }
+                _ => parsed.name.to_string(),
+            });
+        }
+
+        if parsed.os != UNKNOWN {
+            info.os = Some(parsed.os.to_string());
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_user_agent() {
+        let info = ClientInfo::new(
+            Some("203.0.113.7".parse().unwrap()),
+            Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"),
+        );
+
+        assert_eq!(info.ip_address.as_deref(), Some("203.0.113.7"));
+        assert_eq!(info.device.as_deref(), Some("desktop"));
+        assert_eq!(info.browser.as_deref(), Some("Chrome 120"));
+        assert_eq!(info.os.as_deref(), Some("Mac OSX"));
+
+        let phone = ClientInfo::new(
+            None,
+            Some("Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1"),
+        );
+
+        assert_eq!(phone.device.as_deref(), Some("mobile"));
+        assert_eq!(phone.browser.as_deref(), Some("Safari 17"));
+        assert_eq!(phone.os.as_deref(), Some("iPhone"));
+    }
+
+    #[test]
+    fn test_unrecognised_user_agent_is_kept_verbatim() {
+        let info = ClientInfo::new(None, Some("merco-mobile/2.3.1"));
+
+        assert_eq!(info.user_agent.as_deref(), Some("merco-mobile/2.3.1"));
+        assert_eq!(info.device, None);
+        assert_eq!(info.browser, None);
+        assert_eq!(info.os, None);
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// An account at a social provider that signs in as a user
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+    /// Provider name as configured in the project's settings, e.g. `github`
+    pub provider: String,
+    /// The provider's id for the account
+    pub provider_id: String,
+    pub email: Option<String>,
+    /// Latest normalised profile the provider returned
+    pub identity_data: serde_json::Value,
+    pub last_signin_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Identity {
+    pub fn new(
+        project_id: Uuid,
+        user_id: Uuid,
+        provider: String,
+        provider_id: String,
+        email: Option<String>,
+        identity_data: serde_json::Value,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            user_id,
+            provider,
+            provider_id,
+            email,
+            identity_data,
+            last_signin_at: Some(now),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Record a sign-in along with the profile the provider returned for it
+    pub fn record_signin(&mut self, email: Option<String>, identity_data: serde_json::Value) {
+        let now = Utc::now();
+        self.email = email;
+        self.identity_data = identity_data;
+        self.last_signin_at = Some(now);
+        self.updated_at = now;
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A sign-in link emailed to a user
+#[derive(Debug, Clone)]
+pub struct MagicLink {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub email: String,
+    /// SHA-256 of the token in the link; the token itself is never stored
+    pub token_hash: String,
+    /// S256 challenge from the requesting browser, which must present the verifier
+    pub code_challenge: Option<String>,
+    /// Where the link sends the user, already checked against the project allowlist
+    pub redirect_to: Option<String>,
+    /// Set for cross-device links: hash of the token the requesting client polls with.
+    /// Opening such a link only approves the request; the poller gets the session.
+    pub poll_token_hash: Option<String>,
+    /// Set for cross-device links: hash of the code shown on the requesting device,
+    /// which must be entered to approve. Without it, someone could request a link for
+    /// another person's address and collect the session once they open it.
+    pub match_code_hash: Option<String>,
+    /// Approvals tried with this link; it is burned once too many had the wrong code
+    pub approval_attempts: i32,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub used: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MagicLink {
+    pub fn new(
+        project_id: Uuid,
+        email: String,
+        token_hash: String,
+        code_challenge: Option<String>,
+        redirect_to: Option<String>,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            email,
+            token_hash,
+            code_challenge,
+            redirect_to,
+            poll_token_hash: None,
+            match_code_hash: None,
+            approval_attempts: 0,
+            approved_at: None,
+            expires_at,
+            used: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Make this a cross-device link completed by whoever holds the poll token, once
+    /// approved with the matching code
+    pub fn with_poll_token_hash(mut self, poll_token_hash: String, match_code_hash: String) -> Self {
+        self.poll_token_hash = Some(poll_token_hash);
+        self.match_code_hash = Some(match_code_hash);
+        self
+    }
+
+    pub fn is_cross_device(&self) -> bool {
+        self.poll_token_hash.is_some()
+    }
+
+    pub fn is_usable(&self) -> bool {
+        !self.used && Utc::now() <= self.expires_at
+    }
+}
@@ -2,8 +2,29 @@ pub mod user;
 pub mod session;
 pub mod role;
 pub mod token;
+pub mod signing_key;
+pub mod project;
+pub mod revoked_token;
+pub mod client;
+pub mod otp;
+pub mod magic_link;
+pub mod sms;
+pub mod oauth;
+pub mod identity;
 
 pub use user::User;
 pub use session::Session;
-pub use role::{Role, Permission};
-pub use token::{AccessToken, RefreshToken};
+pub use role::{flatten_permissions, permissions_version, Role, Permission};
+pub use token::{AccessToken, RefreshToken, TokenPair};
+pub use signing_key::{SigningKeyRecord, SigningKeyStatus};
+pub use project::{
+    ClaimsHookSettings, JwtSettings, OAuthProviderSettings, Project, ProjectSettings, RedirectSettings,
+    RetiringJwtSecret, SessionLimitPolicy, SessionSettings, SmsRoute, SmsSettings, TokenSettings,
+};
+pub use revoked_token::RevokedToken;
+pub use client::ClientInfo;
+pub use otp::{OtpChannel, OtpCode};
+pub use magic_link::MagicLink;
+pub use sms::SmsDelivery;
+pub use oauth::OAuthState;
+pub use identity::Identity;
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// An authorization request waiting for the provider to send the user back
+#[derive(Debug, Clone)]
+pub struct OAuthState {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// Provider name as configured in the project's settings
+    pub provider: String,
+    /// SHA-256 of the `state` parameter sent to the provider
+    pub state_hash: String,
+    /// PKCE verifier whose challenge went to the provider
+    pub code_verifier: String,
+    /// Where the user goes once signed in, already checked against the project allowlist
+    pub redirect_to: String,
+    /// The signed-in user who asked to link the provider account, if any
+    pub user_id: Option<Uuid>,
+    /// Sent to OpenID Connect providers, which must echo it in the id_token
+    pub nonce: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthState {
+    pub fn new(
+        project_id: Uuid,
+        provider: String,
+        state_hash: String,
+        code_verifier: String,
+        redirect_to: String,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            provider,
+            state_hash,
+            code_verifier,
+            redirect_to,
+            user_id: None,
+            nonce: None,
+            expires_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Link the provider account to this user instead of signing in with it
+    pub fn with_user_id(mut self, user_id: Option<Uuid>) -> Self {
+        self.user_id = user_id;
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: Option<String>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::error::AuthError;
+
+/// How a one-time code reaches the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtpChannel {
+    Email,
+    Sms,
+}
+
+impl OtpChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpChannel::Email => "email",
+            OtpChannel::Sms => "sms",
+        }
+    }
+}
+
+impl FromStr for OtpChannel {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "email" => Ok(OtpChannel::Email),
+            "sms" => Ok(OtpChannel::Sms),
+            other => Err(AuthError::InvalidInput(format!("unknown OTP channel '{}'", other))),
+        }
+    }
+}
+
+/// A sign-in code sent to an email address or phone number
+#[derive(Debug, Clone)]
+pub struct OtpCode {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// Email address or phone number the code was sent to
+    pub identifier: String,
+    /// See `OtpService::hash_code`; the code itself is never stored
+    pub code_hash: String,
+    pub channel: OtpChannel,
+    pub expires_at: DateTime<Utc>,
+    pub used: bool,
+    pub created_at: DateTime<Utc>,
+    /// Verification attempts made against this code
+    pub attempts: i32,
+}
+
+impl OtpCode {
+    pub fn new(
+        project_id: Uuid,
+        channel: OtpChannel,
+        identifier: String,
+        code_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            identifier,
+            code_hash,
+            channel,
+            expires_at,
+            used: false,
+            created_at: Utc::now(),
+            attempts: 0,
+        }
+    }
+
+    /// Unused and unexpired; whether attempts remain is up to the caller's limit
+    pub fn is_usable(&self) -> bool {
+        !self.used && Utc::now() <= self.expires_at
+    }
+}
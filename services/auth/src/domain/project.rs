@@ -0,0 +1,308 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+use uuid::Uuid;
+
+use crate::error::AuthError;
+
+/// A project as seen by the auth service
+#[derive(Clone)]
+pub struct Project {
+    pub id: Uuid,
+    pub name: String,
+    pub settings: ProjectSettings,
+    /// Project-specific HMAC secret for access tokens; the service keyring signs when unset
+    pub jwt_secret: Option<String>,
+    /// Signer before `jwt_secret` last changed, still accepted for tokens it issued
+    pub retiring_jwt_secret: Option<RetiringJwtSecret>,
+}
+
+/// A project's previous access token signer, accepted until `expires_at`
+#[derive(Clone)]
+pub struct RetiringJwtSecret {
+    /// `None` when the service keyring signed before
+    pub secret: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Project {
+    pub fn new(id: Uuid, name: String) -> Self {
+        Self {
+            id,
+            name,
+            settings: ProjectSettings::default(),
+            jwt_secret: None,
+            retiring_jwt_secret: None,
+        }
+    }
+}
+
+/// Typed view of `projects.settings`; keys the auth service does not use are ignored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    #[serde(default)]
+    pub jwt: JwtSettings,
+    #[serde(default)]
+    pub claims_hook: ClaimsHookSettings,
+    #[serde(default)]
+    pub sessions: SessionSettings,
+    #[serde(default)]
+    pub redirects: RedirectSettings,
+    #[serde(default)]
+    pub sms: SmsSettings,
+    /// Social sign-in providers by name, e.g. `google` or `github`
+    #[serde(default)]
+    pub oauth: BTreeMap<String, OAuthProviderSettings>,
+}
+
+impl ProjectSettings {
+    /// Parse stored settings, falling back to defaults if they are malformed
+    pub fn from_value(project_id: Uuid, value: serde_json::Value) -> Self {
+        serde_json::from_value(value).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring malformed settings for project {}: {}", project_id, e);
+            Self::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JwtSettings {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Can shorten, but not extend, the service-wide `JWT_EXPIRY_SECONDS`
+    pub access_token_ttl_seconds: Option<u64>,
+    pub refresh_token_ttl_seconds: Option<u64>,
+}
+
+/// Project-specific claims added to access tokens when they are minted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClaimsHookSettings {
+    /// Claims rendered from the user's admin-set `app_metadata`; a string value such as
+    /// `"{{ app_metadata.org.id }}"` is replaced by the value at that path
+    pub template: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Endpoint whose `claims` response object is merged over the template
+    pub url: Option<String>,
+    /// Key for the HMAC-SHA256 signature sent with each callout
+    pub secret: Option<String>,
+    pub timeout_ms: Option<u64>,
+    /// Mint tokens without the callout's claims when it fails, instead of refusing to
+    #[serde(default)]
+    pub fail_open: bool,
+}
+
+/// Limits on how long and how many sessions a user may hold; unset means unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSettings {
+    /// End sessions with no refresh or authenticated request for this long
+    pub idle_timeout_seconds: Option<u64>,
+    /// End sessions this long after sign-in, however often they are refreshed
+    pub max_lifetime_seconds: Option<u64>,
+    /// Active sessions allowed per user
+    pub max_concurrent: Option<usize>,
+    #[serde(default)]
+    pub on_limit: SessionLimitPolicy,
+}
+
+/// What signing in does once a user holds `max_concurrent` sessions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionLimitPolicy {
+    /// End the user's oldest sessions to make room
+    #[default]
+    EvictOldest,
+    /// Refuse the new session
+    RejectNew,
+}
+
+/// Where emailed links may send users
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedirectSettings {
+    /// The project's app; links go here unless the request names an allowed redirect
+    pub site_url: Option<String>,
+    /// Further redirect targets. An entry matches a URL with the same origin and path;
+    /// one ending in `*` matches any URL on its origin under that prefix.
+    #[serde(default)]
+    pub allowed_urls: Vec<String>,
+}
+
+impl RedirectSettings {
+    /// The URL a link should send the user to: `redirect_to` if the project allows it,
+    /// otherwise the site URL
+    pub fn resolve(&self, redirect_to: Option<&str>) -> Result<Url, AuthError> {
+        let site_url = self.site_url.as_deref().and_then(|site| Url::parse(site).ok());
+
+        let Some(redirect_to) = redirect_to else {
+            return site_url
+                .ok_or_else(|| AuthError::InvalidInput("project has no site URL configured".to_string()));
+        };
+
+        let url = Url::parse(redirect_to).map_err(|_| AuthError::RedirectNotAllowed)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AuthError::RedirectNotAllowed);
+        }
+
+        let same_site = site_url.is_some_and(|site| site.origin() == url.origin());
+        if same_site || self.allowed_urls.iter().any(|pattern| url_matches(pattern, &url)) {
+            Ok(url)
+        } else {
+            Err(AuthError::RedirectNotAllowed)
+        }
+    }
+}
+
+fn url_matches(pattern: &str, url: &Url) -> bool {
+    let (base, wildcard) = match pattern.strip_suffix('*') {
+        Some(prefix) => (prefix, true),
+        None => (pattern, false),
+    };
+    let Ok(base) = Url::parse(base) else {
+        return false;
+    };
+
+    // Comparing origins first keeps `https://app.example.com*` from matching another host
+    base.origin() == url.origin()
+        && if wildcard {
+            url.path().starts_with(base.path())
+        } else {
+            url.path() == base.path()
+        }
+}
+
+/// Which SMS providers deliver a project's messages. Each provider in the chosen list
+/// is tried in turn until one accepts the message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SmsSettings {
+    /// Provider names in order of preference; empty uses every configured provider
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Provider lists for numbers starting with a prefix such as `+90`; the longest
+    /// matching prefix wins over `providers`
+    #[serde(default)]
+    pub routes: Vec<SmsRoute>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsRoute {
+    pub prefix: String,
+    pub providers: Vec<String>,
+}
+
+impl SmsSettings {
+    /// Providers to try, in order, for a message to `to`
+    pub fn providers_for(&self, to: &str) -> &[String] {
+        self.routes
+            .iter()
+            .filter(|route| to.starts_with(&route.prefix))
+            .max_by_key(|route| route.prefix.len())
+            .map_or(&self.providers, |route| &route.providers)
+    }
+}
+
+/// A project's OAuth client registration with one provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthProviderSettings {
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Scopes to request; empty asks for the provider's defaults
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// OpenID Connect issuer. Its endpoints and signing keys are discovered from
+    /// `/.well-known/openid-configuration`, and the id_tokens it returns are validated.
+    pub issuer: Option<String>,
+    /// Endpoint overrides, required for providers without built-in endpoints or an issuer
+    pub authorization_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Token parameters for one project with the service defaults applied
+#[derive(Clone)]
+pub struct TokenSettings {
+    pub project_id: Uuid,
+    pub issuer: String,
+    pub audience: String,
+    pub access_token_ttl_seconds: u64,
+    pub refresh_token_ttl_seconds: u64,
+    pub signing_secret: Option<String>,
+    /// Previous signer while tokens it issued may still be unexpired
+    pub retiring_secret: Option<RetiringJwtSecret>,
+    pub claims_hook: ClaimsHookSettings,
+    pub sessions: SessionSettings,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirects() -> RedirectSettings {
+        RedirectSettings {
+            site_url: Some("https://app.example.com".to_string()),
+            allowed_urls: vec![
+                "https://admin.example.com/callback".to_string(),
+                "http://localhost:3000/auth/*".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_sms_providers_for_longest_matching_route() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let sms = SmsSettings {
+            providers: names(&["twilio"]),
+            routes: vec![
+                SmsRoute { prefix: "+3".to_string(), providers: names(&["messagebird"]) },
+                SmsRoute { prefix: "+90".to_string(), providers: names(&["vonage", "twilio"]) },
+                SmsRoute { prefix: "+905".to_string(), providers: names(&["webhook"]) },
+            ],
+        };
+
+        assert_eq!(sms.providers_for("+905551234567"), names(&["webhook"]));
+        assert_eq!(sms.providers_for("+902121234567"), names(&["vonage", "twilio"]));
+        assert_eq!(sms.providers_for("+33612345678"), names(&["messagebird"]));
+        assert_eq!(sms.providers_for("+15550100199"), names(&["twilio"]));
+    }
+
+    #[test]
+    fn test_resolve_defaults_to_site_url() {
+        let url = redirects().resolve(None).unwrap();
+        assert_eq!(url.as_str(), "https://app.example.com/");
+
+        let unset = RedirectSettings::default().resolve(None);
+        assert!(matches!(unset, Err(AuthError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_resolve_checks_allowlist() {
+        let redirects = redirects();
+
+        for allowed in [
+            "https://app.example.com/welcome?ref=email",
+            "https://admin.example.com/callback",
+            "http://localhost:3000/auth/confirm",
+        ] {
+            assert_eq!(redirects.resolve(Some(allowed)).unwrap().as_str(), allowed);
+        }
+
+        for rejected in [
+            "https://admin.example.com/callback/extra",
+            "https://app.example.com.evil.test/",
+            "http://localhost:3000/other",
+            "http://localhost:3001/auth/confirm",
+            "javascript:alert(1)",
+            "/relative",
+        ] {
+            assert!(
+                matches!(redirects.resolve(Some(rejected)), Err(AuthError::RedirectNotAllowed)),
+                "{} should be rejected",
+                rejected
+            );
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// An access token rejected before its `exp`, identified by its `jti`
+#[derive(Debug, Clone)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    /// When the token expires on its own and the entry can be dropped
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
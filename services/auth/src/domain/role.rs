@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Permission {
@@ -7,6 +7,12 @@ pub struct Permission {
     pub action: String,
 }
 
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.resource, self.action)
+    }
+}
+
 impl Permission {
     pub fn new(resource: impl Into<String>, action: impl Into<String>) -> Self {
         Self {
@@ -15,10 +21,6 @@ impl Permission {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{}:{}", self.resource, self.action)
-    }
-
     pub fn from_string(s: &str) -> Option<Self> {
         let parts: Vec<&str> = s.split(':').collect();
         if parts.len() == 2 {
@@ -37,10 +39,10 @@ impl Permission {
         }
 
         if let Some(perm) = Permission::from_string(pattern) {
-            if perm.resource == "*" || perm.resource == self.resource {
-                if perm.action == "*" || perm.action == self.action {
-                    return true;
-                }
+            if (perm.resource == "*" || perm.resource == self.resource)
+                && (perm.action == "*" || perm.action == self.action)
+            {
+                return true;
             }
         }
 
@@ -88,6 +90,21 @@ impl Role {
     }
 }
 
+/// Union of every role's permissions as sorted `resource:action` strings
+pub fn flatten_permissions(roles: &[Role]) -> Vec<String> {
+    roles
+        .iter()
+        .flat_map(|role| role.permissions.iter().map(Permission::to_string))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Short digest identifying a permission set, so clients can tell when a cached copy is stale
+pub fn permissions_version(permissions: &[String]) -> String {
+    crate::utils::crypto::hash_token(&permissions.join("\n"))[..16].to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,4 +150,18 @@ mod tests {
         assert!(role.has_permission(&Permission::new("posts", "write")));
         assert!(!role.has_permission(&Permission::new("posts", "delete")));
     }
+
+    #[test]
+    fn test_flatten_permissions_dedupes_across_roles() {
+        let project_id = uuid::Uuid::new_v4();
+        let editor = Role::new(project_id, "editor".to_string())
+            .with_permissions(vec![Permission::new("posts", "write"), Permission::new("posts", "read")]);
+        let viewer = Role::new(project_id, "viewer".to_string())
+            .with_permissions(vec![Permission::new("posts", "read")]);
+
+        let permissions = flatten_permissions(&[editor, viewer]);
+        assert_eq!(permissions, vec!["posts:read", "posts:write"]);
+        assert_eq!(permissions_version(&permissions).len(), 16);
+        assert_ne!(permissions_version(&permissions), permissions_version(&permissions[..1]));
+    }
 }
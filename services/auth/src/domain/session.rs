@@ -1,16 +1,26 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::domain::{AccessToken, ClientInfo, SessionSettings};
+use crate::utils::crypto::hash_token;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub id: String,
     pub user_id: Uuid,
     pub project_id: Uuid,
-    pub access_token: String,
-    pub refresh_token: String,
+    pub family_id: Uuid,
+    pub access_token_hash: String,
+    pub refresh_token_hash: String,
+    /// `jti` of the latest access token, denied when the session is revoked
+    pub access_token_jti: Option<String>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// `desktop`, `mobile`, `bot` or `other`, parsed from the user agent
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub last_active_at: DateTime<Utc>,
@@ -22,21 +32,25 @@ impl Session {
         id: String,
         user_id: Uuid,
         project_id: Uuid,
-        access_token: String,
-        refresh_token: String,
+        access_token: &AccessToken,
+        refresh_token: &str,
         expires_at: DateTime<Utc>,
-        ip_address: Option<String>,
-        user_agent: Option<String>,
+        client: ClientInfo,
     ) -> Self {
         let now = Utc::now();
         Self {
             id,
             user_id,
             project_id,
-            access_token,
-            refresh_token,
-            ip_address,
-            user_agent,
+            family_id: Uuid::new_v4(),
+            access_token_hash: hash_token(&access_token.token),
+            refresh_token_hash: hash_token(refresh_token),
+            access_token_jti: Some(access_token.jti.clone()),
+            ip_address: client.ip_address,
+            user_agent: client.user_agent,
+            device: client.device,
+            browser: client.browser,
+            os: client.os,
             created_at: now,
             expires_at,
             last_active_at: now,
@@ -44,10 +58,35 @@ impl Session {
         }
     }
 
+    /// Replace the session tokens; only their hashes are kept
+    pub fn rotate_tokens(&mut self, access_token: &AccessToken, refresh_token: &str) {
+        self.access_token_hash = hash_token(&access_token.token);
+        self.refresh_token_hash = hash_token(refresh_token);
+        self.access_token_jti = Some(access_token.jti.clone());
+    }
+
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at || self.revoked
     }
 
+    /// Not expired, and within the project's idle timeout and absolute lifetime
+    pub fn is_active(&self, limits: &SessionSettings) -> bool {
+        let now = Utc::now();
+        let idle = limits
+            .idle_timeout_seconds
+            .is_some_and(|timeout| now > self.last_active_at + Duration::seconds(timeout as i64));
+        let outlived = self.lifetime_ends_at(limits).is_some_and(|end| now > end);
+
+        !self.is_expired() && !idle && !outlived
+    }
+
+    /// When the project's absolute lifetime ends the session, if it sets one
+    pub fn lifetime_ends_at(&self, limits: &SessionSettings) -> Option<DateTime<Utc>> {
+        limits
+            .max_lifetime_seconds
+            .map(|lifetime| self.created_at + Duration::seconds(lifetime as i64))
+    }
+
     pub fn update_last_active(&mut self) {
         self.last_active_at = Utc::now();
     }
@@ -61,6 +100,10 @@ impl Session {
 mod tests {
     use super::*;
 
+    fn access_token() -> AccessToken {
+        AccessToken::new("access_token".to_string(), "jti_123".to_string(), 3600)
+    }
+
     #[test]
     fn test_session_creation() {
         let user_id = Uuid::new_v4();
@@ -71,14 +114,16 @@ mod tests {
             "sess_123".to_string(),
             user_id,
             project_id,
-            "access_token".to_string(),
-            "refresh_token".to_string(),
+            &access_token(),
+            "refresh_token",
             expires_at,
-            Some("127.0.0.1".to_string()),
-            Some("Mozilla/5.0".to_string()),
+            ClientInfo::new(Some("127.0.0.1".parse().unwrap()), Some("Mozilla/5.0")),
         );
 
         assert_eq!(session.user_id, user_id);
+        assert_eq!(session.refresh_token_hash, hash_token("refresh_token"));
+        assert_eq!(session.access_token_jti.as_deref(), Some("jti_123"));
+        assert_eq!(session.ip_address.as_deref(), Some("127.0.0.1"));
         assert!(!session.is_expired());
         assert!(!session.revoked);
     }
@@ -93,11 +138,10 @@ mod tests {
             "sess_123".to_string(),
             user_id,
             project_id,
-            "access_token".to_string(),
-            "refresh_token".to_string(),
+            &access_token(),
+            "refresh_token",
             expires_at,
-            None,
-            None,
+            ClientInfo::default(),
         );
 
         assert!(session.is_expired());
@@ -113,11 +157,10 @@ mod tests {
             "sess_123".to_string(),
             user_id,
             project_id,
-            "access_token".to_string(),
-            "refresh_token".to_string(),
+            &access_token(),
+            "refresh_token",
             expires_at,
-            None,
-            None,
+            ClientInfo::default(),
         );
 
         assert!(!session.revoked);
@@ -125,4 +168,34 @@ mod tests {
         assert!(session.revoked);
         assert!(session.is_expired());
     }
+
+    #[test]
+    fn test_session_limits() {
+        let mut session = Session::new(
+            "sess_123".to_string(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            &access_token(),
+            "refresh_token",
+            Utc::now() + Duration::days(30),
+            ClientInfo::default(),
+        );
+        let limits = SessionSettings {
+            idle_timeout_seconds: Some(1800),
+            max_lifetime_seconds: Some(86400),
+            ..Default::default()
+        };
+
+        assert!(session.is_active(&limits));
+
+        session.last_active_at = Utc::now() - Duration::hours(1);
+        assert!(!session.is_active(&limits));
+        assert!(session.is_active(&SessionSettings::default()));
+
+        // Recent activity does not extend the absolute lifetime
+        session.created_at = Utc::now() - Duration::days(2);
+        session.update_last_active();
+        assert!(!session.is_active(&limits));
+        assert_eq!(session.lifetime_ends_at(&limits), Some(session.created_at + Duration::days(1)));
+    }
 }
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A signing key created through rotation.
+/// `private_key` holds a PEM for asymmetric algorithms and a base64 secret for HMAC.
+#[derive(Clone)]
+pub struct SigningKeyRecord {
+    pub kid: String,
+    pub algorithm: String,
+    pub private_key: String,
+    pub created_at: DateTime<Utc>,
+    /// When the key starts signing; until then it is only published for verification
+    pub activates_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningKeyStatus {
+    /// Published in the JWKS, not signing yet
+    Pending,
+    /// Signing new access tokens
+    Current,
+    /// Superseded, still accepted until retired
+    Verifying,
+    Retired,
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Statuses after which a provider reports nothing more about a message
+pub const FINAL_SMS_STATUSES: &[&str] = &["delivered", "undelivered", "failed", "canceled"];
+
+/// A message handed to an SMS provider
+#[derive(Debug, Clone)]
+pub struct SmsDelivery {
+    pub id: Uuid,
+    /// Provider name, e.g. `twilio`
+    pub provider: String,
+    /// The provider's id for the message, which its status callbacks refer to
+    pub provider_message_id: String,
+    pub recipient: String,
+    /// Latest status in the provider's vocabulary
+    pub status: String,
+    pub error_code: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SmsDelivery {
+    pub fn new(provider: &str, provider_message_id: String, recipient: String, status: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            provider: provider.to_string(),
+            provider_message_id,
+            recipient,
+            status,
+            error_code: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -3,12 +3,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessToken {
     pub token: String,
+    /// The token's `jti` claim, used to revoke it before it expires
+    pub jti: String,
     pub expires_in: u64,
 }
 
 impl AccessToken {
-    pub fn new(token: String, expires_in: u64) -> Self {
-        Self { token, expires_in }
+    pub fn new(token: String, jti: String, expires_in: u64) -> Self {
+        Self { token, jti, expires_in }
     }
 }
 
@@ -23,3 +25,10 @@ impl RefreshToken {
         Self { token, expires_in }
     }
 }
+
+/// Tokens handed to the client once; sessions only keep their hashes
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: AccessToken,
+    pub refresh_token: RefreshToken,
+}
@@ -7,12 +7,14 @@ use validator::Validate;
 pub struct User {
     pub id: Uuid,
     pub project_id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
     pub password_hash: Option<String>,
     pub metadata: serde_json::Value,
+    /// Set only through the admin API, so unlike `metadata` it can back token claims
+    pub app_metadata: serde_json::Value,
     pub mfa_enabled: bool,
     pub mfa_secret: Option<String>,
     pub mfa_backup_codes: Option<Vec<String>>,
@@ -24,16 +26,32 @@ pub struct User {
 
 impl User {
     pub fn new(project_id: Uuid, email: String) -> Self {
+        Self {
+            email: Some(email.to_lowercase()),
+            ..Self::blank(project_id)
+        }
+    }
+
+    /// A user known only by phone number, as created by SMS sign-in
+    pub fn with_phone_only(project_id: Uuid, phone: String) -> Self {
+        Self {
+            phone: Some(phone),
+            ..Self::blank(project_id)
+        }
+    }
+
+    fn blank(project_id: Uuid) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
             project_id,
-            email: email.to_lowercase(),
+            email: None,
             email_verified: false,
             phone: None,
             phone_verified: false,
             password_hash: None,
             metadata: serde_json::json!({}),
+            app_metadata: serde_json::json!({}),
             mfa_enabled: false,
             mfa_secret: None,
             mfa_backup_codes: None,
@@ -54,6 +72,11 @@ impl User {
         self
     }
 
+    pub fn set_app_metadata(&mut self, app_metadata: serde_json::Value) {
+        self.app_metadata = app_metadata;
+        self.updated_at = Utc::now();
+    }
+
     pub fn verify_email(&mut self) {
         self.email_verified = true;
         self.updated_at = Utc::now();
@@ -89,7 +112,7 @@ mod tests {
         let project_id = Uuid::new_v4();
         let user = User::new(project_id, "test@example.com".to_string());
         
-        assert_eq!(user.email, "test@example.com");
+        assert_eq!(user.email.as_deref(), Some("test@example.com"));
         assert!(!user.email_verified);
         assert!(!user.banned);
     }
@@ -99,7 +122,7 @@ mod tests {
         let project_id = Uuid::new_v4();
         let user = User::new(project_id, "Test@Example.COM".to_string());
         
-        assert_eq!(user.email, "test@example.com");
+        assert_eq!(user.email.as_deref(), Some("test@example.com"));
     }
 
     #[test]
@@ -41,6 +41,53 @@ pub struct VerifyOtpRequest {
 pub struct SendMagicLinkRequest {
     #[validate(email)]
     pub email: String,
+    /// Must be on the project's redirect allowlist; defaults to the site URL
+    pub redirect_to: Option<String>,
+    /// S256 challenge for a verifier the requesting browser keeps
+    pub code_challenge: Option<String>,
+    /// Let the link be opened on another device; the response carries a poll token
+    #[serde(default)]
+    pub cross_device: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyMagicLinkRequest {
+    pub token: String,
+    /// Required when the link was requested with a `code_challenge`
+    pub code_verifier: Option<String>,
+}
+
+/// Code exchange for apps that ran the provider's consent screen themselves
+#[derive(Debug, Deserialize, Validate)]
+pub struct OAuthTokenRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+    /// When linking, the `state` our callback handed back with the code; the request
+    /// it belongs to supplies the redirect URI, PKCE verifier and nonce
+    pub state: Option<String>,
+    /// The redirect URI the code was issued to; defaults to our callback
+    pub redirect_uri: Option<String>,
+    /// Required when the app sent a PKCE challenge
+    pub code_verifier: Option<String>,
+    /// The nonce the app sent an OpenID Connect provider, checked against the id_token
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ApproveMagicLinkRequest {
+    pub token: String,
+    /// The match code shown on the device that requested the link
+    #[validate(length(min = 1, max = 10))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PollMagicLinkRequest {
+    pub poll_token: String,
+    /// Required when the link was requested with a `code_challenge`
+    pub code_verifier: Option<String>,
+    /// Hold the request open this long waiting for approval
+    pub wait_seconds: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -69,6 +116,12 @@ pub struct UpdateUserRequest {
     pub phone: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AdminUpdateUserRequest {
+    /// Replaces the user's `app_metadata`, which token claims templates read from
+    pub app_metadata: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ChangeEmailRequest {
     #[validate(email)]
@@ -109,3 +162,20 @@ pub struct CreateApiKeyRequest {
     pub name: String,
     pub project_id: uuid::Uuid,
 }
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateSigningKeyRequest {
+    /// Defaults to `JWT_ALGORITHM`
+    pub algorithm: Option<String>,
+    /// PEM private key; generated when omitted (required for RSA)
+    pub private_key: Option<String>,
+    pub kid: Option<String>,
+}
+
+/// Form body of token introspection (RFC 7662) and revocation (RFC 7009) requests
+#[derive(Debug, Deserialize)]
+pub struct TokenHintRequest {
+    pub token: String,
+    /// `access_token` or `refresh_token`
+    pub token_type_hint: Option<String>,
+}
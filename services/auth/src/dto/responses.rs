@@ -1,7 +1,8 @@
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::domain::{Session, User};
+use crate::domain::{Identity, Role, Session, SigningKeyRecord, SigningKeyStatus, TokenPair, User};
+use crate::services::IntrospectedToken;
 
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
@@ -11,13 +12,13 @@ pub struct AuthResponse {
     pub expires_in: u64,
 }
 
-impl From<(User, Session)> for AuthResponse {
-    fn from((user, session): (User, Session)) -> Self {
+impl From<(User, TokenPair)> for AuthResponse {
+    fn from((user, tokens): (User, TokenPair)) -> Self {
         Self {
             user: UserResponse::from(user),
-            access_token: session.access_token,
-            refresh_token: session.refresh_token,
-            expires_in: 3600, // TODO: Calculate from expiry
+            access_token: tokens.access_token.token,
+            refresh_token: tokens.refresh_token.token,
+            expires_in: tokens.access_token.expires_in,
         }
     }
 }
@@ -25,11 +26,12 @@ impl From<(User, Session)> for AuthResponse {
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
     pub metadata: serde_json::Value,
+    pub app_metadata: serde_json::Value,
     pub mfa_enabled: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
@@ -44,6 +46,7 @@ impl From<User> for UserResponse {
             phone: user.phone,
             phone_verified: user.phone_verified,
             metadata: user.metadata,
+            app_metadata: user.app_metadata,
             mfa_enabled: user.mfa_enabled,
             created_at: user.created_at,
             updated_at: user.updated_at,
@@ -58,6 +61,9 @@ pub struct SessionResponse {
     pub last_active: chrono::DateTime<chrono::Utc>,
     pub ip: Option<String>,
     pub user_agent: Option<String>,
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
     pub current: bool,
 }
 
@@ -69,6 +75,9 @@ impl From<(Session, bool)> for SessionResponse {
             last_active: session.last_active_at,
             ip: session.ip_address,
             user_agent: session.user_agent,
+            device: session.device,
+            browser: session.browser,
+            os: session.os,
             current,
         }
     }
@@ -79,6 +88,37 @@ pub struct SessionsResponse {
     pub sessions: Vec<SessionResponse>,
 }
 
+/// A provider account linked to the signed-in user
+#[derive(Debug, Serialize)]
+pub struct IdentityResponse {
+    pub id: Uuid,
+    pub provider: String,
+    pub provider_id: String,
+    pub email: Option<String>,
+    pub identity_data: serde_json::Value,
+    pub last_signin_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Identity> for IdentityResponse {
+    fn from(identity: Identity) -> Self {
+        Self {
+            id: identity.id,
+            provider: identity.provider,
+            provider_id: identity.provider_id,
+            email: identity.email,
+            identity_data: identity.identity_data,
+            last_signin_at: identity.last_signin_at,
+            created_at: identity.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentitiesResponse {
+    pub identities: Vec<IdentityResponse>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct OtpSentResponse {
     pub message: String,
@@ -87,6 +127,19 @@ pub struct OtpSentResponse {
 #[derive(Debug, Serialize)]
 pub struct MagicLinkSentResponse {
     pub message: String,
+    /// Only for cross-device links; poll `/magic-link/poll` with it to get the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_token: Option<String>,
+    /// Only for cross-device links; show it on this device, to be entered where the
+    /// link is opened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_code: Option<String>,
+}
+
+/// Returned with 202 while a cross-device magic link has not been opened
+#[derive(Debug, Serialize)]
+pub struct MagicLinkPendingResponse {
+    pub status: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -104,6 +157,20 @@ pub struct RoleResponse {
     pub permissions: Vec<String>,
 }
 
+impl From<Role> for RoleResponse {
+    fn from(role: Role) -> Self {
+        let mut permissions: Vec<String> = role.permissions.iter().map(|p| p.to_string()).collect();
+        permissions.sort();
+
+        Self {
+            id: role.id,
+            name: role.name,
+            description: role.description,
+            permissions,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct RolesResponse {
     pub roles: Vec<RoleResponse>,
@@ -112,6 +179,14 @@ pub struct RolesResponse {
 #[derive(Debug, Serialize)]
 pub struct PermissionsResponse {
     pub permissions: Vec<String>,
+    /// Matches the `perms_ver` claim of tokens issued for this permission set
+    pub version: String,
+}
+
+/// The provider consent page to send the browser to
+#[derive(Debug, Serialize)]
+pub struct OAuthAuthorizeResponse {
+    pub url: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -145,3 +220,93 @@ pub struct ApiKeyListItem {
     pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
     pub is_active: bool,
 }
+
+#[derive(Debug, Serialize)]
+pub struct SigningKeyResponse {
+    pub kid: String,
+    pub algorithm: String,
+    pub status: SigningKeyStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub activates_at: chrono::DateTime<chrono::Utc>,
+    pub retired_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<(SigningKeyRecord, SigningKeyStatus)> for SigningKeyResponse {
+    fn from((key, status): (SigningKeyRecord, SigningKeyStatus)) -> Self {
+        Self {
+            kid: key.kid,
+            algorithm: key.algorithm,
+            status,
+            created_at: key.created_at,
+            activates_at: key.activates_at,
+            retired_at: key.retired_at,
+        }
+    }
+}
+
+/// RFC 7662 introspection result; inactive tokens carry no other members
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub perms_ver: Option<String>,
+    /// Project claims from the claims hook
+    #[serde(flatten)]
+    pub custom: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<Option<IntrospectedToken>> for IntrospectionResponse {
+    fn from(token: Option<IntrospectedToken>) -> Self {
+        match token {
+            None => Self::default(),
+            Some(IntrospectedToken::Access { claims, session: _ }) => {
+                let claims = *claims;
+                Self {
+                    active: true,
+                    token_type: Some("access_token".to_string()),
+                    sub: Some(claims.sub),
+                    iss: Some(claims.iss),
+                    aud: Some(claims.aud),
+                    exp: Some(claims.exp),
+                    iat: Some(claims.iat),
+                    project_id: Some(claims.project_id),
+                    sid: Some(claims.sid),
+                    roles: Some(claims.roles),
+                    permissions: Some(claims.permissions),
+                    perms_ver: claims.perms_ver,
+                    custom: claims.custom,
+                }
+            }
+            Some(IntrospectedToken::Refresh { session }) => Self {
+                active: true,
+                token_type: Some("refresh_token".to_string()),
+                sub: Some(session.user_id),
+                exp: Some(session.expires_at.timestamp()),
+                iat: Some(session.created_at.timestamp()),
+                project_id: Some(session.project_id),
+                sid: Some(session.id),
+                ..Self::default()
+            },
+        }
+    }
+}
@@ -23,6 +23,9 @@ pub enum AuthError {
     #[error("Invalid token")]
     InvalidToken,
 
+    #[error("Refresh token reuse detected")]
+    TokenReused,
+
     #[error("Unauthorized")]
     Unauthorized,
 
@@ -65,6 +68,24 @@ pub enum AuthError {
     #[error("Session not found")]
     SessionNotFound,
 
+    #[error("Session limit reached")]
+    SessionLimitReached,
+
+    #[error("Code does not match the one shown on the requesting device")]
+    MagicLinkCodeMismatch,
+
+    #[error("Redirect URL not allowed")]
+    RedirectNotAllowed,
+
+    #[error("Identity not found")]
+    IdentityNotFound,
+
+    #[error("Identity is already linked to a user")]
+    IdentityAlreadyLinked,
+
+    #[error("Cannot remove the last way to sign in")]
+    LastSignInMethod,
+
     #[error("Role not found")]
     RoleNotFound,
 
@@ -80,6 +101,15 @@ pub enum AuthError {
     #[error("API key expired")]
     ApiKeyExpired,
 
+    #[error("Signing key not found")]
+    SigningKeyNotFound,
+
+    #[error("Signing key configuration error: {0}")]
+    KeyConfiguration(String),
+
+    #[error("Claims hook failed: {0}")]
+    ClaimsHook(String),
+
     #[error("Internal server error")]
     Internal,
 }
@@ -90,14 +120,16 @@ pub struct ErrorResponse {
     pub message: String,
 }
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> Response {
-        let (status, code) = match &self {
+impl AuthError {
+    /// HTTP status and machine-readable code reported for this error
+    pub fn status_and_code(&self) -> (StatusCode, &'static str) {
+        match self {
             AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "invalid_credentials"),
             AuthError::UserExists => (StatusCode::CONFLICT, "user_exists"),
             AuthError::UserNotFound => (StatusCode::NOT_FOUND, "user_not_found"),
             AuthError::TokenExpired => (StatusCode::UNAUTHORIZED, "token_expired"),
             AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            AuthError::TokenReused => (StatusCode::UNAUTHORIZED, "token_reused"),
             AuthError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
             AuthError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
             AuthError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "invalid_input"),
@@ -111,14 +143,29 @@ impl IntoResponse for AuthError {
             AuthError::MfaRequired => (StatusCode::UNAUTHORIZED, "mfa_required"),
             AuthError::MfaInvalid => (StatusCode::UNAUTHORIZED, "mfa_invalid"),
             AuthError::SessionNotFound => (StatusCode::NOT_FOUND, "session_not_found"),
+            AuthError::SessionLimitReached => (StatusCode::CONFLICT, "session_limit_reached"),
+            AuthError::MagicLinkCodeMismatch => (StatusCode::BAD_REQUEST, "magic_link_code_mismatch"),
+            AuthError::RedirectNotAllowed => (StatusCode::BAD_REQUEST, "redirect_not_allowed"),
+            AuthError::IdentityNotFound => (StatusCode::NOT_FOUND, "identity_not_found"),
+            AuthError::IdentityAlreadyLinked => (StatusCode::CONFLICT, "identity_already_linked"),
+            AuthError::LastSignInMethod => (StatusCode::CONFLICT, "last_sign_in_method"),
             AuthError::RoleNotFound => (StatusCode::NOT_FOUND, "role_not_found"),
             AuthError::PermissionDenied => (StatusCode::FORBIDDEN, "permission_denied"),
             AuthError::ProjectNotFound => (StatusCode::NOT_FOUND, "project_not_found"),
             AuthError::InvalidApiKey => (StatusCode::UNAUTHORIZED, "invalid_api_key"),
             AuthError::ApiKeyExpired => (StatusCode::UNAUTHORIZED, "api_key_expired"),
             AuthError::Validation(_) => (StatusCode::BAD_REQUEST, "validation_error"),
+            AuthError::SigningKeyNotFound => (StatusCode::NOT_FOUND, "signing_key_not_found"),
+            AuthError::KeyConfiguration(_) => (StatusCode::INTERNAL_SERVER_ERROR, "key_configuration_error"),
+            AuthError::ClaimsHook(_) => (StatusCode::BAD_GATEWAY, "claims_hook_failed"),
             AuthError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
-        };
+        }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, code) = self.status_and_code();
 
         let body = Json(ErrorResponse {
             error: code.to_string(),
@@ -6,9 +6,15 @@ use axum::{
 use serde::Deserialize;
 use sqlx::PgPool;
 
-use crate::dto::{CreateApiKeyRequest, CreateApiKeyResponse, ApiKeyListItem, UserResponse};
+use crate::dto::{
+    AdminUpdateUserRequest, CreateApiKeyRequest, CreateApiKeyResponse, ApiKeyListItem,
+    RotateSigningKeyRequest, SigningKeyResponse, UserResponse,
+};
 use crate::error::AuthError;
 use crate::middleware::ApiKeyContext;
+use crate::repository::traits::ProjectRepository;
+use crate::state::AppState;
+use crate::utils::crypto::generate_random_token;
 use common::ApiKey;
 
 #[derive(Deserialize)]
@@ -37,11 +43,22 @@ pub async fn create_user() -> Result<Json<UserResponse>, AuthError> {
     Err(AuthError::Internal) // Placeholder
 }
 
+/// PATCH /admin/users/{id} - sets the server-controlled `app_metadata`
 pub async fn update_user(
-    Path(_user_id): Path<uuid::Uuid>,
+    State(state): State<AppState>,
+    axum::extract::Extension(context): axum::extract::Extension<ApiKeyContext>,
+    Path(user_id): Path<uuid::Uuid>,
+    Json(req): Json<AdminUpdateUserRequest>,
 ) -> Result<Json<UserResponse>, AuthError> {
-    // TODO: Update user
-    Err(AuthError::Internal) // Placeholder
+    let app_metadata = req
+        .app_metadata
+        .ok_or_else(|| AuthError::InvalidInput("nothing to update".to_string()))?;
+    if !app_metadata.is_object() {
+        return Err(AuthError::InvalidInput("app_metadata must be an object".to_string()));
+    }
+
+    let user = state.auth_service.set_app_metadata(context.project_id, user_id, app_metadata).await?;
+    Ok(Json(user.into()))
 }
 
 pub async fn delete_user(
@@ -51,17 +68,22 @@ pub async fn delete_user(
     Ok(Json(serde_json::json!({ "message": "User deleted" })))
 }
 
+/// POST /admin/users/{id}/ban - also signs the user out everywhere
 pub async fn ban_user(
-    Path(_user_id): Path<uuid::Uuid>,
+    State(state): State<AppState>,
+    axum::extract::Extension(context): axum::extract::Extension<ApiKeyContext>,
+    Path(user_id): Path<uuid::Uuid>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Ban user
+    state.auth_service.set_banned(context.project_id, user_id, true).await?;
     Ok(Json(serde_json::json!({ "message": "User banned" })))
 }
 
 pub async fn unban_user(
-    Path(_user_id): Path<uuid::Uuid>,
+    State(state): State<AppState>,
+    axum::extract::Extension(context): axum::extract::Extension<ApiKeyContext>,
+    Path(user_id): Path<uuid::Uuid>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Unban user
+    state.auth_service.set_banned(context.project_id, user_id, false).await?;
     Ok(Json(serde_json::json!({ "message": "User unbanned" })))
 }
 
@@ -140,3 +162,73 @@ pub async fn revoke_api_key(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// GET /admin/signing-keys
+pub async fn list_signing_keys(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SigningKeyResponse>>, AuthError> {
+    let keys = state.keyring_service.list().await?;
+    Ok(Json(keys.into_iter().map(SigningKeyResponse::from).collect()))
+}
+
+/// POST /admin/signing-keys/rotate
+pub async fn rotate_signing_key(
+    State(state): State<AppState>,
+    req: Option<Json<RotateSigningKeyRequest>>,
+) -> Result<(StatusCode, Json<SigningKeyResponse>), AuthError> {
+    let Json(req) = req.unwrap_or_default();
+    let key = state
+        .keyring_service
+        .rotate(req.algorithm.as_deref(), req.private_key.as_deref(), req.kid)
+        .await?;
+
+    let status = state.keyring_service.status(&key);
+    Ok((StatusCode::CREATED, Json(SigningKeyResponse::from((key, status)))))
+}
+
+/// DELETE /admin/signing-keys/{kid}
+pub async fn retire_signing_key(
+    State(state): State<AppState>,
+    Path(kid): Path<String>,
+) -> Result<Json<SigningKeyResponse>, AuthError> {
+    let key = state.keyring_service.retire(&kid).await?;
+    let status = state.keyring_service.status(&key);
+    Ok(Json(SigningKeyResponse::from((key, status))))
+}
+
+/// POST /admin/projects/{id}/jwt-secret
+/// Give a project its own HS256 signing secret. Tokens from the previous signer keep
+/// verifying for one access token lifetime.
+pub async fn rotate_project_jwt_secret(
+    State(state): State<AppState>,
+    Path(project_id): Path<uuid::Uuid>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    let secret = generate_random_token(64);
+    set_project_jwt_secret(&state, project_id, Some(&secret)).await?;
+
+    // Only time the secret is returned
+    Ok(Json(serde_json::json!({ "secret": secret, "algorithm": "HS256" })))
+}
+
+/// DELETE /admin/projects/{id}/jwt-secret
+/// Go back to signing with the service keys
+pub async fn remove_project_jwt_secret(
+    State(state): State<AppState>,
+    Path(project_id): Path<uuid::Uuid>,
+) -> Result<StatusCode, AuthError> {
+    set_project_jwt_secret(&state, project_id, None).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_project_jwt_secret(state: &AppState, project_id: uuid::Uuid, secret: Option<&str>) -> Result<(), AuthError> {
+    state
+        .repos
+        .project
+        .find_by_id(project_id)
+        .await?
+        .ok_or(AuthError::ProjectNotFound)?;
+
+    let secret = secret.map(|secret| state.token_service.encrypt_secret(secret)).transpose()?;
+    let previous_expires_at = chrono::Utc::now() + state.token_service.max_access_token_ttl();
+    state.repos.project.set_jwt_secret(project_id, secret.as_deref(), previous_expires_at).await
+}
@@ -1,66 +1,81 @@
 use axum::{
-    http::HeaderMap,
+    extract::{Extension, State},
     response::Json,
 };
 use validator::Validate;
 
+use crate::domain::ClientInfo;
 use crate::dto::{SignupRequest, SigninRequest, RefreshTokenRequest, AuthResponse};
 use crate::error::AuthError;
+use crate::middleware::{ApiKeyContext, AuthUser};
+use crate::state::AppState;
 
 pub async fn signup(
-    _headers: HeaderMap,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    client: ClientInfo,
     Json(req): Json<SignupRequest>,
 ) -> Result<Json<AuthResponse>, AuthError> {
     req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
 
-    // TODO: Get project_id from middleware
-    // let project_ctx = request.extensions().get::<ProjectContext>()
-    //     .ok_or(AuthError::ProjectNotFound)?;
-    
-    // TODO: Get auth_service from state
-    // let auth_service = state.auth_service.downcast_ref::<AuthService<...>>()
-    //     .ok_or(AuthError::Internal)?;
+    let (user, _session, tokens) = state.auth_service.signup(
+        context.project_id,
+        &req.email,
+        &req.password,
+        req.metadata,
+        client,
+    ).await?;
 
-    // let (user, session) = auth_service.signup(
-    //     project_ctx.project_id,
-    //     &req.email,
-    //     &req.password,
-    //     req.metadata,
-    // ).await?;
-
-    // Ok(Json(AuthResponse::from((user, session))))
-
-    Err(AuthError::Internal) // Placeholder
+    Ok(Json(AuthResponse::from((user, tokens))))
 }
 
 pub async fn signin(
-    _headers: HeaderMap,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    client: ClientInfo,
     Json(req): Json<SigninRequest>,
 ) -> Result<Json<AuthResponse>, AuthError> {
     req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
 
-    // TODO: Implement signin handler
-    Err(AuthError::Internal) // Placeholder
+    let (user, _session, tokens) = state.auth_service.signin(
+        context.project_id,
+        &req.email,
+        &req.password,
+        client,
+    ).await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))))
 }
 
 pub async fn signout(
-    _headers: HeaderMap,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Get session_id from auth middleware
-    // TODO: Call auth_service.signout
+    state.auth_service.signout(&auth_user.session_id).await?;
+
     Ok(Json(serde_json::json!({ "message": "Signed out successfully" })))
 }
 
 pub async fn refresh_token(
-    Json(_req): Json<RefreshTokenRequest>,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Json(req): Json<RefreshTokenRequest>,
 ) -> Result<Json<AuthResponse>, AuthError> {
-    // TODO: Implement refresh token handler
-    Err(AuthError::Internal) // Placeholder
+    let (user, _session, tokens) = state.auth_service
+        .refresh_token(context.project_id, &req.refresh_token)
+        .await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))))
 }
 
 pub async fn verify_token(
-    _headers: HeaderMap,
+    auth_user: AuthUser,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Verify token from Authorization header
-    Ok(Json(serde_json::json!({ "valid": true })))
+    Ok(Json(serde_json::json!({
+        "valid": true,
+        "user_id": auth_user.user_id,
+        "session_id": auth_user.session_id,
+        "roles": auth_user.roles,
+        "permissions": auth_user.permissions,
+    })))
 }
@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::dto::{IdentitiesResponse, IdentityResponse, OAuthAuthorizeResponse, OAuthTokenRequest};
+use crate::error::AuthError;
+use crate::handlers::oauth::OAuthQuery;
+use crate::middleware::AuthUser;
+use crate::state::AppState;
+
+pub async fn list_identities(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<IdentitiesResponse>, AuthError> {
+    let identities = state.auth_service.list_identities(auth_user.user_id).await?;
+
+    Ok(Json(IdentitiesResponse {
+        identities: identities.into_iter().map(IdentityResponse::from).collect(),
+    }))
+}
+
+/// Start linking a provider account in the browser. The callback sends the browser
+/// back with the provider's `code` and our `state` in the URL fragment, which the app
+/// posts to `link_identity` while signed in as the same user.
+pub async fn authorize_identity(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(provider): Path<String>,
+    Query(query): Query<OAuthQuery>,
+) -> Result<Json<OAuthAuthorizeResponse>, AuthError> {
+    let settings = state.auth_service.project_settings(auth_user.project_id).await?;
+    let url = state.oauth_service
+        .authorize(
+            auth_user.project_id,
+            &settings,
+            &provider,
+            query.redirect_to.as_deref(),
+            Some(auth_user.user_id),
+        )
+        .await?;
+
+    Ok(Json(OAuthAuthorizeResponse { url }))
+}
+
+/// Link a provider account with a code from our callback, or one the app obtained itself
+pub async fn link_identity(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(provider): Path<String>,
+    Json(req): Json<OAuthTokenRequest>,
+) -> Result<Json<IdentityResponse>, AuthError> {
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    let settings = state.auth_service.project_settings(auth_user.project_id).await?;
+    let profile = match &req.state {
+        Some(state_param) => {
+            let pending = state.oauth_service
+                .take_link_state(auth_user.project_id, auth_user.user_id, &provider, state_param)
+                .await?;
+            state.oauth_service
+                .exchange_code(
+                    &settings,
+                    &provider,
+                    &state.oauth_service.redirect_uri(&provider),
+                    &req.code,
+                    Some(&pending.code_verifier),
+                    pending.nonce.as_deref(),
+                )
+                .await?
+        }
+        None => {
+            let redirect_uri = req
+                .redirect_uri
+                .clone()
+                .unwrap_or_else(|| state.oauth_service.redirect_uri(&provider));
+            state.oauth_service
+                .exchange_code(&settings, &provider, &redirect_uri, &req.code, req.code_verifier.as_deref(), req.nonce.as_deref())
+                .await?
+        }
+    };
+
+    let identity = state.auth_service
+        .link_identity(auth_user.project_id, auth_user.user_id, &provider, &profile)
+        .await?;
+
+    Ok(Json(IdentityResponse::from(identity)))
+}
+
+pub async fn unlink_identity(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AuthError> {
+    state.auth_service.unlink_identity(auth_user.user_id, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,9 @@
+use axum::{extract::State, Json};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::state::AppState;
+
+/// Publish the access token verification keys (RFC 7517)
+pub async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.token_service.jwks())
+}
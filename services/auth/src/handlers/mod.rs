@@ -1,6 +1,7 @@
 pub mod auth;
 pub mod user;
 pub mod session;
+pub mod identity;
 pub mod password;
 pub mod passwordless;
 pub mod oauth;
@@ -9,15 +10,7 @@ pub mod rbac;
 pub mod admin;
 pub mod settings;
 pub mod webhooks;
+pub mod jwks;
+pub mod oauth2;
 
-pub use auth::*;
-pub use user::*;
-pub use session::*;
-pub use password::*;
-pub use passwordless::*;
-pub use oauth::*;
-pub use mfa::*;
-pub use rbac::*;
-pub use admin::*;
-pub use settings::*;
-pub use webhooks::*;
+pub mod sms;
@@ -1,44 +1,223 @@
-use axum::{extract::{Path, Query}, response::Json};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    response::{Json, Redirect},
+    Form,
+};
 use serde::Deserialize;
+use url::{form_urlencoded, Url};
+use validator::Validate;
 
-use crate::dto::OAuthProvidersResponse;
+use crate::domain::{ClientInfo, OAuthState};
+use crate::dto::{AuthResponse, OAuthAuthorizeResponse, OAuthProviderInfo, OAuthProvidersResponse, OAuthTokenRequest};
 use crate::error::AuthError;
+use crate::middleware::ApiKeyContext;
+use crate::services::oauth_service::OAuthProvider;
+use crate::services::oauth_userinfo::OAuthUserInfo;
+use crate::state::AppState;
 
 #[derive(Deserialize)]
 pub struct OAuthQuery {
-    pub redirect_uri: Option<String>,
+    /// Must be on the project's redirect allowlist; defaults to the site URL
+    pub redirect_to: Option<String>,
 }
 
+/// What the provider appends to our callback URL, or posts to it
+#[derive(Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Start a browser sign-in. The app navigates to the returned URL itself, since
+/// a navigation cannot carry the API key.
 pub async fn initiate_oauth(
-    Path(_provider): Path<String>,
-    Query(_query): Query<OAuthQuery>,
-) -> Result<axum::response::Redirect, AuthError> {
-    // TODO: Get OAuth config for provider
-    // TODO: Generate authorization URL
-    // TODO: Redirect
-    Err(AuthError::Internal) // Placeholder
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Path(provider): Path<String>,
+    Query(query): Query<OAuthQuery>,
+) -> Result<Json<OAuthAuthorizeResponse>, AuthError> {
+    let settings = state.auth_service.project_settings(context.project_id).await?;
+    let url = state.oauth_service
+        .authorize(context.project_id, &settings, &provider, query.redirect_to.as_deref(), None)
+        .await?;
+
+    Ok(Json(OAuthAuthorizeResponse { url }))
 }
 
+/// Where the provider sends the browser back. Once the `state` checks out the user
+/// always lands on the app, with the URL fragment carrying the new session, the code
+/// to finish linking with, or the error.
 pub async fn oauth_callback(
-    Path(_provider): Path<String>,
-    Query(_query): Query<OAuthQuery>,
-) -> Result<axum::response::Redirect, AuthError> {
-    // TODO: Exchange code for tokens
-    // TODO: Get user info
-    // TODO: Create or find user
-    // TODO: Create session
-    // TODO: Redirect to app
-    Err(AuthError::Internal) // Placeholder
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    client: ClientInfo,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<Redirect, AuthError> {
+    finish_callback(state, provider, client, query).await
+}
+
+/// The same callback posted as a form, as Apple does
+pub async fn oauth_callback_form(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    client: ClientInfo,
+    Form(query): Form<OAuthCallbackQuery>,
+) -> Result<Redirect, AuthError> {
+    finish_callback(state, provider, client, query).await
+}
+
+async fn finish_callback(
+    state: AppState,
+    provider: String,
+    client: ClientInfo,
+    query: OAuthCallbackQuery,
+) -> Result<Redirect, AuthError> {
+    let state_param = query
+        .state
+        .clone()
+        .ok_or_else(|| AuthError::InvalidInput("state missing".to_string()))?;
+    let pending = state.oauth_service.find_state(&provider, &state_param).await?;
+    let mut redirect = Url::parse(&pending.redirect_to).map_err(|_| AuthError::Internal)?;
+
+    let result = match pending.user_id {
+        // The browser here may not be the one that asked to link, so the app finishes
+        // the link while signed in as the user who did
+        Some(_) => hand_back_link(&state, &provider, &state_param, query).await,
+        None => match state.oauth_service.take_state(&provider, &state_param).await {
+            Ok(pending) => complete_signin(&state, &pending, query, client).await,
+            Err(e) => Err(e),
+        },
+    };
+    let pairs = result.unwrap_or_else(|e| {
+        tracing::info!("OAuth sign-in with {} failed: {}", provider, e);
+        vec![("error", e.status_and_code().1.to_string()), ("error_description", e.to_string())]
+    });
+    let fragment = form_urlencoded::Serializer::new(String::new()).extend_pairs(pairs).finish();
+    redirect.set_fragment(Some(&fragment));
+
+    Ok(Redirect::to(redirect.as_str()))
+}
+
+/// Parameters to hand back to the app in the redirect's fragment
+type Fragment = Vec<(&'static str, String)>;
+
+async fn complete_signin(
+    state: &AppState,
+    pending: &OAuthState,
+    query: OAuthCallbackQuery,
+    client: ClientInfo,
+) -> Result<Fragment, AuthError> {
+    let profile = fetch_profile(state, pending, query).await?;
+    let (_user, _session, tokens) = state.auth_service
+        .signin_oauth(pending.project_id, &pending.provider, &profile, client)
+        .await?;
+
+    Ok(vec![
+        ("access_token", tokens.access_token.token),
+        ("refresh_token", tokens.refresh_token.token),
+        ("expires_in", tokens.access_token.expires_in.to_string()),
+        ("token_type", "bearer".to_string()),
+    ])
+}
+
+/// Pass the code on for the app to post to `POST /oauth/{provider}/link` with the
+/// same `state`. A refused consent ends the link request straight away.
+async fn hand_back_link(
+    state: &AppState,
+    provider: &str,
+    state_param: &str,
+    query: OAuthCallbackQuery,
+) -> Result<Fragment, AuthError> {
+    if let Some(error) = query.error {
+        state.oauth_service.take_state(provider, state_param).await?;
+        return Err(provider_error(error, query.error_description));
+    }
+    let code = query
+        .code
+        .ok_or_else(|| AuthError::InvalidInput("code missing".to_string()))?;
+
+    Ok(vec![
+        ("code", code),
+        ("state", state_param.to_string()),
+        ("provider", provider.to_string()),
+    ])
 }
 
+fn provider_error(error: String, description: Option<String>) -> AuthError {
+    AuthError::OAuth(match description {
+        Some(description) => format!("{}: {}", error, description),
+        None => error,
+    })
+}
+
+async fn fetch_profile(
+    state: &AppState,
+    pending: &OAuthState,
+    query: OAuthCallbackQuery,
+) -> Result<OAuthUserInfo, AuthError> {
+    if let Some(error) = query.error {
+        return Err(provider_error(error, query.error_description));
+    }
+    let code = query
+        .code
+        .ok_or_else(|| AuthError::InvalidInput("code missing".to_string()))?;
+
+    let settings = state.auth_service.project_settings(pending.project_id).await?;
+    state.oauth_service
+        .exchange_code(
+            &settings,
+            &pending.provider,
+            &state.oauth_service.redirect_uri(&pending.provider),
+            &code,
+            Some(&pending.code_verifier),
+            pending.nonce.as_deref(),
+        )
+        .await
+}
+
+/// Exchange a code for a session, for mobile and native apps that ran the
+/// provider's consent screen themselves
 pub async fn oauth_token(
-    Path(_provider): Path<String>,
-) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Exchange code for tokens (for mobile/SPA)
-    Err(AuthError::Internal) // Placeholder
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Path(provider): Path<String>,
+    client: ClientInfo,
+    Json(req): Json<OAuthTokenRequest>,
+) -> Result<Json<AuthResponse>, AuthError> {
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    let settings = state.auth_service.project_settings(context.project_id).await?;
+    let redirect_uri = req
+        .redirect_uri
+        .unwrap_or_else(|| state.oauth_service.redirect_uri(&provider));
+    let profile = state.oauth_service
+        .exchange_code(&settings, &provider, &redirect_uri, &req.code, req.code_verifier.as_deref(), req.nonce.as_deref())
+        .await?;
+
+    let (user, _session, tokens) = state.auth_service
+        .signin_oauth(context.project_id, &provider, &profile, client)
+        .await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))))
 }
 
-pub async fn list_oauth_providers() -> Result<Json<OAuthProvidersResponse>, AuthError> {
-    // TODO: Get enabled providers from settings
-    Ok(Json(OAuthProvidersResponse { providers: vec![] }))
+pub async fn list_oauth_providers(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+) -> Result<Json<OAuthProvidersResponse>, AuthError> {
+    let settings = state.auth_service.project_settings(context.project_id).await?;
+    let providers = settings
+        .oauth
+        .iter()
+        .filter(|(_, config)| config.enabled)
+        .map(|(id, config)| OAuthProviderInfo {
+            id: id.clone(),
+            name: OAuthProvider::from_name(id).display_name().to_string(),
+            enabled: config.enabled,
+        })
+        .collect();
+
+    Ok(Json(OAuthProvidersResponse { providers }))
 }
@@ -0,0 +1,34 @@
+use axum::{extract::State, http::StatusCode, response::Json, Extension, Form};
+
+use crate::dto::{IntrospectionResponse, TokenHintRequest};
+use crate::error::AuthError;
+use crate::middleware::ApiKeyContext;
+use crate::state::AppState;
+
+/// RFC 7662 token introspection for resource servers and gateways
+pub async fn introspect(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Form(req): Form<TokenHintRequest>,
+) -> Result<Json<IntrospectionResponse>, AuthError> {
+    let token = state
+        .auth_service
+        .introspect(context.project_id, &req.token, req.token_type_hint.as_deref())
+        .await?;
+
+    Ok(Json(token.into()))
+}
+
+/// RFC 7009 token revocation; succeeds whether or not the token was active
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Form(req): Form<TokenHintRequest>,
+) -> Result<StatusCode, AuthError> {
+    state
+        .auth_service
+        .revoke_token(context.project_id, &req.token, req.token_type_hint.as_deref())
+        .await?;
+
+    Ok(StatusCode::OK)
+}
@@ -1,39 +1,151 @@
-use axum::response::Json;
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use std::time::Duration;
+use validator::Validate;
 
-use crate::dto::{SendOtpRequest, VerifyOtpRequest, SendMagicLinkRequest, OtpSentResponse, MagicLinkSentResponse, AuthResponse};
+use crate::domain::{ClientInfo, OtpChannel};
+use crate::dto::{
+    ApproveMagicLinkRequest, AuthResponse, MagicLinkPendingResponse, MagicLinkSentResponse, OtpSentResponse,
+    PollMagicLinkRequest, SendMagicLinkRequest, SendOtpRequest, VerifyMagicLinkRequest, VerifyOtpRequest,
+};
 use crate::error::AuthError;
+use crate::middleware::ApiKeyContext;
+use crate::services::MagicLinkPoll;
+use crate::state::AppState;
 
 pub async fn send_otp(
-    Json(_req): Json<SendOtpRequest>,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Json(req): Json<SendOtpRequest>,
 ) -> Result<Json<OtpSentResponse>, AuthError> {
-    // TODO: Generate OTP
-    // TODO: Store in database
-    // TODO: Send via email or SMS
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    let channel: OtpChannel = req.channel.parse()?;
+    let identifier = match channel {
+        OtpChannel::Email => req.email,
+        OtpChannel::Sms => req.phone,
+    }
+    .ok_or_else(|| AuthError::InvalidInput(format!("{} channel needs a destination", channel.as_str())))?;
+
+    let settings = state.auth_service.project_settings(context.project_id).await?;
+    state.passwordless_service
+        .send_otp(context.project_id, &settings.sms, channel, &identifier)
+        .await?;
+
     Ok(Json(OtpSentResponse { message: "OTP sent".to_string() }))
 }
 
 pub async fn verify_otp(
-    Json(_req): Json<VerifyOtpRequest>,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    client: ClientInfo,
+    Json(req): Json<VerifyOtpRequest>,
 ) -> Result<Json<AuthResponse>, AuthError> {
-    // TODO: Verify OTP
-    // TODO: Create or find user
-    // TODO: Create session
-    Err(AuthError::Internal) // Placeholder
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    let (channel, identifier) = match (req.email, req.phone) {
+        (Some(email), None) => (OtpChannel::Email, email),
+        (None, Some(phone)) => (OtpChannel::Sms, phone),
+        _ => return Err(AuthError::InvalidInput("provide either email or phone".to_string())),
+    };
+
+    let identifier = state.passwordless_service
+        .verify_otp(context.project_id, channel, &identifier, &req.code)
+        .await?;
+
+    let (user, _session, tokens) = state.auth_service
+        .signin_passwordless(context.project_id, channel, &identifier, client)
+        .await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))))
 }
 
 pub async fn send_magic_link(
-    Json(_req): Json<SendMagicLinkRequest>,
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Json(req): Json<SendMagicLinkRequest>,
 ) -> Result<Json<MagicLinkSentResponse>, AuthError> {
-    // TODO: Generate magic link token
-    // TODO: Store in database
-    // TODO: Send email
-    Ok(Json(MagicLinkSentResponse { message: "Magic link sent".to_string() }))
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    let settings = state.auth_service.project_settings(context.project_id).await?;
+    let cross_device = state.passwordless_service
+        .send_magic_link(
+            context.project_id,
+            &settings.redirects,
+            &req.email,
+            req.redirect_to.as_deref(),
+            req.code_challenge.as_deref(),
+            req.cross_device,
+        )
+        .await?;
+
+    let (poll_token, match_code) = match cross_device {
+        Some(request) => (Some(request.poll_token), Some(request.match_code)),
+        None => (None, None),
+    };
+    Ok(Json(MagicLinkSentResponse { message: "Magic link sent".to_string(), poll_token, match_code }))
 }
 
-pub async fn verify_magic_link() -> Result<Json<AuthResponse>, AuthError> {
-    // TODO: Get token from query params
-    // TODO: Verify token
-    // TODO: Create or find user
-    // TODO: Create session
-    Err(AuthError::Internal) // Placeholder
+pub async fn verify_magic_link(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    client: ClientInfo,
+    Json(req): Json<VerifyMagicLinkRequest>,
+) -> Result<Json<AuthResponse>, AuthError> {
+    let email = state.passwordless_service
+        .verify_magic_link(context.project_id, &req.token, req.code_verifier.as_deref())
+        .await?;
+
+    let (user, _session, tokens) = state.auth_service
+        .signin_passwordless(context.project_id, OtpChannel::Email, &email, client)
+        .await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))))
+}
+
+/// Called from whichever device opened a cross-device link, with the code the
+/// requesting device shows
+pub async fn approve_magic_link(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    Json(req): Json<ApproveMagicLinkRequest>,
+) -> Result<StatusCode, AuthError> {
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    state.passwordless_service
+        .approve_magic_link(context.project_id, &req.token, &req.code)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Called by the client that requested a cross-device link: 202 until the link is
+/// opened, then the session
+pub async fn poll_magic_link(
+    State(state): State<AppState>,
+    Extension(context): Extension<ApiKeyContext>,
+    client: ClientInfo,
+    Json(req): Json<PollMagicLinkRequest>,
+) -> Result<Response, AuthError> {
+    let wait = Duration::from_secs(req.wait_seconds.unwrap_or(0));
+    let poll = state.passwordless_service
+        .poll_magic_link(context.project_id, &req.poll_token, req.code_verifier.as_deref(), wait)
+        .await?;
+
+    let email = match poll {
+        MagicLinkPoll::Pending => {
+            let pending = MagicLinkPendingResponse { status: "pending".to_string() };
+            return Ok((StatusCode::ACCEPTED, Json(pending)).into_response());
+        }
+        MagicLinkPoll::Approved(email) => email,
+    };
+
+    let (user, _session, tokens) = state.auth_service
+        .signin_passwordless(context.project_id, OtpChannel::Email, &email, client)
+        .await?;
+
+    Ok(Json(AuthResponse::from((user, tokens))).into_response())
 }
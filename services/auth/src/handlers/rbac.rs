@@ -1,11 +1,24 @@
-use axum::{extract::Path, response::Json};
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
 
+use crate::domain::permissions_version;
 use crate::dto::{CreateRoleRequest, AssignRoleRequest, RolesResponse, PermissionsResponse};
 use crate::error::AuthError;
+use crate::middleware::AuthUser;
+use crate::repository::traits::UserRoleRepository;
+use crate::state::AppState;
 
-pub async fn get_roles() -> Result<Json<RolesResponse>, AuthError> {
-    // TODO: Get user roles
-    Ok(Json(RolesResponse { roles: vec![] }))
+pub async fn get_roles(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<RolesResponse>, AuthError> {
+    let roles = state.repos.user_role.get_user_roles(auth_user.user_id).await?;
+
+    Ok(Json(RolesResponse {
+        roles: roles.into_iter().map(Into::into).collect(),
+    }))
 }
 
 pub async fn list_all_roles() -> Result<Json<RolesResponse>, AuthError> {
@@ -43,7 +56,15 @@ pub async fn remove_role(
     Ok(Json(serde_json::json!({ "message": "Role removed" })))
 }
 
-pub async fn get_permissions() -> Result<Json<PermissionsResponse>, AuthError> {
-    // TODO: Get user permissions
-    Ok(Json(PermissionsResponse { permissions: vec![] }))
+/// Full permission set, for tokens that carry only a `perms_ver` claim
+pub async fn get_permissions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<PermissionsResponse>, AuthError> {
+    let (_, permissions) = state.auth_service.grants(auth_user.user_id).await?;
+
+    Ok(Json(PermissionsResponse {
+        version: permissions_version(&permissions),
+        permissions,
+    }))
 }
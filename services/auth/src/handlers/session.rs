@@ -1,23 +1,60 @@
-use axum::{extract::Path, response::Json};
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde::Deserialize;
 
-use crate::dto::SessionsResponse;
+use crate::dto::{SessionResponse, SessionsResponse};
 use crate::error::AuthError;
+use crate::middleware::AuthUser;
+use crate::state::AppState;
 
-pub async fn list_sessions() -> Result<Json<SessionsResponse>, AuthError> {
-    // TODO: Get user_id from auth middleware
-    // TODO: Fetch sessions from repository
-    Ok(Json(SessionsResponse { sessions: vec![] }))
+#[derive(Deserialize)]
+pub struct DeleteSessionsQuery {
+    /// Keep the session making the request signed in
+    #[serde(default)]
+    pub except_current: bool,
+}
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<SessionsResponse>, AuthError> {
+    let sessions = state.auth_service.list_sessions(auth_user.project_id, auth_user.user_id).await?;
+
+    Ok(Json(SessionsResponse {
+        sessions: sessions
+            .into_iter()
+            .map(|session| {
+                let current = session.id == auth_user.session_id;
+                SessionResponse::from((session, current))
+            })
+            .collect(),
+    }))
 }
 
 pub async fn delete_session(
-    Path(_session_id): Path<String>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(session_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Verify session belongs to user
-    // TODO: Delete session
+    state.auth_service.revoke_session(auth_user.user_id, &session_id).await?;
+
     Ok(Json(serde_json::json!({ "message": "Session deleted" })))
 }
 
-pub async fn delete_all_sessions() -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Delete all user sessions
-    Ok(Json(serde_json::json!({ "message": "All sessions deleted" })))
+/// DELETE /sessions?except_current=true signs out every other device
+pub async fn delete_all_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<DeleteSessionsQuery>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    let keep = query.except_current.then_some(auth_user.session_id.as_str());
+    let revoked = state.auth_service.signout_everywhere(auth_user.user_id, keep).await?;
+
+    let message = if query.except_current { "Other sessions deleted" } else { "All sessions deleted" };
+    Ok(Json(serde_json::json!({
+        "message": message,
+        "revoked": revoked,
+    })))
 }
@@ -1,4 +1,7 @@
-use axum::{extract::Path, response::Json};
+use axum::{
+    extract::Path,
+    response::Json,
+};
 
 use crate::error::AuthError;
 
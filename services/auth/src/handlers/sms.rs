@@ -0,0 +1,23 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Form,
+};
+
+use crate::error::AuthError;
+use crate::services::twilio::SIGNATURE_HEADER;
+use crate::state::AppState;
+
+/// Twilio delivery status callback
+pub async fn twilio_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(params): Form<Vec<(String, String)>>,
+) -> Result<StatusCode, AuthError> {
+    let twilio = state.twilio.as_ref().ok_or(AuthError::Unauthorized)?;
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|value| value.to_str().ok());
+
+    twilio.record_status(signature, &params).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
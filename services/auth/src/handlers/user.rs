@@ -1,7 +1,10 @@
-use axum::response::Json;
+use axum::{extract::State, response::Json};
+use validator::Validate;
 
 use crate::dto::{UpdateUserRequest, ChangePasswordRequest, ChangeEmailRequest, UserResponse};
 use crate::error::AuthError;
+use crate::middleware::AuthUser;
+use crate::state::AppState;
 
 pub async fn get_user() -> Result<Json<UserResponse>, AuthError> {
     // TODO: Get user from auth middleware
@@ -20,10 +23,21 @@ pub async fn delete_user() -> Result<Json<serde_json::Value>, AuthError> {
     Ok(Json(serde_json::json!({ "message": "User deleted" })))
 }
 
+/// Other sessions are signed out; the one making the change stays valid
 pub async fn change_password(
-    Json(_req): Json<ChangePasswordRequest>,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(req): Json<ChangePasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // TODO: Implement change password
+    req.validate().map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+    state.auth_service.change_password(
+        auth_user.user_id,
+        &auth_user.session_id,
+        &req.current_password,
+        &req.new_password,
+    ).await?;
+
     Ok(Json(serde_json::json!({ "message": "Password changed" })))
 }
 
@@ -0,0 +1,86 @@
+use common::{Job, MagicLink, OtpCode, PasswordResetToken};
+use std::time::Duration;
+
+use crate::repository::traits::{
+    OAuthStateRepository, RevokedTokenRepository, SessionRepository, SmsDeliveryRepository,
+};
+use crate::state::AppState;
+
+/// Cleanup jobs for the auth tables, for the `common::Scheduler` to run
+pub fn jobs(state: &AppState) -> Vec<Job> {
+    let sessions = Duration::from_secs(state.config.session_cleanup_interval_seconds);
+    let tokens = Duration::from_secs(state.config.token_cleanup_interval_seconds);
+    // A superseded refresh token must be remembered for as long as it could be replayed
+    let retention = chrono::Duration::seconds(
+        state
+            .config
+            .history_retention_seconds
+            .max(state.config.refresh_token_expiry_seconds) as i64,
+    );
+
+    let repos = state.repos.clone();
+    let revoke_sessions = Job::new("auth.sessions.revoke_expired", sessions, move || {
+        let repos = repos.clone();
+        async move { repos.session.revoke_expired().await }
+    });
+
+    let repos = state.repos.clone();
+    let prune_revoked_tokens = Job::new("auth.revoked_tokens.delete_expired", tokens, move || {
+        let repos = repos.clone();
+        async move { repos.revoked_token.delete_expired().await }
+    });
+
+    let pool = state.pool.clone();
+    let prune_otp_codes = Job::new("auth.otp_codes.cleanup_expired", tokens, move || {
+        let pool = pool.clone();
+        async move { OtpCode::cleanup_expired(&pool).await }
+    });
+
+    let pool = state.pool.clone();
+    let prune_magic_links = Job::new("auth.magic_links.cleanup_expired", tokens, move || {
+        let pool = pool.clone();
+        async move { MagicLink::cleanup_expired(&pool).await }
+    });
+
+    let pool = state.pool.clone();
+    let prune_password_resets = Job::new("auth.password_reset_tokens.cleanup_expired", tokens, move || {
+        let pool = pool.clone();
+        async move { PasswordResetToken::cleanup_expired(&pool).await }
+    });
+
+    let repos = state.repos.clone();
+    let prune_oauth_states = Job::new("auth.oauth_states.delete_expired", tokens, move || {
+        let repos = repos.clone();
+        async move { repos.oauth_state.delete_expired().await }
+    });
+
+    let repos = state.repos.clone();
+    let prune_sessions = Job::new("auth.sessions.delete_ended", tokens, move || {
+        let repos = repos.clone();
+        async move { repos.session.delete_ended_before(chrono::Utc::now() - retention).await }
+    });
+
+    let repos = state.repos.clone();
+    let prune_rotated_tokens = Job::new("auth.rotated_refresh_tokens.delete_old", tokens, move || {
+        let repos = repos.clone();
+        async move { repos.session.delete_rotated_before(chrono::Utc::now() - retention).await }
+    });
+
+    let repos = state.repos.clone();
+    let prune_sms_deliveries = Job::new("auth.sms_deliveries.delete_old", tokens, move || {
+        let repos = repos.clone();
+        async move { repos.sms_delivery.delete_before(chrono::Utc::now() - retention).await }
+    });
+
+    vec![
+        revoke_sessions,
+        prune_sessions,
+        prune_rotated_tokens,
+        prune_sms_deliveries,
+        prune_revoked_tokens,
+        prune_otp_codes,
+        prune_magic_links,
+        prune_password_resets,
+        prune_oauth_states,
+    ]
+}
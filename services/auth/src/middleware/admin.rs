@@ -0,0 +1,37 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error::AuthError;
+use crate::state::AppState;
+use crate::utils::crypto::constant_time_eq;
+
+/// Guards operator endpoints with the `ADMIN_API_TOKEN` bearer token.
+/// Without a configured token these endpoints are disabled.
+pub async fn admin_token_middleware(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let expected = state
+        .config
+        .admin_api_token
+        .as_deref()
+        .ok_or(AuthError::Forbidden)?;
+
+    let token = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or(AuthError::Unauthorized)?;
+
+    if !constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(next.run(request).await)
+}
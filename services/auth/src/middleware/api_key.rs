@@ -55,7 +55,6 @@ mod tests {
     use axum::{
         body::Body,
         http::{Request, StatusCode},
-        response::Response,
         routing::get,
         Router,
     };
@@ -77,8 +76,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_missing_api_key_returns_401() {
-        // This test requires a real DB connection, so we'll skip it for now
-        // In a real scenario, you'd use sqlx::test or a test database
+        // The header check runs before any query, so a lazy pool never connects
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let app = create_test_app(pool);
+
+        let response = app
+            .oneshot(Request::builder().uri("/test").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
     #[tokio::test]
@@ -1,51 +1,120 @@
 use axum::{
-    extract::Request,
-    http::{HeaderMap, StatusCode},
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
     middleware::Next,
     response::Response,
 };
 use uuid::Uuid;
 
 use crate::error::AuthError;
-use crate::services::TokenService;
+use crate::middleware::ApiKeyContext;
+use crate::state::AppState;
 
+/// Caller identity injected into request after successful bearer token validation
+#[derive(Debug, Clone)]
 pub struct AuthUser {
     pub user_id: Uuid,
     pub project_id: Uuid,
+    pub session_id: String,
     pub roles: Vec<String>,
     pub permissions: Vec<String>,
 }
 
 pub async fn auth_middleware(
+    State(state): State<AppState>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> Result<Response, AuthError> {
-    let auth_header = headers
+    let token = headers
         .get("authorization")
         .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
         .ok_or(AuthError::Unauthorized)?;
 
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AuthError::Unauthorized);
-    }
+    // Tokens are only valid for the project that issued them
+    let project_id = request
+        .extensions()
+        .get::<ApiKeyContext>()
+        .map(|context| context.project_id)
+        .ok_or(AuthError::InvalidApiKey)?;
+
+    let settings = state.auth_service.token_settings(project_id).await?;
+    let claims = state.token_service.verify_access_token(token, &settings)?;
+
+    // Signed-out, expired and idle sessions invalidate their access tokens
+    state.auth_service.authenticate_session(&claims, &settings).await?;
+
+    request.extensions_mut().insert(AuthUser {
+        user_id: claims.sub,
+        project_id: claims.project_id,
+        session_id: claims.sid,
+        roles: claims.roles,
+        permissions: claims.permissions,
+    });
 
-    let token = &auth_header[7..];
-    
-    // TODO: Get TokenService from state
-    // For now, this is a placeholder
-    // let token_service = request.extensions().get::<TokenService>()
-    //     .ok_or(AuthError::Internal)?;
-    
-    // let claims = token_service.verify_access_token(token)?;
-    
-    // request.extensions_mut().insert(AuthUser {
-    //     user_id: claims.sub,
-    //     project_id: claims.project_id,
-    //     roles: claims.roles,
-    //     permissions: claims.permissions,
-    // });
-
-    // For now, just pass through
     Ok(next.run(request).await)
 }
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or(AuthError::Unauthorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    async fn whoami(auth_user: AuthUser) -> String {
+        auth_user.user_id.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_extractor_rejects_without_auth_user() {
+        let app = Router::new().route("/whoami", get(whoami));
+
+        let response = app
+            .oneshot(Request::builder().uri("/whoami").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_extractor_reads_auth_user_extension() {
+        let user_id = Uuid::new_v4();
+        let app = Router::new()
+            .route("/whoami", get(whoami))
+            .layer(axum::Extension(AuthUser {
+                user_id,
+                project_id: Uuid::new_v4(),
+                session_id: "sess_test".to_string(),
+                roles: vec![],
+                permissions: vec![],
+            }));
+
+        let response = app
+            .oneshot(Request::builder().uri("/whoami").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, user_id.to_string());
+    }
+}
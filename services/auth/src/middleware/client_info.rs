@@ -0,0 +1,172 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+
+use crate::domain::ClientInfo;
+use crate::state::AppState;
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        // Only present when the server is run with `into_make_service_with_connect_info`
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        let ip = client_ip(peer, &parts.headers, &state.config.trusted_proxies);
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+
+        Ok(ClientInfo::new(ip, user_agent))
+    }
+}
+
+/// The connecting peer, unless it is a trusted proxy; then the forwarded chain is walked
+/// back from the nearest hop to the first address that is not one of ours. Anything to
+/// the left of that address was supplied by the client and cannot be believed.
+pub fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[IpNet]) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+
+    let mut client = peer?;
+    if !is_trusted(&client) {
+        return Some(client);
+    }
+
+    for hop in forwarded_chain(headers).into_iter().rev() {
+        // An obfuscated or malformed hop ends the chain at the proxy that reported it
+        let Some(ip) = hop else { break };
+        client = ip;
+        if !is_trusted(&ip) {
+            break;
+        }
+    }
+
+    Some(client)
+}
+
+/// Forwarded addresses, client first. `Forwarded` takes precedence over `X-Forwarded-For`.
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    let forwarded = values(header::FORWARDED.as_str());
+    if !forwarded.is_empty() {
+        return forwarded
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(key, _)| key.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect();
+    }
+
+    values("x-forwarded-for").into_iter().map(parse_node).collect()
+}
+
+/// `192.0.2.60`, `192.0.2.60:4711`, `2001:db8::1` or `"[2001:db8::1]:4711"`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+
+    node.parse().ok().or_else(|| {
+        let (host, _port) = node.split_once(':')?;
+        host.parse::<std::net::Ipv4Addr>().ok().map(IpAddr::V4)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_untrusted_peer_cannot_spoof_forwarded_headers() {
+        let headers = headers(&[("x-forwarded-for", "198.51.100.1")]);
+
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), &headers, &trusted()),
+            Some(ip("203.0.113.9"))
+        );
+        // Nothing is trusted by default
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &headers, &[]),
+            Some(ip("10.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn test_walks_x_forwarded_for_past_trusted_hops() {
+        // The client prepended a fake address; the second proxy appended the first
+        let headers = headers(&[
+            ("x-forwarded-for", "198.51.100.1, 203.0.113.9"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ]);
+
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &headers, &trusted()),
+            Some(ip("203.0.113.9"))
+        );
+    }
+
+    #[test]
+    fn test_forwarded_takes_precedence() {
+        let headers = headers(&[
+            ("forwarded", r#"for="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.2:8080"#),
+            ("x-forwarded-for", "198.51.100.1"),
+        ]);
+
+        assert_eq!(
+            client_ip(Some(ip("::1")), &headers, &trusted()),
+            Some(ip("2001:db8:cafe::17"))
+        );
+    }
+
+    #[test]
+    fn test_obfuscated_hop_stops_at_reporting_proxy() {
+        let headers = headers(&[("forwarded", "for=_hidden, for=10.0.0.2")]);
+
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &headers, &trusted()),
+            Some(ip("10.0.0.2"))
+        );
+    }
+}
@@ -2,8 +2,11 @@ pub mod auth;
 pub mod project;
 pub mod rate_limit;
 pub mod api_key;
+pub mod admin;
+pub mod client_info;
 
 pub use auth::{auth_middleware, AuthUser};
 pub use project::{project_middleware, ProjectContext};
 pub use rate_limit::RateLimitMiddleware;
 pub use api_key::{api_key_middleware, ApiKeyContext};
+pub use admin::admin_token_middleware;
@@ -15,10 +15,10 @@ pub struct ProjectContext {
 
 pub async fn project_middleware(
     headers: HeaderMap,
-    mut request: Request,
+    request: Request,
     next: Next,
 ) -> Result<Response, AuthError> {
-    let api_key = headers
+    let _api_key = headers
         .get("x-api-key")
         .and_then(|h| h.to_str().ok())
         .ok_or(AuthError::ProjectNotFound)?;
@@ -38,27 +38,28 @@ impl RateLimitMiddleware {
     ) -> Result<Response, AuthError> {
         let key = self.get_rate_limit_key(&headers);
         
-        let mut limits = self.limits.lock().unwrap();
+        {
+            let mut limits = self.limits.lock().unwrap();
         
-        let entry = limits.entry(key.clone()).or_insert_with(|| {
-            RateLimitEntry {
-                count: 0,
-                reset_at: Instant::now() + Duration::from_secs(self.window_seconds),
+            let entry = limits.entry(key.clone()).or_insert_with(|| {
+                RateLimitEntry {
+                    count: 0,
+                    reset_at: Instant::now() + Duration::from_secs(self.window_seconds),
+                }
+            });
+
+            if Instant::now() > entry.reset_at {
+                entry.count = 0;
+                entry.reset_at = Instant::now() + Duration::from_secs(self.window_seconds);
             }
-        });
 
-        if Instant::now() > entry.reset_at {
-            entry.count = 0;
-            entry.reset_at = Instant::now() + Duration::from_secs(self.window_seconds);
-        }
+            if entry.count >= self.max_requests {
+                return Err(AuthError::RateLimitExceeded);
+            }
 
-        if entry.count >= self.max_requests {
-            return Err(AuthError::RateLimitExceeded);
+            entry.count += 1;
         }
 
-        entry.count += 1;
-
-        drop(limits);
         Ok(next.run(request).await)
     }
 
@@ -0,0 +1,556 @@
+//! In-memory repositories for unit tests
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::domain::sms::FINAL_SMS_STATUSES;
+use crate::domain::{Identity, MagicLink, OAuthState, OtpCode, Project, RetiringJwtSecret, RevokedToken, Role, Session, SigningKeyRecord, SmsDelivery, User};
+use crate::error::AuthError;
+use crate::repository::traits::{
+    IdentityRepository, MagicLinkRepository, OAuthStateRepository, OtpRepository, ProjectRepository, RevokedTokenRepository, SessionRepository,
+    SigningKeyRepository, SmsDeliveryRepository, UserRepository, UserRoleRepository,
+};
+use crate::utils::crypto::hash_token;
+
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    users: Mutex<HashMap<Uuid, User>>,
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn create(&self, user: &User) -> Result<User, AuthError> {
+        self.users.lock().unwrap().insert(user.id, user.clone());
+        Ok(user.clone())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, AuthError> {
+        Ok(self.users.lock().unwrap().get(&id).cloned())
+    }
+
+    async fn find_by_email(&self, project_id: Uuid, email: &str) -> Result<Option<User>, AuthError> {
+        Ok(self.users.lock().unwrap().values()
+            .find(|u| u.project_id == project_id && u.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(email)))
+            .cloned())
+    }
+
+    async fn find_by_phone(&self, project_id: Uuid, phone: &str) -> Result<Option<User>, AuthError> {
+        Ok(self.users.lock().unwrap().values()
+            .find(|u| u.project_id == project_id && u.phone.as_deref() == Some(phone))
+            .cloned())
+    }
+
+    async fn update(&self, user: &User) -> Result<User, AuthError> {
+        self.users.lock().unwrap().insert(user.id, user.clone());
+        Ok(user.clone())
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), AuthError> {
+        self.users.lock().unwrap().remove(&id);
+        Ok(())
+    }
+
+    async fn list(&self, project_id: Uuid, limit: i64, offset: i64) -> Result<Vec<User>, AuthError> {
+        Ok(self.users.lock().unwrap().values()
+            .filter(|u| u.project_id == project_id)
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySessionRepository {
+    sessions: Mutex<HashMap<String, Session>>,
+    // rotated refresh token hash -> (session id, rotated at)
+    rotated: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+}
+
+#[async_trait]
+impl SessionRepository for InMemorySessionRepository {
+    async fn create(&self, session: &Session) -> Result<Session, AuthError> {
+        self.sessions.lock().unwrap().insert(session.id.clone(), session.clone());
+        Ok(session.clone())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Session>, AuthError> {
+        Ok(self.sessions.lock().unwrap().get(id).cloned())
+    }
+
+    async fn find_by_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, AuthError> {
+        Ok(self.sessions.lock().unwrap().values()
+            .find(|s| s.refresh_token_hash == hash_token(refresh_token))
+            .cloned())
+    }
+
+    async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<Session>, AuthError> {
+        Ok(self.sessions.lock().unwrap().values()
+            .filter(|s| s.user_id == user_id && !s.revoked)
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, session: &Session) -> Result<Session, AuthError> {
+        self.sessions.lock().unwrap().insert(session.id.clone(), session.clone());
+        Ok(session.clone())
+    }
+
+    async fn touch(&self, id: &str) -> Result<(), AuthError> {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(id) {
+            session.update_last_active();
+        }
+        Ok(())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        session: &Session,
+        previous_refresh_token: &str,
+    ) -> Result<Option<Session>, AuthError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(&session.id) {
+            Some(current) if current.refresh_token_hash == hash_token(previous_refresh_token) && !current.revoked => {}
+            _ => return Ok(None),
+        }
+
+        sessions.insert(session.id.clone(), session.clone());
+        self.rotated.lock().unwrap()
+            .insert(hash_token(previous_refresh_token), (session.id.clone(), Utc::now()));
+        Ok(Some(session.clone()))
+    }
+
+    async fn find_by_rotated_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, AuthError> {
+        let session_id = self.rotated.lock().unwrap().get(&hash_token(refresh_token)).map(|(id, _)| id.clone());
+        Ok(session_id.and_then(|id| self.sessions.lock().unwrap().get(&id).cloned()))
+    }
+
+    async fn revoke_family(&self, family_id: Uuid) -> Result<Vec<Session>, AuthError> {
+        let mut revoked = Vec::new();
+        for session in self.sessions.lock().unwrap().values_mut() {
+            if session.family_id == family_id && !session.revoked {
+                session.revoke();
+                revoked.push(session.clone());
+            }
+        }
+        Ok(revoked)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AuthError> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    async fn delete_by_user_id(&self, user_id: Uuid) -> Result<(), AuthError> {
+        self.sessions.lock().unwrap().retain(|_, s| s.user_id != user_id);
+        Ok(())
+    }
+
+    async fn revoke_expired(&self) -> Result<u64, AuthError> {
+        let mut revoked = 0;
+        for session in self.sessions.lock().unwrap().values_mut() {
+            if session.is_expired() && !session.revoked {
+                session.revoke();
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    async fn delete_ended_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, s| s.expires_at >= cutoff && !(s.revoked && s.last_active_at < cutoff));
+
+        // Rotated tokens go with their session, as with the foreign key cascade
+        self.rotated.lock().unwrap().retain(|_, (id, _)| sessions.contains_key(id));
+        Ok((before - sessions.len()) as u64)
+    }
+
+    async fn delete_rotated_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let mut rotated = self.rotated.lock().unwrap();
+        let before = rotated.len();
+        rotated.retain(|_, (_, rotated_at)| *rotated_at >= cutoff);
+        Ok((before - rotated.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySigningKeyRepository {
+    keys: Mutex<Vec<SigningKeyRecord>>,
+}
+
+impl InMemorySigningKeyRepository {
+    /// Edit stored keys directly, e.g. to move activation times into the past
+    pub fn keys_mut(&self, edit: impl FnOnce(&mut Vec<SigningKeyRecord>)) {
+        edit(&mut self.keys.lock().unwrap());
+    }
+}
+
+#[async_trait]
+impl SigningKeyRepository for InMemorySigningKeyRepository {
+    async fn create(&self, key: &SigningKeyRecord) -> Result<SigningKeyRecord, AuthError> {
+        self.keys.lock().unwrap().push(key.clone());
+        Ok(key.clone())
+    }
+
+    async fn find_by_kid(&self, kid: &str) -> Result<Option<SigningKeyRecord>, AuthError> {
+        Ok(self.keys.lock().unwrap().iter().find(|k| k.kid == kid).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<SigningKeyRecord>, AuthError> {
+        let mut keys = self.keys.lock().unwrap().clone();
+        keys.sort_by_key(|k| std::cmp::Reverse(k.activates_at));
+        Ok(keys)
+    }
+
+    async fn retire(&self, kid: &str) -> Result<(), AuthError> {
+        for key in self.keys.lock().unwrap().iter_mut() {
+            if key.kid == kid && key.retired_at.is_none() {
+                key.retired_at = Some(chrono::Utc::now());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryProjectRepository {
+    projects: Mutex<HashMap<Uuid, Project>>,
+}
+
+impl InMemoryProjectRepository {
+    pub fn insert(&self, project: Project) {
+        self.projects.lock().unwrap().insert(project.id, project);
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for InMemoryProjectRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Project>, AuthError> {
+        Ok(self.projects.lock().unwrap().get(&id).cloned())
+    }
+
+    async fn set_jwt_secret(
+        &self,
+        id: Uuid,
+        secret: Option<&str>,
+        previous_expires_at: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        if let Some(project) = self.projects.lock().unwrap().get_mut(&id) {
+            let previous = std::mem::replace(&mut project.jwt_secret, secret.map(str::to_string));
+            project.retiring_jwt_secret = Some(RetiringJwtSecret {
+                secret: previous,
+                expires_at: previous_expires_at,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryUserRoleRepository {
+    roles: Mutex<HashMap<Uuid, Role>>,
+    assignments: Mutex<Vec<(Uuid, Uuid)>>,
+}
+
+impl InMemoryUserRoleRepository {
+    /// Register a role and assign it to the user in one step
+    pub fn insert(&self, user_id: Uuid, role: Role) {
+        self.assignments.lock().unwrap().push((user_id, role.id));
+        self.roles.lock().unwrap().insert(role.id, role);
+    }
+}
+
+#[async_trait]
+impl UserRoleRepository for InMemoryUserRoleRepository {
+    async fn assign_role(&self, user_id: Uuid, role_id: Uuid) -> Result<(), AuthError> {
+        let mut assignments = self.assignments.lock().unwrap();
+        if !assignments.contains(&(user_id, role_id)) {
+            assignments.push((user_id, role_id));
+        }
+        Ok(())
+    }
+
+    async fn remove_role(&self, user_id: Uuid, role_id: Uuid) -> Result<(), AuthError> {
+        self.assignments.lock().unwrap().retain(|a| *a != (user_id, role_id));
+        Ok(())
+    }
+
+    async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<Role>, AuthError> {
+        let roles = self.roles.lock().unwrap();
+        Ok(self.assignments.lock().unwrap().iter()
+            .filter(|(user, _)| *user == user_id)
+            .filter_map(|(_, role_id)| roles.get(role_id).cloned())
+            .collect())
+    }
+
+    async fn has_role(&self, user_id: Uuid, role_name: &str) -> Result<bool, AuthError> {
+        Ok(self.get_user_roles(user_id).await?.iter().any(|r| r.name == role_name))
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryRevokedTokenRepository {
+    tokens: Mutex<HashMap<String, RevokedToken>>,
+}
+
+#[async_trait]
+impl RevokedTokenRepository for InMemoryRevokedTokenRepository {
+    async fn create(&self, token: &RevokedToken) -> Result<(), AuthError> {
+        self.tokens.lock().unwrap().entry(token.jti.clone()).or_insert_with(|| token.clone());
+        Ok(())
+    }
+
+    async fn list_active(&self) -> Result<Vec<RevokedToken>, AuthError> {
+        let now = chrono::Utc::now();
+        Ok(self.tokens.lock().unwrap().values()
+            .filter(|t| t.expires_at > now)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, AuthError> {
+        let now = chrono::Utc::now();
+        let mut tokens = self.tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|_, t| t.expires_at > now);
+        Ok((before - tokens.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryOtpRepository {
+    codes: Mutex<HashMap<Uuid, OtpCode>>,
+}
+
+#[async_trait]
+impl OtpRepository for InMemoryOtpRepository {
+    async fn create(&self, otp: &OtpCode) -> Result<OtpCode, AuthError> {
+        self.codes.lock().unwrap().insert(otp.id, otp.clone());
+        Ok(otp.clone())
+    }
+
+    async fn find_latest(&self, project_id: Uuid, identifier: &str) -> Result<Option<OtpCode>, AuthError> {
+        Ok(self.codes.lock().unwrap().values()
+            .filter(|o| o.project_id == project_id && o.identifier == identifier)
+            .max_by_key(|o| o.created_at)
+            .cloned())
+    }
+
+    async fn record_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, AuthError> {
+        Ok(match self.codes.lock().unwrap().get_mut(&id) {
+            Some(otp) if !otp.used && otp.attempts < max_attempts => {
+                otp.attempts += 1;
+                Some(otp.attempts)
+            }
+            _ => None,
+        })
+    }
+
+    async fn mark_used(&self, id: Uuid) -> Result<bool, AuthError> {
+        Ok(match self.codes.lock().unwrap().get_mut(&id) {
+            Some(otp) if !otp.used => {
+                otp.used = true;
+                true
+            }
+            _ => false,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryMagicLinkRepository {
+    links: Mutex<HashMap<Uuid, MagicLink>>,
+}
+
+#[async_trait]
+impl MagicLinkRepository for InMemoryMagicLinkRepository {
+    async fn create(&self, link: &MagicLink) -> Result<MagicLink, AuthError> {
+        self.links.lock().unwrap().insert(link.id, link.clone());
+        Ok(link.clone())
+    }
+
+    async fn find_by_token_hash(&self, token_hash: &str) -> Result<Option<MagicLink>, AuthError> {
+        Ok(self.links.lock().unwrap().values()
+            .find(|l| l.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn find_by_poll_token_hash(&self, poll_token_hash: &str) -> Result<Option<MagicLink>, AuthError> {
+        Ok(self.links.lock().unwrap().values()
+            .find(|l| l.poll_token_hash.as_deref() == Some(poll_token_hash))
+            .cloned())
+    }
+
+    async fn approve(&self, id: Uuid) -> Result<bool, AuthError> {
+        Ok(match self.links.lock().unwrap().get_mut(&id) {
+            Some(link) if link.approved_at.is_none() && !link.used => {
+                link.approved_at = Some(chrono::Utc::now());
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn record_approval_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, AuthError> {
+        Ok(match self.links.lock().unwrap().get_mut(&id) {
+            Some(link) if link.approved_at.is_none() && !link.used && link.approval_attempts < max_attempts => {
+                link.approval_attempts += 1;
+                Some(link.approval_attempts)
+            }
+            _ => None,
+        })
+    }
+
+    async fn mark_used(&self, id: Uuid) -> Result<bool, AuthError> {
+        Ok(match self.links.lock().unwrap().get_mut(&id) {
+            Some(link) if !link.used => {
+                link.used = true;
+                true
+            }
+            _ => false,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct InMemorySmsDeliveryRepository {
+    deliveries: Mutex<HashMap<Uuid, SmsDelivery>>,
+}
+
+impl InMemorySmsDeliveryRepository {
+    pub fn find(&self, provider: &str, provider_message_id: &str) -> Option<SmsDelivery> {
+        self.deliveries.lock().unwrap().values()
+            .find(|d| d.provider == provider && d.provider_message_id == provider_message_id)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl SmsDeliveryRepository for InMemorySmsDeliveryRepository {
+    async fn create(&self, delivery: &SmsDelivery) -> Result<SmsDelivery, AuthError> {
+        self.deliveries.lock().unwrap().insert(delivery.id, delivery.clone());
+        Ok(delivery.clone())
+    }
+
+    async fn update_status(
+        &self,
+        provider: &str,
+        provider_message_id: &str,
+        status: &str,
+        error_code: Option<&str>,
+    ) -> Result<bool, AuthError> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let delivery = deliveries.values_mut().find(|d| {
+            d.provider == provider
+                && d.provider_message_id == provider_message_id
+                && !FINAL_SMS_STATUSES.contains(&d.status.as_str())
+        });
+
+        Ok(match delivery {
+            Some(delivery) => {
+                delivery.status = status.to_string();
+                delivery.error_code = error_code.map(str::to_string);
+                delivery.updated_at = chrono::Utc::now();
+                true
+            }
+            None => false,
+        })
+    }
+
+    async fn delete_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let before = deliveries.len();
+        deliveries.retain(|_, d| d.created_at >= cutoff);
+        Ok((before - deliveries.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryOAuthStateRepository {
+    states: Mutex<HashMap<String, OAuthState>>,
+}
+
+#[async_trait]
+impl OAuthStateRepository for InMemoryOAuthStateRepository {
+    async fn create(&self, state: &OAuthState) -> Result<OAuthState, AuthError> {
+        self.states.lock().unwrap().insert(state.state_hash.clone(), state.clone());
+        Ok(state.clone())
+    }
+
+    async fn take(&self, state_hash: &str) -> Result<Option<OAuthState>, AuthError> {
+        Ok(self.states.lock().unwrap().remove(state_hash)
+            .filter(|s| s.expires_at > chrono::Utc::now()))
+    }
+
+    async fn find(&self, state_hash: &str) -> Result<Option<OAuthState>, AuthError> {
+        Ok(self.states.lock().unwrap().get(state_hash)
+            .filter(|s| s.expires_at > chrono::Utc::now())
+            .cloned())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, AuthError> {
+        let mut states = self.states.lock().unwrap();
+        let before = states.len();
+        let now = chrono::Utc::now();
+        states.retain(|_, s| s.expires_at > now);
+        Ok((before - states.len()) as u64)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryIdentityRepository {
+    identities: Mutex<HashMap<Uuid, Identity>>,
+}
+
+#[async_trait]
+impl IdentityRepository for InMemoryIdentityRepository {
+    async fn create(&self, identity: &Identity) -> Result<Identity, AuthError> {
+        let mut identities = self.identities.lock().unwrap();
+        if identities.values().any(|i| {
+            i.project_id == identity.project_id && i.provider == identity.provider && i.provider_id == identity.provider_id
+        }) {
+            return Err(AuthError::IdentityAlreadyLinked);
+        }
+        identities.insert(identity.id, identity.clone());
+        Ok(identity.clone())
+    }
+
+    async fn find_by_provider_id(
+        &self,
+        project_id: Uuid,
+        provider: &str,
+        provider_id: &str,
+    ) -> Result<Option<Identity>, AuthError> {
+        Ok(self.identities.lock().unwrap().values()
+            .find(|i| i.project_id == project_id && i.provider == provider && i.provider_id == provider_id)
+            .cloned())
+    }
+
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Identity>, AuthError> {
+        let mut identities: Vec<_> = self.identities.lock().unwrap().values()
+            .filter(|i| i.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by_key(|i| i.created_at);
+        Ok(identities)
+    }
+
+    async fn update(&self, identity: &Identity) -> Result<Identity, AuthError> {
+        self.identities.lock().unwrap().insert(identity.id, identity.clone());
+        Ok(identity.clone())
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, AuthError> {
+        let mut identities = self.identities.lock().unwrap();
+        if identities.get(&id).is_some_and(|i| i.user_id == user_id) {
+            identities.remove(&id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
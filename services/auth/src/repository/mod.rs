@@ -1,5 +1,7 @@
 pub mod traits;
 pub mod postgres;
+#[cfg(test)]
+pub mod memory;
 
 pub use traits::*;
 pub use postgres::PostgresRepositories;
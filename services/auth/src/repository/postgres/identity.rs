@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::Identity;
+use crate::error::AuthError;
+use crate::repository::traits::IdentityRepository;
+use super::models::IdentityRow;
+
+pub struct PostgresIdentityRepository {
+    pool: PgPool,
+}
+
+impl PostgresIdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for PostgresIdentityRepository {
+    async fn create(&self, identity: &Identity) -> Result<Identity, AuthError> {
+        let row = sqlx::query_as::<_, IdentityRow>(
+            r#"
+            INSERT INTO identities (
+                id, project_id, user_id, provider, provider_id, email, identity_data,
+                last_signin_at, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(identity.id)
+        .bind(identity.project_id)
+        .bind(identity.user_id)
+        .bind(&identity.provider)
+        .bind(&identity.provider_id)
+        .bind(&identity.email)
+        .bind(&identity.identity_data)
+        .bind(identity.last_signin_at)
+        .bind(identity.created_at)
+        .bind(identity.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => AuthError::IdentityAlreadyLinked,
+            e => AuthError::Database(e),
+        })?;
+
+        Ok(row.into())
+    }
+
+    async fn find_by_provider_id(
+        &self,
+        project_id: Uuid,
+        provider: &str,
+        provider_id: &str,
+    ) -> Result<Option<Identity>, AuthError> {
+        let row = sqlx::query_as::<_, IdentityRow>(
+            "SELECT * FROM identities WHERE project_id = $1 AND provider = $2 AND provider_id = $3",
+        )
+        .bind(project_id)
+        .bind(provider)
+        .bind(provider_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Identity>, AuthError> {
+        let rows = sqlx::query_as::<_, IdentityRow>(
+            "SELECT * FROM identities WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn update(&self, identity: &Identity) -> Result<Identity, AuthError> {
+        let row = sqlx::query_as::<_, IdentityRow>(
+            r#"
+            UPDATE identities
+            SET email = $2, identity_data = $3, last_signin_at = $4, updated_at = $5
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(identity.id)
+        .bind(&identity.email)
+        .bind(&identity.identity_data)
+        .bind(identity.last_signin_at)
+        .bind(identity.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, AuthError> {
+        let result = sqlx::query("DELETE FROM identities WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
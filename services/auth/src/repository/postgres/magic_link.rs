@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::MagicLink;
+use crate::error::AuthError;
+use crate::repository::traits::MagicLinkRepository;
+use super::models::MagicLinkRow;
+
+pub struct PostgresMagicLinkRepository {
+    pool: PgPool,
+}
+
+impl PostgresMagicLinkRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MagicLinkRepository for PostgresMagicLinkRepository {
+    async fn create(&self, link: &MagicLink) -> Result<MagicLink, AuthError> {
+        let row = sqlx::query_as::<_, MagicLinkRow>(
+            r#"
+            INSERT INTO magic_links (
+                id, project_id, email, token_hash, code_challenge, redirect_to, poll_token_hash,
+                match_code_hash, approval_attempts, approved_at, expires_at, used, created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING *
+            "#,
+        )
+        .bind(link.id)
+        .bind(link.project_id)
+        .bind(&link.email)
+        .bind(&link.token_hash)
+        .bind(&link.code_challenge)
+        .bind(&link.redirect_to)
+        .bind(&link.poll_token_hash)
+        .bind(&link.match_code_hash)
+        .bind(link.approval_attempts)
+        .bind(link.approved_at)
+        .bind(link.expires_at)
+        .bind(link.used)
+        .bind(link.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn find_by_token_hash(&self, token_hash: &str) -> Result<Option<MagicLink>, AuthError> {
+        let row = sqlx::query_as::<_, MagicLinkRow>("SELECT * FROM magic_links WHERE token_hash = $1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn find_by_poll_token_hash(&self, poll_token_hash: &str) -> Result<Option<MagicLink>, AuthError> {
+        let row = sqlx::query_as::<_, MagicLinkRow>("SELECT * FROM magic_links WHERE poll_token_hash = $1")
+            .bind(poll_token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn approve(&self, id: Uuid) -> Result<bool, AuthError> {
+        let result = sqlx::query(
+            "UPDATE magic_links SET approved_at = NOW() WHERE id = $1 AND approved_at IS NULL AND used = false",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn record_approval_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, AuthError> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE magic_links SET approval_attempts = approval_attempts + 1
+            WHERE id = $1 AND used = false AND approved_at IS NULL AND approval_attempts < $2
+            RETURNING approval_attempts
+            "#,
+        )
+        .bind(id)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)
+    }
+
+    async fn mark_used(&self, id: Uuid) -> Result<bool, AuthError> {
+        let result = sqlx::query("UPDATE magic_links SET used = true WHERE id = $1 AND used = false")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -3,15 +3,30 @@ pub mod user;
 pub mod session;
 pub mod role;
 pub mod user_role;
+pub mod signing_key;
+pub mod project;
+pub mod revoked_token;
+pub mod otp;
+pub mod magic_link;
+pub mod sms_delivery;
+pub mod oauth_state;
+pub mod identity;
 
 use sqlx::PgPool;
-use crate::repository::traits::*;
 
 pub struct PostgresRepositories {
     pub user: user::PostgresUserRepository,
     pub session: session::PostgresSessionRepository,
     pub role: role::PostgresRoleRepository,
     pub user_role: user_role::PostgresUserRoleRepository,
+    pub signing_key: signing_key::PostgresSigningKeyRepository,
+    pub project: project::PostgresProjectRepository,
+    pub revoked_token: revoked_token::PostgresRevokedTokenRepository,
+    pub otp: otp::PostgresOtpRepository,
+    pub magic_link: magic_link::PostgresMagicLinkRepository,
+    pub sms_delivery: sms_delivery::PostgresSmsDeliveryRepository,
+    pub oauth_state: oauth_state::PostgresOAuthStateRepository,
+    pub identity: identity::PostgresIdentityRepository,
 }
 
 impl PostgresRepositories {
@@ -20,7 +35,15 @@ impl PostgresRepositories {
             user: user::PostgresUserRepository::new(pool.clone()),
             session: session::PostgresSessionRepository::new(pool.clone()),
             role: role::PostgresRoleRepository::new(pool.clone()),
-            user_role: user_role::PostgresUserRoleRepository::new(pool),
+            user_role: user_role::PostgresUserRoleRepository::new(pool.clone()),
+            signing_key: signing_key::PostgresSigningKeyRepository::new(pool.clone()),
+            project: project::PostgresProjectRepository::new(pool.clone()),
+            revoked_token: revoked_token::PostgresRevokedTokenRepository::new(pool.clone()),
+            otp: otp::PostgresOtpRepository::new(pool.clone()),
+            magic_link: magic_link::PostgresMagicLinkRepository::new(pool.clone()),
+            sms_delivery: sms_delivery::PostgresSmsDeliveryRepository::new(pool.clone()),
+            oauth_state: oauth_state::PostgresOAuthStateRepository::new(pool.clone()),
+            identity: identity::PostgresIdentityRepository::new(pool),
         }
     }
 }
@@ -7,12 +7,13 @@ use uuid::Uuid;
 pub struct UserRow {
     pub id: Uuid,
     pub project_id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
     pub password_hash: Option<String>,
     pub metadata: Value,
+    pub app_metadata: Value,
     pub mfa_enabled: bool,
     pub mfa_secret: Option<String>,
     pub mfa_backup_codes: Option<Vec<String>>,
@@ -33,6 +34,7 @@ impl From<UserRow> for crate::domain::User {
             phone_verified: row.phone_verified,
             password_hash: row.password_hash,
             metadata: row.metadata,
+            app_metadata: row.app_metadata,
             mfa_enabled: row.mfa_enabled,
             mfa_secret: row.mfa_secret,
             mfa_backup_codes: row.mfa_backup_codes,
@@ -49,10 +51,15 @@ pub struct SessionRow {
     pub id: String,
     pub user_id: Uuid,
     pub project_id: Uuid,
-    pub access_token: String,
-    pub refresh_token: String,
+    pub family_id: Uuid,
+    pub access_token_hash: String,
+    pub refresh_token_hash: String,
+    pub access_token_jti: Option<String>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub last_active_at: DateTime<Utc>,
@@ -65,10 +72,15 @@ impl From<SessionRow> for crate::domain::Session {
             id: row.id,
             user_id: row.user_id,
             project_id: row.project_id,
-            access_token: row.access_token,
-            refresh_token: row.refresh_token,
+            family_id: row.family_id,
+            access_token_hash: row.access_token_hash,
+            refresh_token_hash: row.refresh_token_hash,
+            access_token_jti: row.access_token_jti,
             ip_address: row.ip_address,
             user_agent: row.user_agent,
+            device: row.device,
+            browser: row.browser,
+            os: row.os,
             created_at: row.created_at,
             expires_at: row.expires_at,
             last_active_at: row.last_active_at,
@@ -86,3 +98,232 @@ pub struct RoleRow {
     pub permissions: Value,
     pub created_at: DateTime<Utc>,
 }
+
+#[derive(Clone, FromRow)]
+pub struct SigningKeyRow {
+    pub kid: String,
+    pub algorithm: String,
+    pub private_key: String,
+    pub created_at: DateTime<Utc>,
+    pub activates_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+impl From<SigningKeyRow> for crate::domain::SigningKeyRecord {
+    fn from(row: SigningKeyRow) -> Self {
+        Self {
+            kid: row.kid,
+            algorithm: row.algorithm,
+            private_key: row.private_key,
+            created_at: row.created_at,
+            activates_at: row.activates_at,
+            retired_at: row.retired_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RevokedTokenRow {
+    pub jti: String,
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
+
+impl From<RevokedTokenRow> for crate::domain::RevokedToken {
+    fn from(row: RevokedTokenRow) -> Self {
+        Self {
+            jti: row.jti,
+            user_id: row.user_id,
+            project_id: row.project_id,
+            expires_at: row.expires_at,
+            revoked_at: row.revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OtpRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub identifier: String,
+    pub code_hash: String,
+    pub channel: String,
+    pub expires_at: DateTime<Utc>,
+    pub used: bool,
+    pub created_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+impl From<OtpRow> for crate::domain::OtpCode {
+    fn from(row: OtpRow) -> Self {
+        Self {
+            id: row.id,
+            project_id: row.project_id,
+            identifier: row.identifier,
+            code_hash: row.code_hash,
+            channel: match row.channel.as_str() {
+                "sms" => crate::domain::OtpChannel::Sms,
+                _ => crate::domain::OtpChannel::Email,
+            },
+            expires_at: row.expires_at,
+            used: row.used,
+            created_at: row.created_at,
+            attempts: row.attempts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct MagicLinkRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub email: String,
+    pub token_hash: String,
+    pub code_challenge: Option<String>,
+    pub redirect_to: Option<String>,
+    pub poll_token_hash: Option<String>,
+    pub match_code_hash: Option<String>,
+    pub approval_attempts: i32,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub used: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<MagicLinkRow> for crate::domain::MagicLink {
+    fn from(row: MagicLinkRow) -> Self {
+        Self {
+            id: row.id,
+            project_id: row.project_id,
+            email: row.email,
+            token_hash: row.token_hash,
+            code_challenge: row.code_challenge,
+            redirect_to: row.redirect_to,
+            poll_token_hash: row.poll_token_hash,
+            match_code_hash: row.match_code_hash,
+            approval_attempts: row.approval_attempts,
+            approved_at: row.approved_at,
+            expires_at: row.expires_at,
+            used: row.used,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SmsDeliveryRow {
+    pub id: Uuid,
+    pub provider: String,
+    pub provider_message_id: String,
+    pub recipient: String,
+    pub status: String,
+    pub error_code: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<SmsDeliveryRow> for crate::domain::SmsDelivery {
+    fn from(row: SmsDeliveryRow) -> Self {
+        Self {
+            id: row.id,
+            provider: row.provider,
+            provider_message_id: row.provider_message_id,
+            recipient: row.recipient,
+            status: row.status,
+            error_code: row.error_code,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthStateRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub provider: String,
+    pub state_hash: String,
+    pub code_verifier: String,
+    pub redirect_to: String,
+    pub user_id: Option<Uuid>,
+    pub nonce: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OAuthStateRow> for crate::domain::OAuthState {
+    fn from(row: OAuthStateRow) -> Self {
+        Self {
+            id: row.id,
+            project_id: row.project_id,
+            provider: row.provider,
+            state_hash: row.state_hash,
+            code_verifier: row.code_verifier,
+            redirect_to: row.redirect_to,
+            user_id: row.user_id,
+            nonce: row.nonce,
+            expires_at: row.expires_at,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct IdentityRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_id: String,
+    pub email: Option<String>,
+    pub identity_data: serde_json::Value,
+    pub last_signin_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<IdentityRow> for crate::domain::Identity {
+    fn from(row: IdentityRow) -> Self {
+        Self {
+            id: row.id,
+            project_id: row.project_id,
+            user_id: row.user_id,
+            provider: row.provider,
+            provider_id: row.provider_id,
+            email: row.email,
+            identity_data: row.identity_data,
+            last_signin_at: row.last_signin_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Clone, FromRow)]
+pub struct ProjectRow {
+    pub id: Uuid,
+    pub name: String,
+    pub settings: Value,
+    pub jwt_secret: Option<String>,
+    pub previous_jwt_secret: Option<String>,
+    pub previous_jwt_secret_expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<ProjectRow> for crate::domain::Project {
+    fn from(row: ProjectRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            settings: crate::domain::ProjectSettings::from_value(row.id, row.settings),
+            jwt_secret: row.jwt_secret,
+            retiring_jwt_secret: row.previous_jwt_secret_expires_at.map(|expires_at| {
+                crate::domain::RetiringJwtSecret {
+                    secret: row.previous_jwt_secret,
+                    expires_at,
+                }
+            }),
+        }
+    }
+}
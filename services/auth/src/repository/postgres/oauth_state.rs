@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::domain::OAuthState;
+use crate::error::AuthError;
+use crate::repository::traits::OAuthStateRepository;
+use super::models::OAuthStateRow;
+
+pub struct PostgresOAuthStateRepository {
+    pool: PgPool,
+}
+
+impl PostgresOAuthStateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OAuthStateRepository for PostgresOAuthStateRepository {
+    async fn create(&self, state: &OAuthState) -> Result<OAuthState, AuthError> {
+        let row = sqlx::query_as::<_, OAuthStateRow>(
+            r#"
+            INSERT INTO oauth_states (
+                id, project_id, provider, state_hash, code_verifier, redirect_to, user_id, nonce, expires_at,
+                created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(state.id)
+        .bind(state.project_id)
+        .bind(&state.provider)
+        .bind(&state.state_hash)
+        .bind(&state.code_verifier)
+        .bind(&state.redirect_to)
+        .bind(state.user_id)
+        .bind(&state.nonce)
+        .bind(state.expires_at)
+        .bind(state.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn take(&self, state_hash: &str) -> Result<Option<OAuthState>, AuthError> {
+        let row = sqlx::query_as::<_, OAuthStateRow>(
+            "DELETE FROM oauth_states WHERE state_hash = $1 AND expires_at > NOW() RETURNING *",
+        )
+        .bind(state_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn find(&self, state_hash: &str) -> Result<Option<OAuthState>, AuthError> {
+        let row = sqlx::query_as::<_, OAuthStateRow>(
+            "SELECT * FROM oauth_states WHERE state_hash = $1 AND expires_at > NOW()",
+        )
+        .bind(state_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn delete_expired(&self) -> Result<u64, AuthError> {
+        let result = sqlx::query("DELETE FROM oauth_states WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+}
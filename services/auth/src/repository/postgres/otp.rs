@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::OtpCode;
+use crate::error::AuthError;
+use crate::repository::traits::OtpRepository;
+use super::models::OtpRow;
+
+pub struct PostgresOtpRepository {
+    pool: PgPool,
+}
+
+impl PostgresOtpRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OtpRepository for PostgresOtpRepository {
+    async fn create(&self, otp: &OtpCode) -> Result<OtpCode, AuthError> {
+        let row = sqlx::query_as::<_, OtpRow>(
+            r#"
+            INSERT INTO otp_codes (
+                id, project_id, identifier, code_hash, channel, expires_at, used, created_at, attempts
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(otp.id)
+        .bind(otp.project_id)
+        .bind(&otp.identifier)
+        .bind(&otp.code_hash)
+        .bind(otp.channel.as_str())
+        .bind(otp.expires_at)
+        .bind(otp.used)
+        .bind(otp.created_at)
+        .bind(otp.attempts)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn find_latest(&self, project_id: Uuid, identifier: &str) -> Result<Option<OtpCode>, AuthError> {
+        let row = sqlx::query_as::<_, OtpRow>(
+            r#"
+            SELECT * FROM otp_codes
+            WHERE project_id = $1 AND identifier = $2
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(project_id)
+        .bind(identifier)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn record_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, AuthError> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE otp_codes SET attempts = attempts + 1
+            WHERE id = $1 AND used = false AND attempts < $2
+            RETURNING attempts
+            "#,
+        )
+        .bind(id)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)
+    }
+
+    async fn mark_used(&self, id: Uuid) -> Result<bool, AuthError> {
+        let result = sqlx::query("UPDATE otp_codes SET used = true WHERE id = $1 AND used = false")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::Project;
+use crate::error::AuthError;
+use crate::repository::traits::ProjectRepository;
+use super::models::ProjectRow;
+
+pub struct PostgresProjectRepository {
+    pool: PgPool,
+}
+
+impl PostgresProjectRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for PostgresProjectRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Project>, AuthError> {
+        let row = sqlx::query_as::<_, ProjectRow>(
+            "SELECT id, name, settings, jwt_secret, previous_jwt_secret, previous_jwt_secret_expires_at
+             FROM projects WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn set_jwt_secret(
+        &self,
+        id: Uuid,
+        secret: Option<&str>,
+        previous_expires_at: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        // Assignments all read the row as it was before the update
+        sqlx::query(
+            "UPDATE projects
+             SET previous_jwt_secret = jwt_secret,
+                 previous_jwt_secret_expires_at = $3,
+                 jwt_secret = $2,
+                 updated_at = NOW()
+             WHERE id = $1",
+        )
+            .bind(id)
+            .bind(secret)
+            .bind(previous_expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+        Ok(())
+    }
+}
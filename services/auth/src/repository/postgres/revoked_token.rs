@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::domain::RevokedToken;
+use crate::error::AuthError;
+use crate::repository::traits::RevokedTokenRepository;
+use super::models::RevokedTokenRow;
+
+pub struct PostgresRevokedTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresRevokedTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RevokedTokenRepository for PostgresRevokedTokenRepository {
+    async fn create(&self, token: &RevokedToken) -> Result<(), AuthError> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, project_id, expires_at, revoked_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(&token.jti)
+        .bind(token.user_id)
+        .bind(token.project_id)
+        .bind(token.expires_at)
+        .bind(token.revoked_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(())
+    }
+
+    async fn list_active(&self) -> Result<Vec<RevokedToken>, AuthError> {
+        let rows = sqlx::query_as::<_, RevokedTokenRow>(
+            "SELECT * FROM revoked_tokens WHERE expires_at > NOW()",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn delete_expired(&self) -> Result<u64, AuthError> {
+        let result = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::domain::Session;
 use crate::error::AuthError;
 use crate::repository::traits::SessionRepository;
+use crate::utils::crypto::hash_token;
 use super::models::SessionRow;
 
 pub struct PostgresSessionRepository {
@@ -23,23 +25,29 @@ impl SessionRepository for PostgresSessionRepository {
         let row = sqlx::query_as::<_, SessionRow>(
             r#"
             INSERT INTO sessions (
-                id, user_id, project_id, access_token, refresh_token,
-                ip_address, user_agent, created_at, expires_at, last_active_at, revoked
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                id, user_id, project_id, family_id, access_token_hash, refresh_token_hash,
+                ip_address, user_agent, device, browser, os,
+                created_at, expires_at, last_active_at, revoked, access_token_jti
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING *
             "#,
         )
         .bind(&session.id)
         .bind(session.user_id)
         .bind(session.project_id)
-        .bind(&session.access_token)
-        .bind(&session.refresh_token)
+        .bind(session.family_id)
+        .bind(&session.access_token_hash)
+        .bind(&session.refresh_token_hash)
         .bind(&session.ip_address)
         .bind(&session.user_agent)
+        .bind(&session.device)
+        .bind(&session.browser)
+        .bind(&session.os)
         .bind(session.created_at)
         .bind(session.expires_at)
         .bind(session.last_active_at)
         .bind(session.revoked)
+        .bind(&session.access_token_jti)
         .fetch_one(&self.pool)
         .await
         .map_err(AuthError::Database)?;
@@ -58,8 +66,8 @@ impl SessionRepository for PostgresSessionRepository {
     }
 
     async fn find_by_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, AuthError> {
-        let row = sqlx::query_as::<_, SessionRow>("SELECT * FROM sessions WHERE refresh_token = $1")
-            .bind(refresh_token)
+        let row = sqlx::query_as::<_, SessionRow>("SELECT * FROM sessions WHERE refresh_token_hash = $1")
+            .bind(hash_token(refresh_token))
             .fetch_optional(&self.pool)
             .await
             .map_err(AuthError::Database)?;
@@ -83,17 +91,19 @@ impl SessionRepository for PostgresSessionRepository {
         let row = sqlx::query_as::<_, SessionRow>(
             r#"
             UPDATE sessions SET
-                access_token = $2, refresh_token = $3, last_active_at = $4, revoked = $5, expires_at = $6
+                access_token_hash = $2, refresh_token_hash = $3, last_active_at = $4, revoked = $5, expires_at = $6,
+                access_token_jti = $7
             WHERE id = $1
             RETURNING *
             "#,
         )
         .bind(&session.id)
-        .bind(&session.access_token)
-        .bind(&session.refresh_token)
+        .bind(&session.access_token_hash)
+        .bind(&session.refresh_token_hash)
         .bind(session.last_active_at)
         .bind(session.revoked)
         .bind(session.expires_at)
+        .bind(&session.access_token_jti)
         .fetch_one(&self.pool)
         .await
         .map_err(AuthError::Database)?;
@@ -101,6 +111,93 @@ impl SessionRepository for PostgresSessionRepository {
         Ok(row.into())
     }
 
+    async fn touch(&self, id: &str) -> Result<(), AuthError> {
+        sqlx::query("UPDATE sessions SET last_active_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+        Ok(())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        session: &Session,
+        previous_refresh_token: &str,
+    ) -> Result<Option<Session>, AuthError> {
+        let previous_hash = hash_token(previous_refresh_token);
+        let mut tx = self.pool.begin().await.map_err(AuthError::Database)?;
+
+        // Only the holder of the current token wins; a concurrent replay updates nothing
+        let row = sqlx::query_as::<_, SessionRow>(
+            r#"
+            UPDATE sessions SET
+                access_token_hash = $3, refresh_token_hash = $4, last_active_at = $5, expires_at = $6,
+                access_token_jti = $7
+            WHERE id = $1 AND refresh_token_hash = $2 AND revoked = false
+            RETURNING *
+            "#,
+        )
+        .bind(&session.id)
+        .bind(&previous_hash)
+        .bind(&session.access_token_hash)
+        .bind(&session.refresh_token_hash)
+        .bind(session.last_active_at)
+        .bind(session.expires_at)
+        .bind(&session.access_token_jti)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AuthError::Database)?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO rotated_refresh_tokens (refresh_token_hash, session_id, family_id)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(&previous_hash)
+        .bind(&session.id)
+        .bind(session.family_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthError::Database)?;
+
+        tx.commit().await.map_err(AuthError::Database)?;
+
+        Ok(Some(row.into()))
+    }
+
+    async fn find_by_rotated_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, AuthError> {
+        let row = sqlx::query_as::<_, SessionRow>(
+            r#"
+            SELECT s.* FROM sessions s
+            INNER JOIN rotated_refresh_tokens rt ON rt.session_id = s.id
+            WHERE rt.refresh_token_hash = $1
+            "#,
+        )
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.map(|r| r.into()))
+    }
+
+    async fn revoke_family(&self, family_id: Uuid) -> Result<Vec<Session>, AuthError> {
+        let rows = sqlx::query_as::<_, SessionRow>(
+            "UPDATE sessions SET revoked = true WHERE family_id = $1 AND revoked = false RETURNING *"
+        )
+        .bind(family_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
     async fn delete(&self, id: &str) -> Result<(), AuthError> {
         sqlx::query("DELETE FROM sessions WHERE id = $1")
             .bind(id)
@@ -128,4 +225,24 @@ impl SessionRepository for PostgresSessionRepository {
         .map_err(AuthError::Database)?;
         Ok(result.rows_affected())
     }
+
+    async fn delete_ended_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let result = sqlx::query(
+            "DELETE FROM sessions WHERE expires_at < $1 OR (revoked = true AND last_active_at < $1)"
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_rotated_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let result = sqlx::query("DELETE FROM rotated_refresh_tokens WHERE rotated_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+        Ok(result.rows_affected())
+    }
 }
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::domain::SigningKeyRecord;
+use crate::error::AuthError;
+use crate::repository::traits::SigningKeyRepository;
+use super::models::SigningKeyRow;
+
+pub struct PostgresSigningKeyRepository {
+    pool: PgPool,
+}
+
+impl PostgresSigningKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SigningKeyRepository for PostgresSigningKeyRepository {
+    async fn create(&self, key: &SigningKeyRecord) -> Result<SigningKeyRecord, AuthError> {
+        let row = sqlx::query_as::<_, SigningKeyRow>(
+            r#"
+            INSERT INTO signing_keys (kid, algorithm, private_key, created_at, activates_at, retired_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(&key.kid)
+        .bind(&key.algorithm)
+        .bind(&key.private_key)
+        .bind(key.created_at)
+        .bind(key.activates_at)
+        .bind(key.retired_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn find_by_kid(&self, kid: &str) -> Result<Option<SigningKeyRecord>, AuthError> {
+        let row = sqlx::query_as::<_, SigningKeyRow>("SELECT * FROM signing_keys WHERE kid = $1")
+            .bind(kid)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+
+        Ok(row.map(Into::into))
+    }
+
+    async fn list(&self) -> Result<Vec<SigningKeyRecord>, AuthError> {
+        let rows = sqlx::query_as::<_, SigningKeyRow>(
+            "SELECT * FROM signing_keys ORDER BY activates_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn retire(&self, kid: &str) -> Result<(), AuthError> {
+        sqlx::query("UPDATE signing_keys SET retired_at = NOW() WHERE kid = $1 AND retired_at IS NULL")
+            .bind(kid)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+        Ok(())
+    }
+}
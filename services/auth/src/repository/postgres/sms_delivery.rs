@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::sms::FINAL_SMS_STATUSES;
+use crate::domain::SmsDelivery;
+use crate::error::AuthError;
+use crate::repository::traits::SmsDeliveryRepository;
+use super::models::SmsDeliveryRow;
+
+pub struct PostgresSmsDeliveryRepository {
+    pool: PgPool,
+}
+
+impl PostgresSmsDeliveryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SmsDeliveryRepository for PostgresSmsDeliveryRepository {
+    async fn create(&self, delivery: &SmsDelivery) -> Result<SmsDelivery, AuthError> {
+        let row = sqlx::query_as::<_, SmsDeliveryRow>(
+            r#"
+            INSERT INTO sms_deliveries (
+                id, provider, provider_message_id, recipient, status, error_code, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(&delivery.provider)
+        .bind(&delivery.provider_message_id)
+        .bind(&delivery.recipient)
+        .bind(&delivery.status)
+        .bind(&delivery.error_code)
+        .bind(delivery.created_at)
+        .bind(delivery.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(row.into())
+    }
+
+    async fn update_status(
+        &self,
+        provider: &str,
+        provider_message_id: &str,
+        status: &str,
+        error_code: Option<&str>,
+    ) -> Result<bool, AuthError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sms_deliveries SET status = $3, error_code = $4, updated_at = NOW()
+            WHERE provider = $1 AND provider_message_id = $2 AND status <> ALL($5)
+            "#,
+        )
+        .bind(provider)
+        .bind(provider_message_id)
+        .bind(status)
+        .bind(error_code)
+        .bind(FINAL_SMS_STATUSES)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthError::Database)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_before(&self, cutoff: DateTime<Utc>) -> Result<u64, AuthError> {
+        let result = sqlx::query("DELETE FROM sms_deliveries WHERE created_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthError::Database)?;
+        Ok(result.rows_affected())
+    }
+}
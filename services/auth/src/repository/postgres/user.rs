@@ -25,9 +25,9 @@ impl UserRepository for PostgresUserRepository {
             r#"
             INSERT INTO users (
                 id, project_id, email, email_verified, phone, phone_verified,
-                password_hash, metadata, mfa_enabled, mfa_secret, mfa_backup_codes,
+                password_hash, metadata, app_metadata, mfa_enabled, mfa_secret, mfa_backup_codes,
                 banned, created_at, updated_at, last_signin_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING *
             "#,
         )
@@ -39,6 +39,7 @@ impl UserRepository for PostgresUserRepository {
         .bind(user.phone_verified)
         .bind(&user.password_hash)
         .bind(&user.metadata)
+        .bind(&user.app_metadata)
         .bind(user.mfa_enabled)
         .bind(&user.mfa_secret)
         .bind(&user.mfa_backup_codes)
@@ -94,8 +95,9 @@ impl UserRepository for PostgresUserRepository {
             r#"
             UPDATE users SET
                 email = $2, email_verified = $3, phone = $4, phone_verified = $5,
-                password_hash = $6, metadata = $7, mfa_enabled = $8, mfa_secret = $9,
-                mfa_backup_codes = $10, banned = $11, updated_at = $12, last_signin_at = $13
+                password_hash = $6, metadata = $7, app_metadata = $8, mfa_enabled = $9,
+                mfa_secret = $10, mfa_backup_codes = $11, banned = $12, updated_at = $13,
+                last_signin_at = $14
             WHERE id = $1
             RETURNING *
             "#,
@@ -107,6 +109,7 @@ impl UserRepository for PostgresUserRepository {
         .bind(user.phone_verified)
         .bind(&user.password_hash)
         .bind(&user.metadata)
+        .bind(&user.app_metadata)
         .bind(user.mfa_enabled)
         .bind(&user.mfa_secret)
         .bind(&user.mfa_backup_codes)
@@ -4,7 +4,7 @@ use uuid::Uuid;
 
 use crate::domain::Role;
 use crate::error::AuthError;
-use crate::repository::traits::{RoleRepository, UserRoleRepository};
+use crate::repository::traits::UserRoleRepository;
 
 pub struct PostgresUserRoleRepository {
     pool: PgPool,
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::domain::{Session, User, Role};
+use crate::domain::{Identity, MagicLink, OAuthState, OtpCode, Project, RevokedToken, Session, SigningKeyRecord, SmsDelivery, User, Role};
 
 #[async_trait]
 pub trait UserRepository: Send + Sync {
@@ -21,9 +22,22 @@ pub trait SessionRepository: Send + Sync {
     async fn find_by_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, crate::error::AuthError>;
     async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<Session>, crate::error::AuthError>;
     async fn update(&self, session: &Session) -> Result<Session, crate::error::AuthError>;
+    /// Record activity on the session without rewriting its tokens
+    async fn touch(&self, id: &str) -> Result<(), crate::error::AuthError>;
+    /// Swap in `session.refresh_token_hash` only if `previous_refresh_token` is still current,
+    /// keeping the previous token on record. Returns `None` if it was already rotated.
+    async fn rotate_refresh_token(&self, session: &Session, previous_refresh_token: &str) -> Result<Option<Session>, crate::error::AuthError>;
+    /// Find the session a superseded refresh token was rotated out of
+    async fn find_by_rotated_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>, crate::error::AuthError>;
+    /// Revoke every active session of the family, returning the sessions revoked
+    async fn revoke_family(&self, family_id: Uuid) -> Result<Vec<Session>, crate::error::AuthError>;
     async fn delete(&self, id: &str) -> Result<(), crate::error::AuthError>;
     async fn delete_by_user_id(&self, user_id: Uuid) -> Result<(), crate::error::AuthError>;
     async fn revoke_expired(&self) -> Result<u64, crate::error::AuthError>;
+    /// Delete sessions that expired, or were revoked and left unused, before `cutoff`
+    async fn delete_ended_before(&self, cutoff: DateTime<Utc>) -> Result<u64, crate::error::AuthError>;
+    /// Forget refresh tokens superseded before `cutoff`
+    async fn delete_rotated_before(&self, cutoff: DateTime<Utc>) -> Result<u64, crate::error::AuthError>;
 }
 
 #[async_trait]
@@ -43,3 +57,101 @@ pub trait UserRoleRepository: Send + Sync {
     async fn get_user_roles(&self, user_id: Uuid) -> Result<Vec<Role>, crate::error::AuthError>;
     async fn has_role(&self, user_id: Uuid, role_name: &str) -> Result<bool, crate::error::AuthError>;
 }
+
+#[async_trait]
+pub trait SigningKeyRepository: Send + Sync {
+    async fn create(&self, key: &SigningKeyRecord) -> Result<SigningKeyRecord, crate::error::AuthError>;
+    async fn find_by_kid(&self, kid: &str) -> Result<Option<SigningKeyRecord>, crate::error::AuthError>;
+    /// Every key including retired ones, newest activation first
+    async fn list(&self) -> Result<Vec<SigningKeyRecord>, crate::error::AuthError>;
+    async fn retire(&self, kid: &str) -> Result<(), crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait ProjectRepository: Send + Sync {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Project>, crate::error::AuthError>;
+    /// Replace the project's secret, keeping the previous signer until `previous_expires_at`
+    async fn set_jwt_secret(
+        &self,
+        id: Uuid,
+        secret: Option<&str>,
+        previous_expires_at: DateTime<Utc>,
+    ) -> Result<(), crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait RevokedTokenRepository: Send + Sync {
+    /// Record a revocation; revoking the same `jti` twice is a no-op
+    async fn create(&self, token: &RevokedToken) -> Result<(), crate::error::AuthError>;
+    /// Revocations whose tokens have not expired yet
+    async fn list_active(&self) -> Result<Vec<RevokedToken>, crate::error::AuthError>;
+    async fn delete_expired(&self) -> Result<u64, crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait OtpRepository: Send + Sync {
+    async fn create(&self, otp: &OtpCode) -> Result<OtpCode, crate::error::AuthError>;
+    /// The code most recently sent to `identifier`, usable or not; older codes are superseded
+    async fn find_latest(&self, project_id: Uuid, identifier: &str) -> Result<Option<OtpCode>, crate::error::AuthError>;
+    /// Count an attempt against an unused code that has had fewer than `max_attempts`.
+    /// Returns the new count, or `None` if no attempt is left.
+    async fn record_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, crate::error::AuthError>;
+    /// Mark the code used. Returns `false` if another request used it first.
+    async fn mark_used(&self, id: Uuid) -> Result<bool, crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait MagicLinkRepository: Send + Sync {
+    async fn create(&self, link: &MagicLink) -> Result<MagicLink, crate::error::AuthError>;
+    async fn find_by_token_hash(&self, token_hash: &str) -> Result<Option<MagicLink>, crate::error::AuthError>;
+    async fn find_by_poll_token_hash(&self, poll_token_hash: &str) -> Result<Option<MagicLink>, crate::error::AuthError>;
+    /// Record that a cross-device link was opened. Returns `false` if it was already
+    /// approved or used.
+    async fn approve(&self, id: Uuid) -> Result<bool, crate::error::AuthError>;
+    /// Count an approval attempt against an unused, unapproved link that has had fewer
+    /// than `max_attempts`. Returns the new count, or `None` if no attempt is left.
+    async fn record_approval_attempt(&self, id: Uuid, max_attempts: i32) -> Result<Option<i32>, crate::error::AuthError>;
+    /// Mark the link used. Returns `false` if another request used it first.
+    async fn mark_used(&self, id: Uuid) -> Result<bool, crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait SmsDeliveryRepository: Send + Sync {
+    async fn create(&self, delivery: &SmsDelivery) -> Result<SmsDelivery, crate::error::AuthError>;
+    /// Apply a status callback. Callbacks can arrive out of order, so a final status
+    /// is never overwritten. Returns `false` if nothing was updated.
+    async fn update_status(
+        &self,
+        provider: &str,
+        provider_message_id: &str,
+        status: &str,
+        error_code: Option<&str>,
+    ) -> Result<bool, crate::error::AuthError>;
+    async fn delete_before(&self, cutoff: DateTime<Utc>) -> Result<u64, crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait OAuthStateRepository: Send + Sync {
+    async fn create(&self, state: &OAuthState) -> Result<OAuthState, crate::error::AuthError>;
+    /// Remove and return the unexpired request with this state, so it can be used only once
+    async fn take(&self, state_hash: &str) -> Result<Option<OAuthState>, crate::error::AuthError>;
+    /// The unexpired request with this state, left in place
+    async fn find(&self, state_hash: &str) -> Result<Option<OAuthState>, crate::error::AuthError>;
+    async fn delete_expired(&self) -> Result<u64, crate::error::AuthError>;
+}
+
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    /// Fails with `IdentityAlreadyLinked` if the provider account is linked already
+    async fn create(&self, identity: &Identity) -> Result<Identity, crate::error::AuthError>;
+    async fn find_by_provider_id(
+        &self,
+        project_id: Uuid,
+        provider: &str,
+        provider_id: &str,
+    ) -> Result<Option<Identity>, crate::error::AuthError>;
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Identity>, crate::error::AuthError>;
+    async fn update(&self, identity: &Identity) -> Result<Identity, crate::error::AuthError>;
+    /// Returns `false` if the user has no such identity
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, crate::error::AuthError>;
+}
@@ -1,36 +1,138 @@
 use chrono::{Duration, Utc};
+use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::domain::{Session, User};
+use crate::domain::{
+    flatten_permissions, AccessToken, ClientInfo, Identity, OtpChannel, Project, ProjectSettings, Session, SessionLimitPolicy,
+    SessionSettings, TokenPair, TokenSettings, User,
+};
 use crate::error::AuthError;
-use crate::repository::traits::{SessionRepository, UserRepository};
-use crate::services::{PasswordService, TokenService};
-use crate::utils::crypto::{generate_session_id, generate_refresh_token};
+use crate::repository::traits::{
+    IdentityRepository, ProjectRepository, RevokedTokenRepository, SessionRepository, UserRepository,
+    UserRoleRepository,
+};
+use crate::services::webhook_service::{EventPublisher, WebhookService, EVENT_REFRESH_TOKEN_REUSED};
+use crate::services::token_service::Claims;
+use crate::services::oauth_userinfo::OAuthUserInfo;
+use crate::services::passwordless_service::normalize_identifier;
+use crate::services::{ClaimsHook, DenylistService, PasswordService, TokenService};
+use crate::utils::crypto::generate_session_id;
 
-pub struct AuthService<UR: UserRepository, SR: SessionRepository> {
+/// Minimum gap between writes of request activity to `last_active_at`
+const ACTIVITY_INTERVAL_SECONDS: i64 = 60;
+
+/// An active token presented for introspection or revocation
+pub enum IntrospectedToken {
+    Access { claims: Box<Claims>, session: Session },
+    Refresh { session: Session },
+}
+
+impl IntrospectedToken {
+    pub fn session(&self) -> &Session {
+        match self {
+            IntrospectedToken::Access { session, .. } | IntrospectedToken::Refresh { session } => session,
+        }
+    }
+}
+
+pub struct AuthService<UR, SR, PR, RR, TR, IR>
+where
+    UR: UserRepository,
+    SR: SessionRepository,
+    PR: ProjectRepository,
+    RR: UserRoleRepository,
+    TR: RevokedTokenRepository,
+    IR: IdentityRepository,
+{
     user_repo: UR,
     session_repo: SR,
+    project_repo: PR,
+    user_role_repo: RR,
+    identity_repo: IR,
     token_service: TokenService,
-    refresh_token_expiry_seconds: u64,
+    denylist: Arc<DenylistService<TR>>,
+    claims_hook: ClaimsHook,
+    events: Option<Arc<dyn EventPublisher>>,
 }
 
-impl<UR: UserRepository, SR: SessionRepository> AuthService<UR, SR> {
-    pub fn new(user_repo: UR, session_repo: SR, token_service: TokenService, refresh_token_expiry_seconds: u64) -> Self {
+impl<UR, SR, PR, RR, TR, IR> AuthService<UR, SR, PR, RR, TR, IR>
+where
+    UR: UserRepository,
+    SR: SessionRepository,
+    PR: ProjectRepository,
+    RR: UserRoleRepository,
+    TR: RevokedTokenRepository,
+    IR: IdentityRepository,
+{
+    pub fn new(
+        user_repo: UR,
+        session_repo: SR,
+        project_repo: PR,
+        user_role_repo: RR,
+        identity_repo: IR,
+        token_service: TokenService,
+        denylist: Arc<DenylistService<TR>>,
+    ) -> Self {
         Self {
             user_repo,
             session_repo,
+            project_repo,
+            user_role_repo,
+            identity_repo,
             token_service,
-            refresh_token_expiry_seconds,
+            denylist,
+            claims_hook: ClaimsHook::new(),
+            events: None,
         }
     }
 
+    /// Publish security events (e.g. refresh token reuse) through `events`
+    pub fn with_events(mut self, events: Arc<dyn EventPublisher>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Token issuer, audience, lifetimes and signing secret for a project
+    pub async fn token_settings(&self, project_id: Uuid) -> Result<TokenSettings, AuthError> {
+        let project = self.find_project(project_id).await?;
+        self.token_service.settings_for(&project)
+    }
+
+    /// Stored settings for a project, such as its redirect allowlist and SMS providers
+    pub async fn project_settings(&self, project_id: Uuid) -> Result<ProjectSettings, AuthError> {
+        let project = self.find_project(project_id).await?;
+        Ok(project.settings)
+    }
+
+    async fn find_project(&self, project_id: Uuid) -> Result<Project, AuthError> {
+        self.project_repo
+            .find_by_id(project_id)
+            .await?
+            .ok_or(AuthError::ProjectNotFound)
+    }
+
+    /// Role names and flattened permissions embedded in the user's access tokens
+    pub async fn grants(&self, user_id: Uuid) -> Result<(Vec<String>, Vec<String>), AuthError> {
+        let roles = self.user_role_repo.get_user_roles(user_id).await?;
+        let permissions = flatten_permissions(&roles);
+
+        let mut names: Vec<String> = roles.into_iter().map(|role| role.name).collect();
+        names.sort();
+        names.dedup();
+
+        Ok((names, permissions))
+    }
+
     pub async fn signup(
         &self,
         project_id: Uuid,
         email: &str,
         password: &str,
         metadata: Option<serde_json::Value>,
-    ) -> Result<(User, Session), AuthError> {
+        client: ClientInfo,
+    ) -> Result<(User, Session, TokenPair), AuthError> {
+        let settings = self.token_settings(project_id).await?;
+
         // Check if user exists
         if self.user_repo.find_by_email(project_id, email).await?.is_some() {
             return Err(AuthError::UserExists);
@@ -56,9 +158,9 @@ impl<UR: UserRepository, SR: SessionRepository> AuthService<UR, SR> {
         let user = self.user_repo.create(&user).await?;
 
         // Create session
-        let session = self.create_session(&user, None, None).await?;
+        let (session, tokens) = self.create_session(&user, &settings, client).await?;
 
-        Ok((user, session))
+        Ok((user, session, tokens))
     }
 
     pub async fn signin(
@@ -66,9 +168,8 @@ impl<UR: UserRepository, SR: SessionRepository> AuthService<UR, SR> {
         project_id: Uuid,
         email: &str,
         password: &str,
-        ip_address: Option<String>,
-        user_agent: Option<String>,
-    ) -> Result<(User, Session), AuthError> {
+        client: ClientInfo,
+    ) -> Result<(User, Session, TokenPair), AuthError> {
         // Find user
         let mut user = self.user_repo
             .find_by_email(project_id, email)
@@ -94,36 +195,385 @@ impl<UR: UserRepository, SR: SessionRepository> AuthService<UR, SR> {
         let user = self.user_repo.update(&user).await?;
 
         // Create session
-        let session = self.create_session(&user, ip_address, user_agent).await?;
+        let settings = self.token_settings(project_id).await?;
+        let (session, tokens) = self.create_session(&user, &settings, client).await?;
+
+        Ok((user, session, tokens))
+    }
+
+    /// Sign in the owner of an email address or phone number whose control was just
+    /// proven, creating the user on first sign-in
+    pub async fn signin_passwordless(
+        &self,
+        project_id: Uuid,
+        channel: OtpChannel,
+        identifier: &str,
+        client: ClientInfo,
+    ) -> Result<(User, Session, TokenPair), AuthError> {
+        let settings = self.token_settings(project_id).await?;
+
+        let existing = match channel {
+            OtpChannel::Email => self.user_repo.find_by_email(project_id, identifier).await?,
+            OtpChannel::Sms => self.user_repo.find_by_phone(project_id, identifier).await?,
+        };
+        let mut user = match existing {
+            Some(user) => user,
+            None => {
+                let user = match channel {
+                    OtpChannel::Email => User::new(project_id, identifier.to_string()),
+                    OtpChannel::Sms => User::with_phone_only(project_id, identifier.to_string()),
+                };
+                self.user_repo.create(&user).await?
+            }
+        };
+
+        if user.banned {
+            return Err(AuthError::Forbidden);
+        }
+
+        match channel {
+            OtpChannel::Email => user.verify_email(),
+            OtpChannel::Sms => user.verify_phone(),
+        }
+        user.update_last_signin();
+        let user = self.user_repo.update(&user).await?;
+
+        let (session, tokens) = self.create_session(&user, &settings, client).await?;
+        Ok((user, session, tokens))
+    }
+
+    /// Sign in with a provider account. An account linked before signs in as its user;
+    /// otherwise it is linked to the user with the same address, if both the provider
+    /// and the user have verified it, or to a new user.
+    pub async fn signin_oauth(
+        &self,
+        project_id: Uuid,
+        provider: &str,
+        profile: &OAuthUserInfo,
+        client: ClientInfo,
+    ) -> Result<(User, Session, TokenPair), AuthError> {
+        let settings = self.token_settings(project_id).await?;
+
+        let identity = self.identity_repo
+            .find_by_provider_id(project_id, provider, &profile.provider_id)
+            .await?;
+        let mut user = match &identity {
+            Some(identity) => self.user_repo
+                .find_by_id(identity.user_id)
+                .await?
+                .ok_or(AuthError::UserNotFound)?,
+            None => self.find_or_create_oauth_user(project_id, profile).await?,
+        };
 
-        Ok((user, session))
+        if user.banned {
+            return Err(AuthError::Forbidden);
+        }
+
+        match identity {
+            Some(mut identity) => {
+                identity.record_signin(profile.email.clone(), identity_data(profile));
+                self.identity_repo.update(&identity).await?;
+            }
+            None => {
+                self.identity_repo.create(&new_identity(project_id, user.id, provider, profile)).await?;
+            }
+        }
+
+        let vouched = profile.email_verified
+            && profile.email.as_deref()
+                .zip(user.email.as_deref())
+                .is_some_and(|(theirs, ours)| theirs.eq_ignore_ascii_case(ours));
+        if vouched {
+            user.verify_email();
+        }
+        user.update_last_signin();
+        let user = self.user_repo.update(&user).await?;
+
+        let (session, tokens) = self.create_session(&user, &settings, client).await?;
+        Ok((user, session, tokens))
+    }
+
+    /// The user with the provider's address, or a new user with that address. An
+    /// existing user is only signed into when both the provider and the user have
+    /// verified it; an unverified account may have been registered by someone else
+    /// in anticipation, so it has to link the provider explicitly once signed in.
+    async fn find_or_create_oauth_user(&self, project_id: Uuid, profile: &OAuthUserInfo) -> Result<User, AuthError> {
+        let email = profile
+            .email
+            .as_deref()
+            .ok_or_else(|| AuthError::OAuth("the provider did not share an email address".to_string()))?;
+        let email = normalize_identifier(OtpChannel::Email, email)?;
+
+        match self.user_repo.find_by_email(project_id, &email).await? {
+            Some(user) if profile.email_verified && user.email_verified => Ok(user),
+            Some(_) => Err(AuthError::UserExists),
+            None => {
+                let user = User::new(project_id, email).with_metadata(profile_metadata(profile));
+                self.user_repo.create(&user).await
+            }
+        }
+    }
+
+    /// Let a provider account sign in as a signed-in user. Linking an account the
+    /// user already has refreshes its profile.
+    pub async fn link_identity(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+        provider: &str,
+        profile: &OAuthUserInfo,
+    ) -> Result<Identity, AuthError> {
+        match self.identity_repo.find_by_provider_id(project_id, provider, &profile.provider_id).await? {
+            Some(mut identity) if identity.user_id == user_id => {
+                identity.record_signin(profile.email.clone(), identity_data(profile));
+                self.identity_repo.update(&identity).await
+            }
+            Some(_) => Err(AuthError::IdentityAlreadyLinked),
+            None => self.identity_repo.create(&new_identity(project_id, user_id, provider, profile)).await,
+        }
+    }
+
+    pub async fn list_identities(&self, user_id: Uuid) -> Result<Vec<Identity>, AuthError> {
+        self.identity_repo.list_by_user(user_id).await
+    }
+
+    /// Unlink one of the user's identities, unless nothing else could sign them in:
+    /// a password, a verified address or number for passwordless sign-in, or another
+    /// identity
+    pub async fn unlink_identity(&self, user_id: Uuid, identity_id: Uuid) -> Result<(), AuthError> {
+        let user = self.user_repo.find_by_id(user_id).await?.ok_or(AuthError::UserNotFound)?;
+        let identities = self.identity_repo.list_by_user(user_id).await?;
+        if !identities.iter().any(|identity| identity.id == identity_id) {
+            return Err(AuthError::IdentityNotFound);
+        }
+
+        let other_methods = [
+            user.password_hash.is_some(),
+            user.email.is_some() && user.email_verified,
+            user.phone.is_some() && user.phone_verified,
+            identities.len() > 1,
+        ];
+        if !other_methods.contains(&true) {
+            return Err(AuthError::LastSignInMethod);
+        }
+
+        if !self.identity_repo.delete(user_id, identity_id).await? {
+            return Err(AuthError::IdentityNotFound);
+        }
+        Ok(())
     }
 
     pub async fn signout(&self, session_id: &str) -> Result<(), AuthError> {
-        let mut session = self.session_repo
+        let session = self.session_repo
+            .find_by_id(session_id)
+            .await?
+            .ok_or(AuthError::SessionNotFound)?;
+
+        self.end_sessions(vec![session]).await?;
+        Ok(())
+    }
+
+    /// The user's sessions that are still active, newest first
+    pub async fn list_sessions(&self, project_id: Uuid, user_id: Uuid) -> Result<Vec<Session>, AuthError> {
+        let settings = self.token_settings(project_id).await?;
+        let mut sessions = self.active_sessions(user_id, &settings.sessions).await?;
+
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    /// The live session behind verified access token claims. Sessions past the project's
+    /// idle timeout or lifetime are ended; otherwise the request counts as activity.
+    pub async fn authenticate_session(&self, claims: &Claims, settings: &TokenSettings) -> Result<Session, AuthError> {
+        let session = self.session_repo
+            .find_by_id(&claims.sid)
+            .await?
+            .filter(|session| !session.is_expired() && session.user_id == claims.sub)
+            .ok_or(AuthError::InvalidToken)?;
+
+        if !session.is_active(&settings.sessions) {
+            self.end_sessions(vec![session]).await?;
+            return Err(AuthError::InvalidToken);
+        }
+
+        // Without a timeout nothing reads last_active_at closely, so spare the writes
+        let stale = Utc::now() - session.last_active_at > Duration::seconds(ACTIVITY_INTERVAL_SECONDS);
+        if settings.sessions.idle_timeout_seconds.is_some() && stale {
+            self.session_repo.touch(&session.id).await?;
+        }
+
+        Ok(session)
+    }
+
+    /// Revoke one of the user's sessions. Sessions of other users are reported as
+    /// missing rather than forbidden, so their ids cannot be probed.
+    pub async fn revoke_session(&self, user_id: Uuid, session_id: &str) -> Result<(), AuthError> {
+        let session = self.session_repo
             .find_by_id(session_id)
             .await?
+            .filter(|session| session.user_id == user_id && !session.revoked)
             .ok_or(AuthError::SessionNotFound)?;
 
-        session.revoke();
-        self.session_repo.update(&session).await?;
+        self.end_sessions(vec![session]).await?;
+        Ok(())
+    }
+
+    /// Revoke every active session of the user, optionally keeping the caller's own
+    pub async fn signout_everywhere(&self, user_id: Uuid, keep_session_id: Option<&str>) -> Result<u64, AuthError> {
+        let sessions = self.session_repo
+            .find_by_user_id(user_id)
+            .await?
+            .into_iter()
+            .filter(|session| Some(session.id.as_str()) != keep_session_id)
+            .collect();
+
+        self.end_sessions(sessions).await
+    }
+
+    /// Ban or unban a user of the project; banning ends all of their sessions
+    pub async fn set_banned(&self, project_id: Uuid, user_id: Uuid, banned: bool) -> Result<User, AuthError> {
+        let mut user = self.user_repo
+            .find_by_id(user_id)
+            .await?
+            .filter(|user| user.project_id == project_id)
+            .ok_or(AuthError::UserNotFound)?;
+
+        if banned {
+            user.ban();
+        } else {
+            user.unban();
+        }
+        let user = self.user_repo.update(&user).await?;
+
+        if banned {
+            self.signout_everywhere(user.id, None).await?;
+        }
+
+        Ok(user)
+    }
+
+    /// Replace the admin-set metadata that token claims templates read from.
+    /// Picked up by the user's next refresh.
+    pub async fn set_app_metadata(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+        app_metadata: serde_json::Value,
+    ) -> Result<User, AuthError> {
+        let mut user = self.user_repo
+            .find_by_id(user_id)
+            .await?
+            .filter(|user| user.project_id == project_id)
+            .ok_or(AuthError::UserNotFound)?;
+
+        user.set_app_metadata(app_metadata);
+        self.user_repo.update(&user).await
+    }
+
+    /// Change the password after checking the current one. Every other session is ended,
+    /// so a stolen token does not survive the change.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        session_id: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), AuthError> {
+        let mut user = self.user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+
+        let hash = user.password_hash.as_deref().ok_or(AuthError::InvalidCredentials)?;
+        if !PasswordService::verify_password(current_password, hash)? {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        crate::utils::validation::validate_password(new_password, "new_password")
+            .map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+
+        user = user.with_password(PasswordService::hash_password(new_password)?);
+        user.updated_at = Utc::now();
+        self.user_repo.update(&user).await?;
 
+        self.signout_everywhere(user.id, Some(session_id)).await?;
         Ok(())
     }
 
+    async fn active_sessions(&self, user_id: Uuid, limits: &SessionSettings) -> Result<Vec<Session>, AuthError> {
+        Ok(self.session_repo
+            .find_by_user_id(user_id)
+            .await?
+            .into_iter()
+            .filter(|session| session.is_active(limits))
+            .collect())
+    }
+
+    /// Apply the project's concurrent session cap before the user gets another session
+    async fn make_room_for_session(&self, user_id: Uuid, limits: &SessionSettings) -> Result<(), AuthError> {
+        let Some(max) = limits.max_concurrent.filter(|&max| max > 0) else {
+            return Ok(());
+        };
+
+        let mut sessions = self.active_sessions(user_id, limits).await?;
+        if sessions.len() < max {
+            return Ok(());
+        }
+
+        match limits.on_limit {
+            SessionLimitPolicy::RejectNew => Err(AuthError::SessionLimitReached),
+            SessionLimitPolicy::EvictOldest => {
+                sessions.sort_by_key(|session| session.created_at);
+                sessions.truncate(sessions.len() + 1 - max);
+                self.end_sessions(sessions).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Revoke the sessions and deny their access tokens right away
+    async fn end_sessions(&self, sessions: Vec<Session>) -> Result<u64, AuthError> {
+        let mut ended = Vec::with_capacity(sessions.len());
+        for mut session in sessions {
+            session.revoke();
+            ended.push(self.session_repo.update(&session).await?);
+        }
+
+        self.denylist.revoke_sessions(&ended).await?;
+        Ok(ended.len() as u64)
+    }
+
     pub async fn refresh_token(
         &self,
+        project_id: Uuid,
         refresh_token: &str,
-    ) -> Result<Session, AuthError> {
-        let mut session = self.session_repo
-            .find_by_refresh_token(refresh_token)
-            .await?
-            .ok_or(AuthError::InvalidToken)?;
+    ) -> Result<(User, Session, TokenPair), AuthError> {
+        let mut session = match self.session_repo.find_by_refresh_token(refresh_token).await? {
+            Some(session) => session,
+            None => {
+                // A superseded token coming back means it leaked: burn the whole family
+                if let Some(session) = self.session_repo.find_by_rotated_refresh_token(refresh_token).await? {
+                    self.revoke_token_family(&session).await?;
+                    return Err(AuthError::TokenReused);
+                }
+                return Err(AuthError::InvalidToken);
+            }
+        };
+
+        if session.project_id != project_id {
+            return Err(AuthError::InvalidToken);
+        }
 
         if session.is_expired() {
             return Err(AuthError::TokenExpired);
         }
 
+        let settings = self.token_settings(project_id).await?;
+        if !session.is_active(&settings.sessions) {
+            self.end_sessions(vec![session]).await?;
+            return Err(AuthError::TokenExpired);
+        }
+
         // Get user
         let user = self.user_repo
             .find_by_id(session.user_id)
@@ -135,70 +585,390 @@ impl<UR: UserRepository, SR: SessionRepository> AuthService<UR, SR> {
         }
 
         // Generate new tokens
-        let access_token = self.token_service.generate_access_token(
-            user.id,
-            user.project_id,
-            vec![], // TODO: Load roles
-            vec![], // TODO: Load permissions
-        )?;
+        // Role and claims changes take effect on the next refresh
+        let access_token = self.mint_access_token(&settings, &user, &session.id).await?;
 
-        let refresh_token = self.token_service.generate_refresh_token();
+        let new_refresh_token = self.token_service.generate_refresh_token(&settings);
 
-        // Update session
-        session.access_token = access_token.token;
-        session.refresh_token = refresh_token.token;
+        // Rotate session tokens
+        session.rotate_tokens(&access_token, &new_refresh_token.token);
         session.expires_at = Utc::now() + Duration::seconds(
-            self.refresh_token_expiry_seconds as i64
+            settings.refresh_token_ttl_seconds as i64
         );
+        if let Some(end) = session.lifetime_ends_at(&settings.sessions) {
+            session.expires_at = session.expires_at.min(end);
+        }
         session.update_last_active();
 
-        let session = self.session_repo.update(&session).await?;
+        match self.session_repo.rotate_refresh_token(&session, refresh_token).await? {
+            Some(session) => Ok((user, session, TokenPair {
+                access_token,
+                refresh_token: new_refresh_token,
+            })),
+            None => {
+                // Another request rotated this token first, so it was presented twice
+                self.revoke_token_family(&session).await?;
+                Err(AuthError::TokenReused)
+            }
+        }
+    }
 
-        Ok(session)
+    /// Resolve an access or refresh token issued to the project, if it is still active.
+    /// The hint only decides which kind is tried first (RFC 7662).
+    pub async fn introspect(
+        &self,
+        project_id: Uuid,
+        token: &str,
+        token_type_hint: Option<&str>,
+    ) -> Result<Option<IntrospectedToken>, AuthError> {
+        if token_type_hint == Some("refresh_token") {
+            if let Some(found) = self.introspect_refresh_token(project_id, token).await? {
+                return Ok(Some(found));
+            }
+            return self.introspect_access_token(project_id, token).await;
+        }
+
+        if let Some(found) = self.introspect_access_token(project_id, token).await? {
+            return Ok(Some(found));
+        }
+        self.introspect_refresh_token(project_id, token).await
+    }
+
+    /// Revoke the session behind an access or refresh token. Tokens that are unknown,
+    /// already inactive or issued to another project are ignored (RFC 7009).
+    pub async fn revoke_token(
+        &self,
+        project_id: Uuid,
+        token: &str,
+        token_type_hint: Option<&str>,
+    ) -> Result<(), AuthError> {
+        if let Some(found) = self.introspect(project_id, token, token_type_hint).await? {
+            self.end_sessions(vec![found.session().clone()]).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn introspect_access_token(
+        &self,
+        project_id: Uuid,
+        token: &str,
+    ) -> Result<Option<IntrospectedToken>, AuthError> {
+        let settings = self.token_settings(project_id).await?;
+        let Ok(claims) = self.token_service.verify_access_token(token, &settings) else {
+            return Ok(None);
+        };
+
+        Ok(self.session_repo
+            .find_by_id(&claims.sid)
+            .await?
+            .filter(|session| session.is_active(&settings.sessions) && session.user_id == claims.sub)
+            .map(|session| IntrospectedToken::Access { claims: Box::new(claims), session }))
+    }
+
+    async fn introspect_refresh_token(
+        &self,
+        project_id: Uuid,
+        token: &str,
+    ) -> Result<Option<IntrospectedToken>, AuthError> {
+        let settings = self.token_settings(project_id).await?;
+        Ok(self.session_repo
+            .find_by_refresh_token(token)
+            .await?
+            .filter(|session| session.project_id == project_id && session.is_active(&settings.sessions))
+            .map(|session| IntrospectedToken::Refresh { session }))
+    }
+
+    /// Revoke every session of the family and deny their access tokens, as
+    /// `end_sessions` does
+    async fn revoke_token_family(&self, session: &Session) -> Result<(), AuthError> {
+        let revoked = self.session_repo.revoke_family(session.family_id).await?;
+        self.denylist.revoke_sessions(&revoked).await?;
+        let revoked = revoked.len();
+
+        tracing::warn!(
+            "Refresh token reuse detected for user {} (family {}), revoked {} session(s)",
+            session.user_id,
+            session.family_id,
+            revoked
+        );
+
+        if let Some(ref events) = self.events {
+            let event = WebhookService::create_event(
+                EVENT_REFRESH_TOKEN_REUSED,
+                session.project_id,
+                serde_json::json!({
+                    "user_id": session.user_id,
+                    "session_id": session.id,
+                    "family_id": session.family_id,
+                    "revoked_sessions": revoked,
+                }),
+            );
+            events.publish(event).await;
+        }
+
+        Ok(())
+    }
+
+    /// Access token carrying the user's grants and the project's custom claims
+    async fn mint_access_token(
+        &self,
+        settings: &TokenSettings,
+        user: &User,
+        session_id: &str,
+    ) -> Result<AccessToken, AuthError> {
+        let (roles, permissions) = self.grants(user.id).await?;
+        let custom = self
+            .claims_hook
+            .resolve(&settings.claims_hook, user, session_id, &roles, &permissions)
+            .await?;
+
+        self.token_service
+            .generate_access_token(settings, user.id, session_id, roles, permissions, custom)
     }
 
     async fn create_session(
         &self,
         user: &User,
-        ip_address: Option<String>,
-        user_agent: Option<String>,
-    ) -> Result<Session, AuthError> {
-        let access_token = self.token_service.generate_access_token(
-            user.id,
-            user.project_id,
-            vec![], // TODO: Load roles
-            vec![], // TODO: Load permissions
-        )?;
+        settings: &TokenSettings,
+        client: ClientInfo,
+    ) -> Result<(Session, TokenPair), AuthError> {
+        self.make_room_for_session(user.id, &settings.sessions).await?;
+
+        let session_id = generate_session_id();
+        let access_token = self.mint_access_token(settings, user, &session_id).await?;
 
-        let refresh_token = self.token_service.generate_refresh_token();
+        let refresh_token = self.token_service.generate_refresh_token(settings);
         let expires_at = Utc::now() + Duration::seconds(
-            self.refresh_token_expiry_seconds as i64
+            settings.refresh_token_ttl_seconds as i64
         );
 
         let session = Session::new(
-            generate_session_id(),
+            session_id,
             user.id,
             user.project_id,
-            access_token.token,
-            refresh_token.token,
+            &access_token,
+            &refresh_token.token,
             expires_at,
-            ip_address,
-            user_agent,
+            client,
         );
 
         let session = self.session_repo.create(&session).await?;
-        Ok(session)
+        Ok((session, TokenPair { access_token, refresh_token }))
+    }
+}
+
+fn new_identity(project_id: Uuid, user_id: Uuid, provider: &str, profile: &OAuthUserInfo) -> Identity {
+    Identity::new(
+        project_id,
+        user_id,
+        provider.to_string(),
+        profile.provider_id.clone(),
+        profile.email.clone(),
+        identity_data(profile),
+    )
+}
+
+fn identity_data(profile: &OAuthUserInfo) -> serde_json::Value {
+    serde_json::to_value(profile).unwrap_or_default()
+}
+
+/// Name and picture from a provider profile, kept as the new user's metadata
+fn profile_metadata(profile: &OAuthUserInfo) -> serde_json::Value {
+    let mut metadata = serde_json::Map::new();
+    if let Some(name) = &profile.name {
+        metadata.insert("full_name".to_string(), name.clone().into());
     }
+    if let Some(avatar_url) = &profile.avatar_url {
+        metadata.insert("avatar_url".to_string(), avatar_url.clone().into());
+    }
+    metadata.into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::services::TokenService;
     use crate::config::Config;
+    use crate::domain::{Permission, Project, Role};
+    use crate::repository::memory::{
+        InMemoryIdentityRepository, InMemoryProjectRepository, InMemorySessionRepository, InMemoryUserRepository,
+        InMemoryRevokedTokenRepository, InMemoryUserRoleRepository,
+    };
+    use crate::services::webhook_service::WebhookEvent;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingPublisher {
+        events: Mutex<Vec<WebhookEvent>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EventPublisher for RecordingPublisher {
+        async fn publish(&self, event: WebhookEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    type TestAuthService = AuthService<
+        InMemoryUserRepository,
+        InMemorySessionRepository,
+        InMemoryProjectRepository,
+        InMemoryUserRoleRepository,
+        InMemoryRevokedTokenRepository,
+        InMemoryIdentityRepository,
+    >;
+
+    /// Service with one project registered, returned alongside its id
+    fn test_service() -> (TestAuthService, Uuid) {
+        let projects = InMemoryProjectRepository::default();
+        let project_id = Uuid::new_v4();
+        projects.insert(Project::new(project_id, "test".to_string()));
+        let token_service = TokenService::new(Config::for_tests()).unwrap();
+        let denylist = Arc::new(DenylistService::new(
+            InMemoryRevokedTokenRepository::default(),
+            token_service.denylist(),
+            &Config::for_tests(),
+        ));
+
+        let service = AuthService::new(
+            InMemoryUserRepository::default(),
+            InMemorySessionRepository::default(),
+            projects,
+            InMemoryUserRoleRepository::default(),
+            InMemoryIdentityRepository::default(),
+            token_service,
+            denylist,
+        );
+        (service, project_id)
+    }
+
+    #[tokio::test]
+    async fn test_oauth_signin_only_takes_over_verified_addresses() {
+        let (service, project_id) = test_service();
+        let profile = |email_verified| OAuthUserInfo {
+            provider_id: "42".to_string(),
+            email: Some("Ada@Example.com".to_string()),
+            email_verified,
+            ..Default::default()
+        };
+
+        let (user, _, _) = service
+            .signup(project_id, "ada@example.com", "password123", None, ClientInfo::default())
+            .await
+            .unwrap();
+        assert!(!user.email_verified);
+
+        let result = service.signin_oauth(project_id, "github", &profile(false), ClientInfo::default()).await;
+        assert!(matches!(result, Err(AuthError::UserExists)));
+
+        // Whoever registered the unverified account may not own the address
+        let result = service.signin_oauth(project_id, "github", &profile(true), ClientInfo::default()).await;
+        assert!(matches!(result, Err(AuthError::UserExists)));
+
+        service
+            .signin_passwordless(project_id, OtpChannel::Email, "ada@example.com", ClientInfo::default())
+            .await
+            .unwrap();
+        let result = service.signin_oauth(project_id, "github", &profile(false), ClientInfo::default()).await;
+        assert!(matches!(result, Err(AuthError::UserExists)));
+
+        let (signed_in, _, _) = service
+            .signin_oauth(project_id, "github", &profile(true), ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(signed_in.id, user.id);
+        assert!(signed_in.email_verified);
+
+        let newcomer = OAuthUserInfo {
+            provider_id: "43".to_string(),
+            email: Some("grace@example.com".to_string()),
+            name: Some("Grace Hopper".to_string()),
+            ..profile(false)
+        };
+        let (created, _, _) = service
+            .signin_oauth(project_id, "github", &newcomer, ClientInfo::default())
+            .await
+            .unwrap();
+        assert_ne!(created.id, user.id);
+        assert!(!created.email_verified);
+        assert_eq!(created.metadata["full_name"], "Grace Hopper");
+    }
+
+    #[tokio::test]
+    async fn test_linked_identity_signs_in_as_its_user() {
+        let (service, project_id) = test_service();
+        let github = OAuthUserInfo {
+            provider_id: "42".to_string(),
+            email: Some("ada@example.com".to_string()),
+            email_verified: true,
+            ..Default::default()
+        };
 
-    // Note: Full integration tests would require mock repositories
-    // This is a placeholder showing the structure
+        let (user, _, _) = service
+            .signin_oauth(project_id, "github", &github, ClientInfo::default())
+            .await
+            .unwrap();
+
+        // The account signs in as the same user after its address changes
+        let moved = OAuthUserInfo { email: Some("ada@work.example.com".to_string()), ..github.clone() };
+        let (again, _, _) = service
+            .signin_oauth(project_id, "github", &moved, ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(again.id, user.id);
+        assert_eq!(again.email.as_deref(), Some("ada@example.com"));
+
+        // Twitter shares no address, so it has to be linked while signed in
+        let twitter = OAuthUserInfo { provider_id: "t1".to_string(), ..Default::default() };
+        let result = service.signin_oauth(project_id, "twitter", &twitter, ClientInfo::default()).await;
+        assert!(matches!(result, Err(AuthError::OAuth(_))));
+
+        service.link_identity(project_id, user.id, "twitter", &twitter).await.unwrap();
+        let (via_twitter, _, _) = service
+            .signin_oauth(project_id, "twitter", &twitter, ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(via_twitter.id, user.id);
+
+        let other = service.signup(project_id, "grace@example.com", "password123", None, ClientInfo::default())
+            .await
+            .unwrap()
+            .0;
+        let result = service.link_identity(project_id, other.id, "twitter", &twitter).await;
+        assert!(matches!(result, Err(AuthError::IdentityAlreadyLinked)));
+    }
+
+    #[tokio::test]
+    async fn test_cannot_unlink_last_sign_in_method() {
+        let (service, project_id) = test_service();
+        let unverified = OAuthUserInfo {
+            provider_id: "42".to_string(),
+            email: Some("ada@example.com".to_string()),
+            email_verified: false,
+            ..Default::default()
+        };
+
+        let (user, _, _) = service
+            .signin_oauth(project_id, "github", &unverified, ClientInfo::default())
+            .await
+            .unwrap();
+        let github = service.list_identities(user.id).await.unwrap().remove(0);
+
+        let result = service.unlink_identity(user.id, github.id).await;
+        assert!(matches!(result, Err(AuthError::LastSignInMethod)));
+
+        let discord = OAuthUserInfo { provider_id: "d1".to_string(), ..Default::default() };
+        service.link_identity(project_id, user.id, "discord", &discord).await.unwrap();
+        service.unlink_identity(user.id, github.id).await.unwrap();
+
+        let remaining = service.list_identities(user.id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].provider, "discord");
+        assert!(matches!(
+            service.unlink_identity(user.id, github.id).await,
+            Err(AuthError::IdentityNotFound)
+        ));
+    }
 
     #[test]
     fn test_password_hashing() {
@@ -206,4 +976,381 @@ mod tests {
         let hash = PasswordService::hash_password(password).unwrap();
         assert!(PasswordService::verify_password(password, &hash).unwrap());
     }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_token_within_family() {
+        let (service, project_id) = test_service();
+        let (_, session, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+
+        let (_, rotated, rotated_tokens) = service
+            .refresh_token(project_id, &tokens.refresh_token.token)
+            .await
+            .unwrap();
+
+        assert_eq!(rotated.id, session.id);
+        assert_eq!(rotated.family_id, session.family_id);
+        assert_ne!(rotated.refresh_token_hash, session.refresh_token_hash);
+        assert!(service.refresh_token(project_id, &rotated_tokens.refresh_token.token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reused_refresh_token_revokes_family() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let (service, project_id) = test_service();
+        let service = service.with_events(publisher.clone());
+        let (_, _, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+
+        let (_, _, rotated_tokens) = service
+            .refresh_token(project_id, &tokens.refresh_token.token)
+            .await
+            .unwrap();
+
+        // Replaying the superseded token is detected and kills the live token too
+        let reused = service.refresh_token(project_id, &tokens.refresh_token.token).await;
+        assert!(matches!(reused, Err(AuthError::TokenReused)));
+
+        let current = service.refresh_token(project_id, &rotated_tokens.refresh_token.token).await;
+        assert!(matches!(current, Err(AuthError::TokenExpired)));
+
+        let events = publisher.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, EVENT_REFRESH_TOKEN_REUSED);
+        assert_eq!(events[0].project_id, project_id);
+    }
+
+    #[tokio::test]
+    async fn test_reused_refresh_token_denies_family_access_tokens() {
+        let (service, project_id) = test_service();
+        let (_, _, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, _, rotated_tokens) = service
+            .refresh_token(project_id, &tokens.refresh_token.token)
+            .await
+            .unwrap();
+        assert!(is_accepted(&service, project_id, &rotated_tokens.access_token.token).await);
+
+        let reused = service.refresh_token(project_id, &tokens.refresh_token.token).await;
+        assert!(matches!(reused, Err(AuthError::TokenReused)));
+
+        // Denied right away rather than left usable until it expires
+        assert!(!is_accepted(&service, project_id, &rotated_tokens.access_token.token).await);
+        assert!(service
+            .introspect(project_id, &rotated_tokens.access_token.token, None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_stores_only_token_hashes() {
+        let (service, project_id) = test_service();
+        let (_, session, tokens) = service
+            .signup(project_id, "a@example.com", "password123", None, ClientInfo::default())
+            .await
+            .unwrap();
+
+        assert_ne!(session.refresh_token_hash, tokens.refresh_token.token);
+        assert_eq!(session.refresh_token_hash, crate::utils::crypto::hash_token(&tokens.refresh_token.token));
+        assert_eq!(session.access_token_hash, crate::utils::crypto::hash_token(&tokens.access_token.token));
+    }
+
+    #[tokio::test]
+    async fn test_signup_requires_known_project() {
+        let (service, _) = test_service();
+
+        let result = service.signup(Uuid::new_v4(), "a@example.com", "password123", None, ClientInfo::default()).await;
+        assert!(matches!(result, Err(AuthError::ProjectNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_refresh_token_is_invalid() {
+        let (service, project_id) = test_service();
+
+        let result = service.refresh_token(project_id, "rt_unknown").await;
+        assert!(matches!(result, Err(AuthError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_access_token_carries_roles_and_permissions() {
+        let (service, project_id) = test_service();
+        let (user, _, _) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+
+        let editor = Role::new(project_id, "editor".to_string())
+            .with_permissions(vec![Permission::new("posts", "read"), Permission::new("posts", "write")]);
+        let viewer = Role::new(project_id, "viewer".to_string())
+            .with_permissions(vec![Permission::new("posts", "read")]);
+        service.user_role_repo.insert(user.id, editor);
+        service.user_role_repo.insert(user.id, viewer);
+
+        // Picked up on refresh without signing in again
+        let (_, _, tokens) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+        let (_, _, refreshed) = service.refresh_token(project_id, &tokens.refresh_token.token).await.unwrap();
+
+        let settings = service.token_settings(project_id).await.unwrap();
+        for token in [&tokens.access_token.token, &refreshed.access_token.token] {
+            let claims = service.token_service.verify_access_token(token, &settings).unwrap();
+            assert_eq!(claims.roles, vec!["editor", "viewer"]);
+            assert_eq!(claims.permissions, vec!["posts:read", "posts:write"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_claims_template_is_applied() {
+        let (service, project_id) = test_service();
+        let mut project = Project::new(project_id, "test".to_string());
+        project.settings.claims_hook.template = serde_json::json!({ "org_id": "{{ app_metadata.org_id }}" })
+            .as_object()
+            .cloned();
+        service.project_repo.insert(project);
+
+        // Clients choose their own signup metadata, so it cannot fill the claim
+        let metadata = serde_json::json!({ "org_id": "org_evil" });
+        let (user, _, tokens) = service
+            .signup(project_id, "a@example.com", "password123", Some(metadata), ClientInfo::default())
+            .await
+            .unwrap();
+
+        let settings = service.token_settings(project_id).await.unwrap();
+        let claims = service.token_service.verify_access_token(&tokens.access_token.token, &settings).unwrap();
+        assert!(!claims.custom.contains_key("org_id"));
+
+        service
+            .set_app_metadata(project_id, user.id, serde_json::json!({ "org_id": "org_42" }))
+            .await
+            .unwrap();
+        let (_, _, refreshed) = service.refresh_token(project_id, &tokens.refresh_token.token).await.unwrap();
+        let claims = service.token_service.verify_access_token(&refreshed.access_token.token, &settings).unwrap();
+        assert_eq!(claims.custom["org_id"], "org_42");
+    }
+
+    #[tokio::test]
+    async fn test_introspect_access_and_refresh_tokens() {
+        let (service, project_id) = test_service();
+        let (user, session, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+
+        match service.introspect(project_id, &tokens.access_token.token, None).await.unwrap() {
+            Some(IntrospectedToken::Access { claims, session: found }) => {
+                assert_eq!(claims.sub, user.id);
+                assert_eq!(found.id, session.id);
+            }
+            _ => panic!("expected an active access token"),
+        }
+
+        // The hint is only an optimisation; a wrong one still finds the token
+        let refresh = service.introspect(project_id, &tokens.refresh_token.token, Some("access_token")).await.unwrap();
+        assert!(matches!(refresh, Some(IntrospectedToken::Refresh { .. })));
+
+        assert!(service.introspect(project_id, "not-a-token", None).await.unwrap().is_none());
+        // Tokens are only active for the project they were issued to
+        let other_project = Uuid::new_v4();
+        service.project_repo.insert(Project::new(other_project, "other".to_string()));
+        for token in [&tokens.access_token.token, &tokens.refresh_token.token] {
+            assert!(service.introspect(other_project, token, None).await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoking_either_token_ends_the_session() {
+        let (service, project_id) = test_service();
+        let (_, _, first) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, _, second) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+
+        service.revoke_token(project_id, &first.access_token.token, None).await.unwrap();
+        service.revoke_token(project_id, &second.refresh_token.token, Some("refresh_token")).await.unwrap();
+        // Unknown tokens are not an error
+        service.revoke_token(project_id, "not-a-token", None).await.unwrap();
+
+        for token in [&first.access_token.token, &first.refresh_token.token, &second.access_token.token] {
+            assert!(service.introspect(project_id, token, None).await.unwrap().is_none());
+        }
+        let refresh = service.refresh_token(project_id, &first.refresh_token.token).await;
+        assert!(matches!(refresh, Err(AuthError::TokenExpired)));
+    }
+
+    /// Whether the access token still passes verification for the project
+    async fn is_accepted(service: &TestAuthService, project_id: Uuid, token: &str) -> bool {
+        let settings = service.token_settings(project_id).await.unwrap();
+        service.token_service.verify_access_token(token, &settings).is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_signout_denies_access_token_immediately() {
+        let (service, project_id) = test_service();
+        let (_, session, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        assert!(is_accepted(&service, project_id, &tokens.access_token.token).await);
+
+        service.signout(&session.id).await.unwrap();
+
+        assert!(!is_accepted(&service, project_id, &tokens.access_token.token).await);
+    }
+
+    #[tokio::test]
+    async fn test_ban_ends_every_session() {
+        let (service, project_id) = test_service();
+        let (user, _, first) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, _, second) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+
+        // Users of other projects cannot be banned through this one
+        let foreign = service.set_banned(Uuid::new_v4(), user.id, true).await;
+        assert!(matches!(foreign, Err(AuthError::UserNotFound)));
+
+        assert!(service.set_banned(project_id, user.id, true).await.unwrap().banned);
+
+        for tokens in [&first, &second] {
+            assert!(!is_accepted(&service, project_id, &tokens.access_token.token).await);
+            let refresh = service.refresh_token(project_id, &tokens.refresh_token.token).await;
+            assert!(refresh.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_password_change_keeps_only_current_session() {
+        let (service, project_id) = test_service();
+        let (user, current, current_tokens) =
+            service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, _, other_tokens) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+
+        let wrong = service.change_password(user.id, &current.id, "wrong-password", "new-password-456").await;
+        assert!(matches!(wrong, Err(AuthError::InvalidCredentials)));
+
+        service.change_password(user.id, &current.id, "password123", "new-password-456").await.unwrap();
+
+        assert!(is_accepted(&service, project_id, &current_tokens.access_token.token).await);
+        assert!(!is_accepted(&service, project_id, &other_tokens.access_token.token).await);
+        assert!(service.signin(project_id, "a@example.com", "new-password-456", ClientInfo::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_scoped_to_their_user() {
+        let (service, project_id) = test_service();
+        let (alice, first, _) = service.signup(project_id, "alice@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, second, _) = service.signin(project_id, "alice@example.com", "password123", ClientInfo::default()).await.unwrap();
+        let (bob, bobs, _) = service.signup(project_id, "bob@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+
+        let listed: Vec<String> = service.list_sessions(project_id, alice.id).await.unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.contains(&first.id) && listed.contains(&second.id));
+
+        let foreign = service.revoke_session(alice.id, &bobs.id).await;
+        assert!(matches!(foreign, Err(AuthError::SessionNotFound)));
+        assert_eq!(service.list_sessions(project_id, bob.id).await.unwrap().len(), 1);
+
+        service.revoke_session(alice.id, &first.id).await.unwrap();
+        let remaining = service.list_sessions(project_id, alice.id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, second.id);
+        assert!(matches!(service.revoke_session(alice.id, &first.id).await, Err(AuthError::SessionNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_signout_everywhere_can_keep_current_session() {
+        let (service, project_id) = test_service();
+        let (user, current, _) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+        service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+
+        assert_eq!(service.signout_everywhere(user.id, Some(&current.id)).await.unwrap(), 2);
+        let remaining = service.list_sessions(project_id, user.id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, current.id);
+
+        assert_eq!(service.signout_everywhere(user.id, None).await.unwrap(), 1);
+        assert!(service.list_sessions(project_id, user.id).await.unwrap().is_empty());
+    }
+
+    fn set_session_limits(service: &TestAuthService, project_id: Uuid, limits: SessionSettings) {
+        let mut project = Project::new(project_id, "test".to_string());
+        project.settings.sessions = limits;
+        service.project_repo.insert(project);
+    }
+
+    #[tokio::test]
+    async fn test_session_cap_evicts_oldest_or_rejects_new() {
+        let (service, project_id) = test_service();
+        set_session_limits(&service, project_id, SessionSettings {
+            max_concurrent: Some(2),
+            ..Default::default()
+        });
+
+        let (user, first, first_tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, second, _) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+        let (_, third, _) = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await.unwrap();
+
+        let listed: Vec<String> = service.list_sessions(project_id, user.id).await.unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(listed, vec![third.id, second.id]);
+        assert!(!listed.contains(&first.id));
+        assert!(!is_accepted(&service, project_id, &first_tokens.access_token.token).await);
+
+        set_session_limits(&service, project_id, SessionSettings {
+            max_concurrent: Some(2),
+            on_limit: SessionLimitPolicy::RejectNew,
+            ..Default::default()
+        });
+        let rejected = service.signin(project_id, "a@example.com", "password123", ClientInfo::default()).await;
+        assert!(matches!(rejected, Err(AuthError::SessionLimitReached)));
+        assert_eq!(service.list_sessions(project_id, user.id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_idle_session_is_ended_on_use() {
+        let (service, project_id) = test_service();
+        set_session_limits(&service, project_id, SessionSettings {
+            idle_timeout_seconds: Some(600),
+            ..Default::default()
+        });
+
+        let (_, mut session, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        session.last_active_at = Utc::now() - Duration::minutes(11);
+        service.session_repo.update(&session).await.unwrap();
+
+        let settings = service.token_settings(project_id).await.unwrap();
+        let claims = service.token_service.verify_access_token(&tokens.access_token.token, &settings).unwrap();
+        assert!(matches!(service.authenticate_session(&claims, &settings).await, Err(AuthError::InvalidToken)));
+
+        let refreshed = service.refresh_token(project_id, &tokens.refresh_token.token).await;
+        assert!(matches!(refreshed, Err(AuthError::TokenExpired)));
+        assert!(!is_accepted(&service, project_id, &tokens.access_token.token).await);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_cannot_extend_past_max_lifetime() {
+        let (service, project_id) = test_service();
+        set_session_limits(&service, project_id, SessionSettings {
+            max_lifetime_seconds: Some(3600),
+            ..Default::default()
+        });
+
+        let (_, session, tokens) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (_, refreshed, _) = service.refresh_token(project_id, &tokens.refresh_token.token).await.unwrap();
+
+        assert_eq!(refreshed.expires_at, session.created_at + Duration::hours(1));
+    }
+
+    #[tokio::test]
+    async fn test_passwordless_signin_creates_user_once() {
+        let (service, project_id) = test_service();
+
+        let (user, _, tokens) = service
+            .signin_passwordless(project_id, OtpChannel::Sms, "+15550100199", ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(user.email, None);
+        assert_eq!(user.phone.as_deref(), Some("+15550100199"));
+        assert!(user.phone_verified);
+        assert!(is_accepted(&service, project_id, &tokens.access_token.token).await);
+
+        let (again, _, _) = service
+            .signin_passwordless(project_id, OtpChannel::Sms, "+15550100199", ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(again.id, user.id);
+
+        // An existing password account is signed in, not duplicated
+        let (existing, _, _) = service.signup(project_id, "a@example.com", "password123", None, ClientInfo::default()).await.unwrap();
+        let (by_email, _, _) = service
+            .signin_passwordless(project_id, OtpChannel::Email, "a@example.com", ClientInfo::default())
+            .await
+            .unwrap();
+        assert_eq!(by_email.id, existing.id);
+        assert!(by_email.email_verified);
+    }
 }
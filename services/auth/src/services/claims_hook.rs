@@ -0,0 +1,329 @@
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::domain::{ClaimsHookSettings, User};
+use crate::error::AuthError;
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "{t}.{body}">`, keyed with the project's hook secret
+pub const SIGNATURE_HEADER: &str = "x-merco-signature";
+
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+/// Callouts sit on the sign-in path, so projects cannot wait longer than this
+const MAX_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Serialize)]
+struct HookRequest<'a> {
+    project_id: Uuid,
+    session_id: &'a str,
+    user: HookUser<'a>,
+    roles: &'a [String],
+    permissions: &'a [String],
+    /// Claims rendered from the template, so the hook can build on them
+    claims: &'a Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct HookUser<'a> {
+    id: Uuid,
+    email: Option<&'a str>,
+    phone: Option<&'a str>,
+    metadata: &'a Value,
+    app_metadata: &'a Value,
+}
+
+#[derive(Deserialize)]
+struct HookResponse {
+    #[serde(default)]
+    claims: Map<String, Value>,
+}
+
+/// Resolves a project's custom access token claims from its template and callout
+#[derive(Clone, Default)]
+pub struct ClaimsHook {
+    client: reqwest::Client,
+}
+
+impl ClaimsHook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Template claims merged with the callout's. A failed callout rejects the token
+    /// unless the project opted to fail open, in which case only the template applies.
+    pub async fn resolve(
+        &self,
+        settings: &ClaimsHookSettings,
+        user: &User,
+        session_id: &str,
+        roles: &[String],
+        permissions: &[String],
+    ) -> Result<Map<String, Value>, AuthError> {
+        let mut claims = settings
+            .template
+            .as_ref()
+            .map(|template| render_template(template, &user.app_metadata))
+            .unwrap_or_default();
+
+        let Some(url) = settings.url.as_deref() else {
+            return Ok(claims);
+        };
+
+        let request = HookRequest {
+            project_id: user.project_id,
+            session_id,
+            user: HookUser {
+                id: user.id,
+                email: user.email.as_deref(),
+                phone: user.phone.as_deref(),
+                metadata: &user.metadata,
+                app_metadata: &user.app_metadata,
+            },
+            roles,
+            permissions,
+            claims: &claims,
+        };
+
+        match self.call(settings, url, &request).await {
+            Ok(extra) => claims.extend(extra),
+            Err(e) if settings.fail_open => {
+                tracing::warn!(
+                    "Claims hook for project {} failed, minting without it: {}",
+                    user.project_id,
+                    e
+                );
+            }
+            Err(e) => {
+                // The detail can name internal hosts, so it stays in the logs
+                tracing::error!("Claims hook for project {} failed: {}", user.project_id, e);
+                return Err(AuthError::ClaimsHook("custom claims unavailable".to_string()));
+            }
+        }
+
+        Ok(claims)
+    }
+
+    async fn call(
+        &self,
+        settings: &ClaimsHookSettings,
+        url: &str,
+        request: &HookRequest<'_>,
+    ) -> Result<Map<String, Value>, AuthError> {
+        let secret = settings
+            .secret
+            .as_deref()
+            .ok_or_else(|| AuthError::ClaimsHook("no signing secret configured".to_string()))?;
+        let body = serde_json::to_vec(request).map_err(|e| AuthError::ClaimsHook(e.to_string()))?;
+        let timeout = settings.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS);
+
+        let response = self
+            .client
+            .post(url)
+            .timeout(Duration::from_millis(timeout))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, sign(secret, chrono::Utc::now().timestamp(), &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AuthError::ClaimsHook(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(AuthError::ClaimsHook(format!("endpoint returned {}", response.status())));
+        }
+
+        let response: HookResponse = response
+            .json()
+            .await
+            .map_err(|e| AuthError::ClaimsHook(e.to_string()))?;
+
+        Ok(response.claims)
+    }
+}
+
+/// Signature header value for a callout body sent at `timestamp`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(format!("{}.", timestamp).as_bytes());
+    context.update(body);
+
+    let digest: String = context
+        .sign()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    format!("t={},v1={}", timestamp, digest)
+}
+
+/// Replace `{{ app_metadata.path }}` strings with the value found at that path.
+/// Claims whose path is missing or null are left out. Only admin-set metadata is
+/// offered: clients write `metadata` themselves, so backends could not trust it.
+pub fn render_template(template: &Map<String, Value>, metadata: &Value) -> Map<String, Value> {
+    template
+        .iter()
+        .filter_map(|(name, value)| render(value, metadata).map(|value| (name.clone(), value)))
+        .collect()
+}
+
+fn render(value: &Value, metadata: &Value) -> Option<Value> {
+    match value {
+        Value::String(s) => match placeholder(s) {
+            Some(inner) => metadata_path(inner)
+                .and_then(|path| lookup(metadata, path))
+                .filter(|v| !v.is_null())
+                .cloned(),
+            None => Some(value.clone()),
+        },
+        Value::Object(map) => Some(Value::Object(render_template(map, metadata))),
+        Value::Array(items) => Some(Value::Array(
+            items.iter().filter_map(|item| render(item, metadata)).collect(),
+        )),
+        _ => Some(value.clone()),
+    }
+}
+
+/// Expression inside a `{{ ... }}` placeholder
+fn placeholder(s: &str) -> Option<&str> {
+    Some(s.trim().strip_prefix("{{")?.strip_suffix("}}")?.trim())
+}
+
+/// Path below `app_metadata` named by a placeholder; empty for the whole object.
+/// Anything else, `metadata` included, names nothing.
+fn metadata_path(inner: &str) -> Option<&str> {
+    match inner {
+        "app_metadata" => Some(""),
+        _ => inner.strip_prefix("app_metadata."),
+    }
+}
+
+fn lookup<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(metadata);
+    }
+
+    path.split('.').try_fold(metadata, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::serve;
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use serde_json::json;
+
+    fn user() -> User {
+        let mut user = User::new(Uuid::new_v4(), "a@example.com".to_string());
+        user.set_app_metadata(json!({ "plan": "pro", "org": { "id": "org_1" }, "flags": ["beta"] }));
+        user
+    }
+
+    fn template(value: Value) -> Option<Map<String, Value>> {
+        value.as_object().cloned()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = json!({
+            "plan": "{{ app_metadata.plan }}",
+            "org_id": "{{app_metadata.org.id}}",
+            "first_flag": "{{ app_metadata.flags.0 }}",
+            "tier": "{{ app_metadata.missing }}",
+            "app": { "name": "merco", "org": "{{ app_metadata.org.id }}" },
+        });
+
+        let claims = render_template(template.as_object().unwrap(), &user().app_metadata);
+
+        assert_eq!(
+            Value::Object(claims),
+            json!({
+                "plan": "pro",
+                "org_id": "org_1",
+                "first_flag": "beta",
+                "app": { "name": "merco", "org": "org_1" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_metadata_does_not_reach_template_claims() {
+        let user = User::new(Uuid::new_v4(), "a@example.com".to_string())
+            .with_metadata(json!({ "plan": "enterprise", "org": { "id": "org_evil" } }));
+        let settings = ClaimsHookSettings {
+            template: template(json!({
+                "plan": "{{ app_metadata.plan }}",
+                "org_id": "{{ metadata.org.id }}",
+            })),
+            ..Default::default()
+        };
+
+        let claims = ClaimsHook::new().resolve(&settings, &user, "sess_test", &[], &[]).await.unwrap();
+
+        assert!(claims.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_callout_is_signed_and_merged() {
+        let url = serve(Router::new().route(
+            "/claims",
+            post(|headers: HeaderMap, body: String| async move {
+                let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+                let timestamp: i64 = signature[2..signature.find(',').unwrap()].parse().unwrap();
+                assert_eq!(signature, sign("hook-secret", timestamp, body.as_bytes()));
+
+                let request: Value = serde_json::from_str(&body).unwrap();
+                Json(json!({ "claims": { "plan": "enterprise", "seats": request["claims"]["seats"] } }))
+            }),
+        ))
+        .await;
+
+        let settings = ClaimsHookSettings {
+            template: template(json!({ "plan": "{{ app_metadata.plan }}", "seats": 5 })),
+            url: Some(format!("{}/claims", url)),
+            secret: Some("hook-secret".to_string()),
+            ..Default::default()
+        };
+
+        let claims = ClaimsHook::new()
+            .resolve(&settings, &user(), "sess_test", &[], &[])
+            .await
+            .unwrap();
+
+        assert_eq!(Value::Object(claims), json!({ "plan": "enterprise", "seats": 5 }));
+    }
+
+    #[tokio::test]
+    async fn test_slow_callout_fails_closed_unless_configured_open() {
+        let url = serve(Router::new().route(
+            "/claims",
+            post(|| async {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                Json(json!({ "claims": { "plan": "late" } }))
+            }),
+        ))
+        .await;
+
+        let mut settings = ClaimsHookSettings {
+            template: template(json!({ "plan": "{{ app_metadata.plan }}" })),
+            url: Some(format!("{}/claims", url)),
+            secret: Some("hook-secret".to_string()),
+            timeout_ms: Some(50),
+            ..Default::default()
+        };
+        let hook = ClaimsHook::new();
+
+        let closed = hook.resolve(&settings, &user(), "sess_test", &[], &[]).await;
+        assert!(matches!(closed, Err(AuthError::ClaimsHook(_))));
+
+        settings.fail_open = true;
+        let open = hook.resolve(&settings, &user(), "sess_test", &[], &[]).await.unwrap();
+        assert_eq!(Value::Object(open), json!({ "plan": "pro" }));
+    }
+}
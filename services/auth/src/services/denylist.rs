@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// In-process copy of the revoked access token ids, consulted on every verification.
+/// Entries are kept until the token they name would have expired anyway.
+#[derive(Default)]
+pub struct Denylist {
+    revoked: RwLock<HashMap<String, DateTime<Utc>>>,
+}
+
+impl Denylist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, jti: &str) -> bool {
+        self.revoked
+            .read()
+            .unwrap()
+            .get(jti)
+            .is_some_and(|expires_at| *expires_at > Utc::now())
+    }
+
+    pub fn insert(&self, jti: String, expires_at: DateTime<Utc>) {
+        self.revoked.write().unwrap().insert(jti, expires_at);
+    }
+
+    /// Swap in the current set of revocations, dropping expired ones
+    pub fn replace(&self, entries: impl IntoIterator<Item = (String, DateTime<Utc>)>) {
+        let now = Utc::now();
+        *self.revoked.write().unwrap() = entries
+            .into_iter()
+            .filter(|(_, expires_at)| *expires_at > now)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_entries_lapse_with_the_token() {
+        let denylist = Denylist::new();
+        denylist.insert("live".to_string(), Utc::now() + Duration::minutes(5));
+        denylist.insert("lapsed".to_string(), Utc::now() - Duration::minutes(5));
+
+        assert!(denylist.contains("live"));
+        assert!(!denylist.contains("lapsed"));
+        assert!(!denylist.contains("unknown"));
+
+        denylist.replace(vec![("other".to_string(), Utc::now() + Duration::minutes(5))]);
+        assert!(!denylist.contains("live"));
+        assert!(denylist.contains("other"));
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::domain::{RevokedToken, Session};
+use crate::error::AuthError;
+use crate::repository::traits::RevokedTokenRepository;
+use crate::services::Denylist;
+
+/// How often every instance reloads revocations made elsewhere. Revoked sessions are
+/// also rejected by the session lookup, so this only bounds the cache's staleness.
+pub const DENYLIST_REFRESH_INTERVAL_SECONDS: u64 = 10;
+
+/// Persists access token revocations and keeps the in-process denylist in sync
+pub struct DenylistService<R: RevokedTokenRepository> {
+    repo: R,
+    denylist: Arc<Denylist>,
+    access_token_lifetime: Duration,
+}
+
+impl<R: RevokedTokenRepository> DenylistService<R> {
+    pub fn new(repo: R, denylist: Arc<Denylist>, config: &Config) -> Self {
+        Self {
+            repo,
+            denylist,
+            access_token_lifetime: Duration::seconds(config.jwt_expiry_seconds as i64),
+        }
+    }
+
+    /// Deny the latest access token of each session. Its exact expiry is not stored,
+    /// so the entry is kept for the longest lifetime any project can configure.
+    pub async fn revoke_sessions(&self, sessions: &[Session]) -> Result<(), AuthError> {
+        let now = Utc::now();
+        for session in sessions {
+            let Some(jti) = &session.access_token_jti else {
+                continue;
+            };
+
+            let token = RevokedToken {
+                jti: jti.clone(),
+                user_id: session.user_id,
+                project_id: session.project_id,
+                expires_at: now + self.access_token_lifetime,
+                revoked_at: now,
+            };
+            self.repo.create(&token).await?;
+            self.denylist.insert(token.jti, token.expires_at);
+        }
+
+        Ok(())
+    }
+
+    /// Load every revocation that is still relevant into the denylist
+    pub async fn reload(&self) -> Result<(), AuthError> {
+        let revoked = self.repo.list_active().await?;
+        self.denylist
+            .replace(revoked.into_iter().map(|token| (token.jti, token.expires_at)));
+        Ok(())
+    }
+
+    /// Reload periodically so revocations on other instances are picked up. Expired rows
+    /// are pruned by the `auth.revoked_tokens.delete_expired` maintenance job.
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()>
+    where
+        R: 'static,
+    {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                DENYLIST_REFRESH_INTERVAL_SECONDS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.reload().await {
+                    tracing::error!("Failed to reload revoked tokens: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AccessToken, ClientInfo};
+    use crate::repository::memory::InMemoryRevokedTokenRepository;
+    use uuid::Uuid;
+
+    fn session(jti: &str) -> Session {
+        Session::new(
+            "sess_test".to_string(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            &AccessToken::new("access".to_string(), jti.to_string(), 3600),
+            "refresh",
+            Utc::now() + Duration::days(1),
+            ClientInfo::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_revocations_reach_other_instances_on_reload() {
+        let service = DenylistService::new(
+            InMemoryRevokedTokenRepository::default(),
+            Arc::new(Denylist::new()),
+            &Config::for_tests(),
+        );
+        service.revoke_sessions(&[session("jti_a"), session("jti_b")]).await.unwrap();
+        assert!(service.denylist.contains("jti_a"));
+
+        // Another instance sharing the table, with an empty cache
+        let other = DenylistService { denylist: Arc::new(Denylist::new()), ..service };
+        assert!(!other.denylist.contains("jti_b"));
+        other.reload().await.unwrap();
+        assert!(other.denylist.contains("jti_a"));
+        assert!(other.denylist.contains("jti_b"));
+    }
+}
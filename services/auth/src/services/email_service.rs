@@ -8,6 +8,32 @@ use crate::error::AuthError;
 #[async_trait]
 pub trait EmailSender: Send + Sync {
     async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), AuthError>;
+
+    async fn send_otp(&self, to: &str, code: &str) -> Result<(), AuthError> {
+        let body = format!(
+            r#"
+            Your verification code is: {}
+            
+            This code will expire in 10 minutes.
+            "#,
+            code
+        );
+        self.send(to, "Your verification code", &body).await
+    }
+
+    async fn send_magic_link(&self, to: &str, url: &str) -> Result<(), AuthError> {
+        let body = format!(
+            r#"
+            Click the link below to sign in:
+            
+            {}
+            
+            This link will expire in 10 minutes.
+            "#,
+            url
+        );
+        self.send(to, "Sign in to your account", &body).await
+    }
 }
 
 pub struct EmailService {
@@ -34,39 +60,20 @@ impl EmailService {
             None
         };
 
+        if smtp_transport.is_none() {
+            if config.email_dev_log {
+                tracing::warn!("No SMTP server configured; logging emails, codes included (EMAIL_DEV_LOG)");
+            } else {
+                tracing::warn!("No SMTP server configured; sending email will fail");
+            }
+        }
+
         Self {
             config,
             smtp_transport,
         }
     }
 
-    pub async fn send_magic_link(&self, to: &str, token: &str) -> Result<(), AuthError> {
-        let url = format!("https://auth.merco.dev/magic-link/verify?token={}", token);
-        let body = format!(
-            r#"
-            Click the link below to sign in:
-            
-            {}
-            
-            This link will expire in 10 minutes.
-            "#,
-            url
-        );
-        self.send(to, "Sign in to your account", &body).await
-    }
-
-    pub async fn send_otp(&self, to: &str, code: &str) -> Result<(), AuthError> {
-        let body = format!(
-            r#"
-            Your verification code is: {}
-            
-            This code will expire in 10 minutes.
-            "#,
-            code
-        );
-        self.send(to, "Your verification code", &body).await
-    }
-
     pub async fn send_password_reset(&self, to: &str, token: &str) -> Result<(), AuthError> {
         let url = format!("https://auth.merco.dev/password/reset?token={}", token);
         let body = format!(
@@ -101,13 +108,33 @@ impl EmailSender for EmailService {
                 .body(body.to_string())
                 .map_err(|e| AuthError::Email(e.to_string()))?;
 
-            transport.send(&email)
+            // The SMTP transport blocks, so keep it off the runtime's worker threads
+            let transport = transport.clone();
+            tokio::task::spawn_blocking(move || transport.send(&email))
+                .await
+                .map_err(|e| AuthError::Email(e.to_string()))?
                 .map_err(|e| AuthError::Email(e.to_string()))?;
-        } else {
-            // In development, just log
+        } else if self.config.email_dev_log {
             tracing::info!("Email would be sent to {}: {} - {}", to, subject, body);
+        } else {
+            return Err(AuthError::Email("No SMTP server configured".to_string()));
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_without_smtp_fails_unless_dev_log() {
+        let mut config = Config::for_tests();
+        let result = EmailService::new(config.clone()).send_otp("ada@example.com", "123456").await;
+        assert!(matches!(result, Err(AuthError::Email(_))));
+
+        config.email_dev_log = true;
+        EmailService::new(config).send_otp("ada@example.com", "123456").await.unwrap();
+    }
+}
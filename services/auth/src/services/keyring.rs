@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use std::sync::{Arc, RwLock};
+
+use crate::services::SigningKey;
+
+/// Keys accepted for verification, and the schedule deciding which one signs.
+/// The configured key is the fallback signer; rotated keys take over once active.
+pub struct Keyring {
+    configured: Arc<SigningKey>,
+    rotated: RwLock<Vec<ScheduledKey>>,
+}
+
+#[derive(Clone)]
+struct ScheduledKey {
+    key: Arc<SigningKey>,
+    activates_at: DateTime<Utc>,
+}
+
+impl Keyring {
+    pub fn new(configured: SigningKey) -> Self {
+        Self {
+            configured: Arc::new(configured),
+            rotated: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Key from configuration, used for tokens that predate `kid` headers
+    pub fn configured(&self) -> Arc<SigningKey> {
+        self.configured.clone()
+    }
+
+    /// The most recently activated rotated key, else the configured key
+    pub fn signing_key(&self) -> Arc<SigningKey> {
+        let now = Utc::now();
+        self.rotated
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|k| k.activates_at <= now)
+            .max_by_key(|k| k.activates_at)
+            .map(|k| k.key.clone())
+            .unwrap_or_else(|| self.configured.clone())
+    }
+
+    /// Any key that may have signed a still valid token, including pending ones
+    pub fn find(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        let rotated = self.rotated.read().unwrap();
+        rotated
+            .iter()
+            .find(|k| k.key.kid() == kid)
+            .map(|k| k.key.clone())
+            .or_else(|| (self.configured.kid() == kid).then(|| self.configured.clone()))
+    }
+
+    /// Public keys of every verification key; pending keys are published ahead of use
+    pub fn jwks(&self) -> JwkSet {
+        let rotated = self.rotated.read().unwrap();
+        let keys = rotated
+            .iter()
+            .map(|k| &k.key)
+            .chain(std::iter::once(&self.configured))
+            .filter_map(|k| k.jwk().cloned())
+            .collect();
+
+        JwkSet { keys }
+    }
+
+    /// Swap in the current set of rotated keys
+    pub fn replace_rotated(&self, keys: Vec<(SigningKey, DateTime<Utc>)>) {
+        let scheduled = keys
+            .into_iter()
+            .map(|(key, activates_at)| ScheduledKey {
+                key: Arc::new(key),
+                activates_at,
+            })
+            .collect();
+
+        *self.rotated.write().unwrap() = scheduled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use jsonwebtoken::Algorithm;
+
+    fn keyring() -> Keyring {
+        Keyring::new(SigningKey::from_secret(Algorithm::HS256, b"configured", None))
+    }
+
+    #[test]
+    fn test_configured_key_signs_until_rotation_activates() {
+        let keyring = keyring();
+        let (pending, _) = SigningKey::generate(Algorithm::ES256, None).unwrap();
+        let pending_kid = pending.kid().to_string();
+        keyring.replace_rotated(vec![(pending, Utc::now() + Duration::minutes(5))]);
+
+        // Published and accepted before it signs anything
+        assert_eq!(keyring.signing_key().kid(), "default");
+        assert!(keyring.find(&pending_kid).is_some());
+        assert_eq!(keyring.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn test_latest_active_key_signs() {
+        let keyring = keyring();
+        let (older, _) = SigningKey::generate(Algorithm::HS256, None).unwrap();
+        let (newer, _) = SigningKey::generate(Algorithm::HS256, None).unwrap();
+        let newer_kid = newer.kid().to_string();
+        keyring.replace_rotated(vec![
+            (older, Utc::now() - Duration::hours(2)),
+            (newer, Utc::now() - Duration::hours(1)),
+        ]);
+
+        assert_eq!(keyring.signing_key().kid(), newer_kid);
+        assert!(keyring.find("default").is_some());
+        assert!(keyring.find("unknown").is_none());
+    }
+}
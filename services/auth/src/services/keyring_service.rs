@@ -0,0 +1,313 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::domain::{SigningKeyRecord, SigningKeyStatus};
+use crate::error::AuthError;
+use crate::repository::traits::SigningKeyRepository;
+use crate::services::signing_key::parse_algorithm;
+use crate::services::{Keyring, SecretCipher, SigningKey};
+
+/// How often every instance reloads the rotated keys
+pub const KEYRING_REFRESH_INTERVAL_SECONDS: u64 = 60;
+
+/// Rotates signing keys and keeps the in-process keyring in sync with the database
+pub struct KeyringService<R: SigningKeyRepository> {
+    repo: R,
+    keyring: Arc<Keyring>,
+    cipher: SecretCipher,
+    default_algorithm: String,
+    access_token_lifetime: Duration,
+    propagation_delay: Duration,
+}
+
+impl<R: SigningKeyRepository> KeyringService<R> {
+    pub fn new(repo: R, keyring: Arc<Keyring>, config: &Config) -> Self {
+        Self {
+            repo,
+            keyring,
+            cipher: SecretCipher::new(&config.key_encryption_key),
+            default_algorithm: config.jwt_algorithm.clone(),
+            access_token_lifetime: Duration::seconds(config.jwt_expiry_seconds as i64),
+            propagation_delay: Duration::seconds(config.jwt_key_propagation_seconds as i64),
+        }
+    }
+
+    /// Load every unretired key into the keyring. Keys that fail to parse are skipped
+    /// so a single bad row cannot take verification down.
+    pub async fn reload(&self) -> Result<(), AuthError> {
+        let keys = self
+            .repo
+            .list()
+            .await?
+            .into_iter()
+            .filter(|record| record.retired_at.is_none())
+            .filter_map(|record| match self.load(&record) {
+                Ok(key) => Some((key, record.activates_at)),
+                Err(e) => {
+                    tracing::error!("Skipping signing key {}: {}", record.kid, e);
+                    None
+                }
+            })
+            .collect();
+
+        self.keyring.replace_rotated(keys);
+        Ok(())
+    }
+
+    /// Decrypt a stored key and rebuild it
+    fn load(&self, record: &SigningKeyRecord) -> Result<SigningKey, AuthError> {
+        if !SecretCipher::is_encrypted(&record.private_key) {
+            tracing::warn!("Signing key {} is stored unencrypted; rotate it out", record.kid);
+        }
+
+        SigningKey::from_record(&SigningKeyRecord {
+            private_key: self.cipher.decrypt(&record.private_key)?,
+            ..record.clone()
+        })
+    }
+
+    /// Reload the keyring periodically so rotations on other instances are picked up
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()>
+    where
+        R: 'static,
+    {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                KEYRING_REFRESH_INTERVAL_SECONDS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.reload().await {
+                    tracing::error!("Failed to reload signing keys: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Start a rotation. The new key is published right away and starts signing once
+    /// the propagation delay has passed, giving instances and JWKS caches time to fetch it.
+    pub async fn rotate(
+        &self,
+        algorithm: Option<&str>,
+        private_key: Option<&str>,
+        kid: Option<String>,
+    ) -> Result<SigningKeyRecord, AuthError> {
+        let algorithm = parse_algorithm(algorithm.unwrap_or(&self.default_algorithm))?;
+
+        let (key, private_key) = match private_key {
+            Some(pem) => (SigningKey::from_pem(algorithm, pem.as_bytes(), kid)?, pem.to_string()),
+            None => SigningKey::generate(algorithm, kid)?,
+        };
+
+        if self.repo.find_by_kid(key.kid()).await?.is_some() || self.keyring.find(key.kid()).is_some() {
+            return Err(AuthError::InvalidInput(format!("signing key {} already exists", key.kid())));
+        }
+
+        let now = Utc::now();
+        let record = self
+            .repo
+            .create(&SigningKeyRecord {
+                kid: key.kid().to_string(),
+                algorithm: format!("{:?}", algorithm),
+                private_key: self.cipher.encrypt(&private_key)?,
+                created_at: now,
+                activates_at: now + self.propagation_delay,
+                retired_at: None,
+            })
+            .await?;
+
+        self.reload().await?;
+        Ok(record)
+    }
+
+    /// Retire a superseded key once every token it could have signed has expired
+    pub async fn retire(&self, kid: &str) -> Result<SigningKeyRecord, AuthError> {
+        let keys = self.repo.list().await?;
+        let record = keys
+            .iter()
+            .find(|k| k.kid == kid)
+            .cloned()
+            .ok_or(AuthError::SigningKeyNotFound)?;
+
+        if record.retired_at.is_some() {
+            return Ok(record);
+        }
+
+        let retirable_at = self
+            .retirable_at(&record, &keys)
+            .ok_or_else(|| AuthError::InvalidInput(format!("signing key {} has not been superseded", kid)))?;
+        if retirable_at > Utc::now() {
+            return Err(AuthError::InvalidInput(format!(
+                "signing key {} may still verify unexpired tokens until {}",
+                kid,
+                retirable_at.to_rfc3339()
+            )));
+        }
+
+        self.repo.retire(kid).await?;
+        self.reload().await?;
+
+        self.repo
+            .find_by_kid(kid)
+            .await?
+            .ok_or(AuthError::SigningKeyNotFound)
+    }
+
+    pub async fn list(&self) -> Result<Vec<(SigningKeyRecord, SigningKeyStatus)>, AuthError> {
+        Ok(self
+            .repo
+            .list()
+            .await?
+            .into_iter()
+            .map(|record| {
+                let status = self.status(&record);
+                (record, status)
+            })
+            .collect())
+    }
+
+    pub fn status(&self, record: &SigningKeyRecord) -> SigningKeyStatus {
+        if record.retired_at.is_some() {
+            SigningKeyStatus::Retired
+        } else if record.activates_at > Utc::now() {
+            SigningKeyStatus::Pending
+        } else if record.kid == self.keyring.signing_key().kid() {
+            SigningKeyStatus::Current
+        } else {
+            SigningKeyStatus::Verifying
+        }
+    }
+
+    /// A key stops signing when the next key activates; tokens it signed live for at most
+    /// one access token lifetime after that, plus one refresh interval for lagging instances.
+    fn retirable_at(&self, record: &SigningKeyRecord, keys: &[SigningKeyRecord]) -> Option<DateTime<Utc>> {
+        let superseded_at = keys
+            .iter()
+            .filter(|k| k.retired_at.is_none() && k.activates_at > record.activates_at)
+            .map(|k| k.activates_at)
+            .min()?;
+
+        Some(
+            superseded_at
+                + self.access_token_lifetime
+                + Duration::seconds(KEYRING_REFRESH_INTERVAL_SECONDS as i64),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemorySigningKeyRepository;
+    use crate::domain::{Project, TokenSettings};
+    use crate::services::TokenService;
+    use uuid::Uuid;
+
+    const PROJECT_ID: Uuid = Uuid::from_u128(1);
+
+    fn setup(propagation_seconds: u64) -> (TokenService, KeyringService<InMemorySigningKeyRepository>) {
+        let mut config = Config::for_tests();
+        config.jwt_key_propagation_seconds = propagation_seconds;
+        let token_service = TokenService::new(config.clone()).unwrap();
+        let keyring_service = KeyringService::new(
+            InMemorySigningKeyRepository::default(),
+            token_service.keyring(),
+            &config,
+        );
+        (token_service, keyring_service)
+    }
+
+    fn project_settings(token_service: &TokenService) -> TokenSettings {
+        token_service.settings_for(&Project::new(PROJECT_ID, "test".to_string())).unwrap()
+    }
+
+    fn issue(token_service: &TokenService) -> String {
+        token_service
+            .generate_access_token(&project_settings(token_service), Uuid::new_v4(), "sess_test", vec![], vec![], serde_json::Map::new())
+            .unwrap()
+            .token
+    }
+
+    fn verify(token_service: &TokenService, token: &str) -> Result<(), AuthError> {
+        token_service
+            .verify_access_token(token, &project_settings(token_service))
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_old_tokens_valid() {
+        let (token_service, keyring_service) = setup(0);
+        let before = issue(&token_service);
+
+        let record = keyring_service.rotate(Some("ES256"), None, None).await.unwrap();
+        let after = issue(&token_service);
+
+        let header = jsonwebtoken::decode_header(&after).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(record.kid.as_str()));
+        assert!(verify(&token_service, &before).is_ok());
+        assert!(verify(&token_service, &after).is_ok());
+        assert_eq!(token_service.jwks().keys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rotated_keys_are_stored_encrypted() {
+        let (token_service, keyring_service) = setup(0);
+        let record = keyring_service.rotate(Some("ES256"), None, None).await.unwrap();
+
+        let stored = keyring_service.repo.find_by_kid(&record.kid).await.unwrap().unwrap();
+        assert!(SecretCipher::is_encrypted(&stored.private_key));
+        assert!(!stored.private_key.contains("PRIVATE KEY"));
+
+        // Reloaded from the encrypted row, the key still signs
+        keyring_service.reload().await.unwrap();
+        assert!(verify(&token_service, &issue(&token_service)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rotated_key_waits_for_propagation() {
+        let (token_service, keyring_service) = setup(600);
+
+        let record = keyring_service.rotate(None, None, Some("next".to_string())).await.unwrap();
+        let header = jsonwebtoken::decode_header(&issue(&token_service)).unwrap();
+
+        assert_eq!(record.kid, "next");
+        assert_eq!(header.kid.as_deref(), Some("default"));
+        let statuses = keyring_service.list().await.unwrap();
+        assert_eq!(statuses[0].1, SigningKeyStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_retire_waits_for_access_token_lifetime() {
+        let (token_service, keyring_service) = setup(0);
+        let old = keyring_service.rotate(Some("ES256"), None, None).await.unwrap();
+        let token = issue(&token_service);
+        keyring_service.rotate(Some("ES256"), None, None).await.unwrap();
+
+        // Tokens signed by `old` may still be live
+        let early = keyring_service.retire(&old.kid).await;
+        assert!(matches!(early, Err(AuthError::InvalidInput(_))));
+        assert!(verify(&token_service, &token).is_ok());
+
+        // Once the successor has been signing for longer than any token lives, it can go
+        keyring_service.repo.keys_mut(|keys| {
+            for key in keys.iter_mut() {
+                key.activates_at -= Duration::days(1);
+            }
+        });
+        let retired = keyring_service.retire(&old.kid).await.unwrap();
+
+        assert!(retired.retired_at.is_some());
+        assert!(matches!(verify(&token_service, &token), Err(AuthError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_current_key_cannot_be_retired() {
+        let (_, keyring_service) = setup(0);
+        let current = keyring_service.rotate(Some("EdDSA"), None, None).await.unwrap();
+
+        let result = keyring_service.retire(&current.kid).await;
+        assert!(matches!(result, Err(AuthError::InvalidInput(_))));
+        assert!(matches!(keyring_service.retire("missing").await, Err(AuthError::SigningKeyNotFound)));
+    }
+}
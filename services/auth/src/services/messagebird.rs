@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::AuthError;
+use crate::services::sms_service::SmsSender;
+
+/// Provider name used in project SMS settings
+pub const MESSAGEBIRD: &str = "messagebird";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    errors: Vec<ErrorDetail>,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    code: i64,
+    description: String,
+}
+
+/// Sends SMS through the MessageBird Messages API
+pub struct MessageBirdSmsSender {
+    client: reqwest::Client,
+    base_url: String,
+    access_key: String,
+    originator: String,
+}
+
+impl MessageBirdSmsSender {
+    /// `None` unless the access key and originator are configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            client: reqwest::Client::new(),
+            base_url: config.messagebird_base_url.trim_end_matches('/').to_string(),
+            access_key: config.messagebird_access_key.clone()?,
+            originator: config.messagebird_originator.clone()?,
+        })
+    }
+}
+
+#[async_trait]
+impl SmsSender for MessageBirdSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+        let response = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header(reqwest::header::AUTHORIZATION, format!("AccessKey {}", self.access_key))
+            .timeout(REQUEST_TIMEOUT)
+            .json(&json!({
+                "originator": self.originator,
+                "recipients": [to.trim_start_matches('+')],
+                "body": message,
+            }))
+            .send()
+            .await
+            .map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        Err(AuthError::Sms(match response.json::<ErrorResponse>().await {
+            Ok(ErrorResponse { errors }) if !errors.is_empty() => {
+                let first = &errors[0];
+                format!("MessageBird error {}: {}", first.code, first.description)
+            }
+            _ => format!("MessageBird returned {}", status),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::serve;
+    use axum::{http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_sends_with_access_key_and_maps_errors() {
+        let base_url = serve(Router::new().route(
+            "/messages",
+            post(|headers: HeaderMap, Json(body): Json<Value>| async move {
+                if headers.get("authorization").unwrap() != "AccessKey live_key" {
+                    let error = json!({ "errors": [{ "code": 2, "description": "Request not allowed (incorrect access_key)" }] });
+                    return (StatusCode::UNAUTHORIZED, Json(error));
+                }
+                assert_eq!(body["recipients"], json!(["33612345678"]));
+                (StatusCode::CREATED, Json(json!({ "id": "e8077d803532c0b5937c639b60216938" })))
+            }),
+        ))
+        .await;
+
+        let mut config = Config::for_tests();
+        config.messagebird_access_key = Some("live_key".to_string());
+        config.messagebird_originator = Some("Merco".to_string());
+        config.messagebird_base_url = base_url;
+        MessageBirdSmsSender::from_config(&config).unwrap().send("+33612345678", "hi").await.unwrap();
+
+        config.messagebird_access_key = Some("wrong".to_string());
+        match MessageBirdSmsSender::from_config(&config).unwrap().send("+33612345678", "hi").await {
+            Err(AuthError::Sms(message)) => {
+                assert_eq!(message, "MessageBird error 2: Request not allowed (incorrect access_key)")
+            }
+            other => panic!("expected an SMS error, got {:?}", other.err()),
+        }
+    }
+}
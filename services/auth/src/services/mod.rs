@@ -1,19 +1,45 @@
 pub mod auth_service;
+pub mod claims_hook;
 pub mod token_service;
+pub mod signing_key;
+pub mod keyring;
+pub mod keyring_service;
+pub mod secret_cipher;
+pub mod denylist;
+pub mod denylist_service;
 pub mod password_service;
 pub mod otp_service;
 pub mod mfa_service;
 pub mod oauth_service;
+pub mod oauth_userinfo;
+pub mod oidc;
 pub mod email_service;
 pub mod sms_service;
+pub mod twilio;
+pub mod vonage;
+pub mod messagebird;
+pub mod sms_webhook;
 pub mod webhook_service;
+pub mod passwordless_service;
 
-pub use auth_service::AuthService;
+pub use auth_service::{AuthService, IntrospectedToken};
+pub use claims_hook::ClaimsHook;
 pub use token_service::TokenService;
+pub use signing_key::SigningKey;
+pub use keyring::Keyring;
+pub use keyring_service::KeyringService;
+pub use secret_cipher::SecretCipher;
+pub use denylist::Denylist;
+pub use denylist_service::DenylistService;
 pub use password_service::PasswordService;
 pub use otp_service::OtpService;
 pub use mfa_service::MfaService;
 pub use oauth_service::OAuthService;
 pub use email_service::EmailService;
-pub use sms_service::SmsService;
-pub use webhook_service::WebhookService;
+pub use sms_service::{LogSmsSender, SmsService};
+pub use twilio::TwilioSmsSender;
+pub use vonage::VonageSmsSender;
+pub use messagebird::MessageBirdSmsSender;
+pub use sms_webhook::WebhookSmsSender;
+pub use passwordless_service::{CrossDeviceLink, MagicLinkPoll, PasswordlessService};
+pub use webhook_service::{EventPublisher, WebhookPublisher, WebhookService};
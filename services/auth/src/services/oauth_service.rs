@@ -1,11 +1,45 @@
-use oauth2::{AuthorizationCode, ClientId, ClientSecret, TokenResponse};
-use oauth2::basic::BasicClient;
+use chrono::{Duration, Utc};
+use oauth2::basic::{BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse, BasicTokenType};
 use oauth2::reqwest::async_http_client;
-use oauth2::{AuthUrl, RedirectUrl, TokenUrl};
+use oauth2::{
+    AuthType, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken, StandardTokenResponse,
+    TokenResponse, TokenUrl,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::config::Config;
+use crate::domain::{OAuthProviderSettings, OAuthState, ProjectSettings};
 use crate::error::AuthError;
+use crate::repository::traits::OAuthStateRepository;
+use crate::services::oauth_userinfo::{self, OAuthUserInfo};
+use crate::services::oidc::{OidcClient, ProviderMetadata};
+use crate::utils::crypto::{generate_random_token, hash_token};
 
+const STATE_EXPIRY_MINUTES: i64 = 10;
+const NONCE_LENGTH: usize = 32;
+
+/// The `id_token` OpenID Connect providers return next to the access token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdTokenFields {
+    id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+type OAuthClient = Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OAuthProvider {
     Google,
     GitHub,
@@ -15,112 +49,658 @@ pub enum OAuthProvider {
     Discord,
     Microsoft,
     LinkedIn,
+    /// Any other provider; its issuer or endpoints must be configured in the project settings
     CustomOidc(String),
 }
 
-pub struct OAuthService;
+/// Where a provider sends users to consent, and where we trade codes and tokens
+#[derive(Debug, Clone)]
+struct Endpoints {
+    authorization_url: String,
+    token_url: String,
+    userinfo_url: Option<String>,
+    /// Present when the provider is discovered from its OpenID Connect issuer
+    oidc: Option<Arc<ProviderMetadata>>,
+}
 
-impl OAuthService {
-    pub fn get_authorization_url(
-        provider: &OAuthProvider,
-        client_id: &str,
-        client_secret: Option<&str>,
-        redirect_uri: &str,
+impl OAuthProvider {
+    /// Built-in providers by name; anything else is treated as a custom provider
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "google" => Self::Google,
+            "github" => Self::GitHub,
+            "apple" => Self::Apple,
+            "facebook" => Self::Facebook,
+            "twitter" => Self::Twitter,
+            "discord" => Self::Discord,
+            "microsoft" => Self::Microsoft,
+            "linkedin" => Self::LinkedIn,
+            other => Self::CustomOidc(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Google => "google",
+            Self::GitHub => "github",
+            Self::Apple => "apple",
+            Self::Facebook => "facebook",
+            Self::Twitter => "twitter",
+            Self::Discord => "discord",
+            Self::Microsoft => "microsoft",
+            Self::LinkedIn => "linkedin",
+            Self::CustomOidc(name) => name,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        match self {
+            Self::Google => "Google",
+            Self::GitHub => "GitHub",
+            Self::Apple => "Apple",
+            Self::Facebook => "Facebook",
+            Self::Twitter => "Twitter",
+            Self::Discord => "Discord",
+            Self::Microsoft => "Microsoft",
+            Self::LinkedIn => "LinkedIn",
+            Self::CustomOidc(name) => name,
+        }
+    }
+
+    fn default_scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Google | Self::Microsoft | Self::CustomOidc(_) => &["openid", "email", "profile"],
+            Self::GitHub => &["read:user", "user:email"],
+            Self::Apple => &["name", "email"],
+            Self::Facebook => &["email", "public_profile"],
+            Self::Twitter => &["users.read", "tweet.read"],
+            Self::Discord => &["identify", "email"],
+            Self::LinkedIn => &["openid", "profile", "email"],
+        }
+    }
+
+    /// How the client credentials are sent to the token endpoint
+    fn token_auth_type(&self) -> AuthType {
+        match self {
+            Self::GitHub | Self::Apple | Self::Facebook => AuthType::RequestBody,
+            _ => AuthType::BasicAuth,
+        }
+    }
+
+    fn default_endpoints(&self) -> Option<Endpoints> {
+        let (authorization_url, token_url, userinfo_url) = match self {
+            Self::Google => (
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "https://oauth2.googleapis.com/token",
+                Some("https://openidconnect.googleapis.com/v1/userinfo"),
+            ),
+            Self::GitHub => (
+                "https://github.com/login/oauth/authorize",
+                "https://github.com/login/oauth/access_token",
+                Some("https://api.github.com/user"),
+            ),
+            Self::Apple => (
+                "https://appleid.apple.com/auth/authorize",
+                "https://appleid.apple.com/auth/token",
+                None,
+            ),
+            Self::Facebook => (
+                "https://www.facebook.com/v18.0/dialog/oauth",
+                "https://graph.facebook.com/v18.0/oauth/access_token",
+                Some("https://graph.facebook.com/v18.0/me?fields=id,name,email,picture.type(large)"),
+            ),
+            Self::Twitter => (
+                "https://twitter.com/i/oauth2/authorize",
+                "https://api.twitter.com/2/oauth2/token",
+                Some("https://api.twitter.com/2/users/me?user.fields=profile_image_url"),
+            ),
+            Self::Discord => (
+                "https://discord.com/api/oauth2/authorize",
+                "https://discord.com/api/oauth2/token",
+                Some("https://discord.com/api/users/@me"),
+            ),
+            Self::Microsoft => (
+                "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+                "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+                Some("https://graph.microsoft.com/oidc/userinfo"),
+            ),
+            Self::LinkedIn => (
+                "https://www.linkedin.com/oauth/v2/authorization",
+                "https://www.linkedin.com/oauth/v2/accessToken",
+                Some("https://api.linkedin.com/v2/userinfo"),
+            ),
+            Self::CustomOidc(_) => return None,
+        };
+
+        Some(Endpoints {
+            authorization_url: authorization_url.to_string(),
+            token_url: token_url.to_string(),
+            userinfo_url: userinfo_url.map(str::to_string),
+            oidc: None,
+        })
+    }
+
+    /// Built-in endpoints with the project's overrides applied
+    fn endpoints(&self, settings: &OAuthProviderSettings) -> Result<Endpoints, AuthError> {
+        let defaults = self.default_endpoints();
+        let pick = |configured: &Option<String>, default: Option<String>| configured.clone().or(default);
+        let missing = |endpoint: &str| {
+            AuthError::OAuth(format!("{} has no {} URL configured", self.display_name(), endpoint))
+        };
+
+        Ok(Endpoints {
+            authorization_url: pick(&settings.authorization_url, defaults.as_ref().map(|d| d.authorization_url.clone()))
+                .ok_or_else(|| missing("authorization"))?,
+            token_url: pick(&settings.token_url, defaults.as_ref().map(|d| d.token_url.clone()))
+                .ok_or_else(|| missing("token"))?,
+            userinfo_url: pick(&settings.userinfo_url, defaults.and_then(|d| d.userinfo_url)),
+            oidc: None,
+        })
+    }
+}
+
+/// Runs the authorization-code flow with social providers. The `state` and PKCE
+/// verifier of each browser sign-in are kept server-side until the provider
+/// sends the user back; signing them in is left to `AuthService::signin_oauth`.
+/// Providers configured with an issuer are run as OpenID Connect: their endpoints
+/// are discovered and their id_tokens validated, nonce included.
+pub struct OAuthService<SR: OAuthStateRepository> {
+    state_repo: SR,
+    http: reqwest::Client,
+    oidc: OidcClient,
+    public_url: String,
+}
+
+impl<SR: OAuthStateRepository> OAuthService<SR> {
+    pub fn new(state_repo: SR, config: &Config) -> Self {
+        Self {
+            state_repo,
+            http: reqwest::Client::new(),
+            oidc: OidcClient::new(),
+            public_url: config.public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Our callback URL for a provider, to be registered with it
+    pub fn redirect_uri(&self, provider: &str) -> String {
+        format!("{}/oauth/{}/callback", self.public_url, provider)
+    }
+
+    /// Build the provider's consent URL, remembering where to send the user afterwards
+    /// and, when `link_to` is set, which signed-in user to link the account to
+    pub async fn authorize(
+        &self,
+        project_id: Uuid,
+        settings: &ProjectSettings,
+        provider: &str,
+        redirect_to: Option<&str>,
+        link_to: Option<Uuid>,
     ) -> Result<String, AuthError> {
-        let (auth_url, token_url) = Self::get_provider_urls(provider)?;
+        let config = provider_settings(settings, provider)?;
+        let redirect_to = settings.redirects.resolve(redirect_to)?;
+        let provider = OAuthProvider::from_name(provider);
+        let endpoints = self.endpoints(&provider, config).await?;
+        let client = client(&provider, config, &endpoints, &self.redirect_uri(provider.as_str()))?;
+
+        let scopes: Vec<String> = if config.scopes.is_empty() {
+            provider.default_scopes().iter().map(|s| s.to_string()).collect()
+        } else {
+            config.scopes.clone()
+        };
+
+        let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+        let mut request = client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.into_iter().map(Scope::new))
+            .set_pkce_challenge(challenge);
+        if provider == OAuthProvider::Apple {
+            // Apple insists on posting the callback when asked for the name or email
+            request = request.add_extra_param("response_mode", "form_post");
+        }
+        let nonce = endpoints.oidc.is_some().then(|| generate_random_token(NONCE_LENGTH));
+        if let Some(nonce) = &nonce {
+            request = request.add_extra_param("nonce", nonce.clone());
+        }
+        let (url, csrf_token) = request.url();
 
-        let client = BasicClient::new(
-            ClientId::new(client_id.to_string()),
-            client_secret.map(|s| ClientSecret::new(s.to_string())),
-            AuthUrl::new(auth_url).map_err(|e| AuthError::OAuth(e.to_string()))?,
-            Some(TokenUrl::new(token_url).map_err(|e| AuthError::OAuth(e.to_string()))?),
+        let state = OAuthState::new(
+            project_id,
+            provider.as_str().to_string(),
+            hash_token(csrf_token.secret()),
+            verifier.secret().clone(),
+            redirect_to.to_string(),
+            Utc::now() + Duration::minutes(STATE_EXPIRY_MINUTES),
         )
-        .set_redirect_uri(RedirectUrl::new(redirect_uri.to_string()).map_err(|e| AuthError::OAuth(e.to_string()))?);
+        .with_user_id(link_to)
+        .with_nonce(nonce);
+        self.state_repo.create(&state).await?;
 
-        let (auth_url, _csrf_token) = client
-            .authorize_url(oauth2::CsrfToken::new_random)
-            .url();
+        Ok(url.to_string())
+    }
+
+    /// Consume the sign-in a callback's `state` belongs to; each can be used only once
+    pub async fn take_state(&self, provider: &str, state: &str) -> Result<OAuthState, AuthError> {
+        let stored = self
+            .state_repo
+            .take(&hash_token(state))
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+
+        if stored.provider != provider {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(stored)
+    }
+
+    /// The sign-in or link a callback's `state` belongs to, without consuming it
+    pub async fn find_state(&self, provider: &str, state: &str) -> Result<OAuthState, AuthError> {
+        self.state_repo
+            .find(&hash_token(state))
+            .await?
+            .filter(|stored| stored.provider == provider)
+            .ok_or(AuthError::InvalidToken)
+    }
+
+    /// Consume a link request on behalf of the signed-in user. Only the user who
+    /// started it can finish it, so a provider URL from someone else's link request
+    /// cannot attach the visitor's provider account to that person.
+    pub async fn take_link_state(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+        provider: &str,
+        state: &str,
+    ) -> Result<OAuthState, AuthError> {
+        let stored = self.take_state(provider, state).await?;
+        if stored.project_id != project_id || stored.user_id != Some(user_id) {
+            return Err(AuthError::InvalidToken);
+        }
 
-        Ok(auth_url.to_string())
+        Ok(stored)
     }
 
+    /// Trade an authorization code for an access token and fetch who it belongs to.
+    /// OpenID Connect providers identify the user by their validated id_token.
     pub async fn exchange_code(
-        provider: &OAuthProvider,
-        client_id: &str,
-        client_secret: Option<&str>,
+        &self,
+        settings: &ProjectSettings,
+        provider: &str,
         redirect_uri: &str,
         code: &str,
+        code_verifier: Option<&str>,
+        nonce: Option<&str>,
     ) -> Result<OAuthUserInfo, AuthError> {
-        let (auth_url, token_url) = Self::get_provider_urls(provider)?;
+        let config = provider_settings(settings, provider)?;
+        let provider = OAuthProvider::from_name(provider);
+        let endpoints = self.endpoints(&provider, config).await?;
+        let client = client(&provider, config, &endpoints, redirect_uri)?;
 
-        let client = BasicClient::new(
-            ClientId::new(client_id.to_string()),
-            client_secret.map(|s| ClientSecret::new(s.to_string())),
-            AuthUrl::new(auth_url).map_err(|e| AuthError::OAuth(e.to_string()))?,
-            Some(TokenUrl::new(token_url).map_err(|e| AuthError::OAuth(e.to_string()))?),
-        )
-        .set_redirect_uri(RedirectUrl::new(redirect_uri.to_string()).map_err(|e| AuthError::OAuth(e.to_string()))?);
-
-        let token_result = client
-            .exchange_code(AuthorizationCode::new(code.to_string()))
+        let mut request = client.exchange_code(AuthorizationCode::new(code.to_string()));
+        if let Some(verifier) = code_verifier {
+            request = request.set_pkce_verifier(PkceCodeVerifier::new(verifier.to_string()));
+        }
+        let token = request
             .request_async(async_http_client)
             .await
-            .map_err(|e| AuthError::OAuth(e.to_string()))?;
-
-        // TODO: Fetch user info from provider's userinfo endpoint
-        // For now, return placeholder
-        Ok(OAuthUserInfo {
-            provider_id: "".to_string(),
-            email: None,
-            name: None,
-        })
-    }
+            .map_err(|e| AuthError::OAuth(format!("{} rejected the code: {}", provider.display_name(), e)))?;
+
+        let Some(metadata) = &endpoints.oidc else {
+            return oauth_userinfo::fetch(
+                &self.http,
+                &provider,
+                endpoints.userinfo_url.as_deref(),
+                token.access_token().secret(),
+                token.extra_fields().id_token.as_deref(),
+            )
+            .await;
+        };
+
+        let id_token = token
+            .extra_fields()
+            .id_token
+            .as_deref()
+            .ok_or_else(|| AuthError::OAuth(format!("{} did not return an id_token", provider.display_name())))?;
+        let claims = self.oidc.validate_id_token(metadata, &config.client_id, id_token, nonce).await?;
+        let mut profile = oauth_userinfo::standard(&claims)
+            .ok_or_else(|| AuthError::OAuth(format!("{} id_token has no subject", provider.display_name())))?;
 
-    fn get_provider_urls(provider: &OAuthProvider) -> Result<(String, String), AuthError> {
-        match provider {
-            OAuthProvider::Google => Ok((
-                "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
-                "https://oauth2.googleapis.com/token".to_string(),
-            )),
-            OAuthProvider::GitHub => Ok((
-                "https://github.com/login/oauth/authorize".to_string(),
-                "https://github.com/login/oauth/access_token".to_string(),
-            )),
-            OAuthProvider::Apple => Ok((
-                "https://appleid.apple.com/auth/authorize".to_string(),
-                "https://appleid.apple.com/auth/token".to_string(),
-            )),
-            OAuthProvider::Facebook => Ok((
-                "https://www.facebook.com/v18.0/dialog/oauth".to_string(),
-                "https://graph.facebook.com/v18.0/oauth/access_token".to_string(),
-            )),
-            OAuthProvider::Twitter => Ok((
-                "https://twitter.com/i/oauth2/authorize".to_string(),
-                "https://api.twitter.com/2/oauth2/token".to_string(),
-            )),
-            OAuthProvider::Discord => Ok((
-                "https://discord.com/api/oauth2/authorize".to_string(),
-                "https://discord.com/api/oauth2/token".to_string(),
-            )),
-            OAuthProvider::Microsoft => Ok((
-                "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".to_string(),
-                "https://login.microsoftonline.com/common/oauth2/v2.0/token".to_string(),
-            )),
-            OAuthProvider::LinkedIn => Ok((
-                "https://www.linkedin.com/oauth/v2/authorization".to_string(),
-                "https://www.linkedin.com/oauth/v2/accessToken".to_string(),
-            )),
-            OAuthProvider::CustomOidc(base_url) => Ok((
-                format!("{}/authorize", base_url),
-                format!("{}/token", base_url),
-            )),
+        // Many issuers keep the profile out of the id_token unless asked; userinfo fills
+        // it in, provided it describes the same subject
+        if profile.email.is_none() {
+            if let Some(url) = &endpoints.userinfo_url {
+                let claims: Value =
+                    oauth_userinfo::get(&self.http, &provider, url, token.access_token().secret()).await?;
+                let userinfo = oauth_userinfo::standard(&claims).filter(|u| u.provider_id == profile.provider_id);
+                if let Some(userinfo) = userinfo {
+                    profile.email = userinfo.email;
+                    profile.email_verified = userinfo.email_verified;
+                    profile.name = profile.name.or(userinfo.name);
+                    profile.avatar_url = profile.avatar_url.or(userinfo.avatar_url);
+                }
+            }
         }
+
+        Ok(profile)
+    }
+
+    /// The provider's endpoints: discovered from its issuer when one is configured,
+    /// otherwise built in. Configured endpoints override either.
+    async fn endpoints(
+        &self,
+        provider: &OAuthProvider,
+        config: &OAuthProviderSettings,
+    ) -> Result<Endpoints, AuthError> {
+        let Some(issuer) = &config.issuer else {
+            return provider.endpoints(config);
+        };
+        let metadata = self.oidc.discover(issuer).await?;
+
+        Ok(Endpoints {
+            authorization_url: config
+                .authorization_url
+                .clone()
+                .unwrap_or_else(|| metadata.authorization_endpoint.clone()),
+            token_url: config.token_url.clone().unwrap_or_else(|| metadata.token_endpoint.clone()),
+            userinfo_url: config.userinfo_url.clone().or_else(|| metadata.userinfo_endpoint.clone()),
+            oidc: Some(metadata),
+        })
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct OAuthUserInfo {
-    pub provider_id: String,
-    pub email: Option<String>,
-    pub name: Option<String>,
+/// The project's registration with a provider, if it is enabled
+fn provider_settings<'a>(settings: &'a ProjectSettings, provider: &str) -> Result<&'a OAuthProviderSettings, AuthError> {
+    settings
+        .oauth
+        .get(provider)
+        .filter(|config| config.enabled)
+        .ok_or_else(|| AuthError::OAuth(format!("{} sign-in is not enabled", provider)))
+}
+
+fn client(
+    provider: &OAuthProvider,
+    config: &OAuthProviderSettings,
+    endpoints: &Endpoints,
+    redirect_uri: &str,
+) -> Result<OAuthClient, AuthError> {
+    let invalid = |e: url::ParseError| AuthError::OAuth(e.to_string());
+
+    Ok(OAuthClient::new(
+        ClientId::new(config.client_id.clone()),
+        config.client_secret.clone().map(ClientSecret::new),
+        AuthUrl::new(endpoints.authorization_url.clone()).map_err(invalid)?,
+        Some(TokenUrl::new(endpoints.token_url.clone()).map_err(invalid)?),
+    )
+    .set_auth_type(provider.token_auth_type())
+    .set_redirect_uri(RedirectUrl::new(redirect_uri.to_string()).map_err(invalid)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::RedirectSettings;
+    use crate::repository::memory::InMemoryOAuthStateRepository;
+    use crate::utils::crypto::pkce_challenge;
+    use crate::utils::test_server::{serve, FakeServer};
+    use axum::{extract::State, http::HeaderMap, routing::{get, post}, Form, Json, Router};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    type Captured = Arc<Mutex<Option<HashMap<String, String>>>>;
+
+    async fn fake_provider(captured: Captured) -> String {
+        let router = Router::new()
+            .route(
+                "/token",
+                post(|State(captured): State<Captured>, Form(form): Form<HashMap<String, String>>| async move {
+                    *captured.lock().unwrap() = Some(form);
+                    Json(json!({ "access_token": "at_123", "token_type": "bearer", "expires_in": 3600 }))
+                }),
+            )
+            .route(
+                "/userinfo",
+                get(|headers: HeaderMap| async move {
+                    assert_eq!(headers.get("authorization").unwrap(), "Bearer at_123");
+                    Json(json!({ "sub": "42", "email": "ada@example.com", "email_verified": "true", "name": "Ada" }))
+                }),
+            )
+            .with_state(captured);
+
+        serve(router).await
+    }
+
+    fn settings(base_url: &str) -> ProjectSettings {
+        let mut settings = ProjectSettings {
+            redirects: RedirectSettings {
+                site_url: Some("https://app.example.com".to_string()),
+                allowed_urls: Vec::new(),
+            },
+            ..Default::default()
+        };
+        settings.oauth.insert(
+            "acme".to_string(),
+            OAuthProviderSettings {
+                enabled: true,
+                client_id: "client".to_string(),
+                client_secret: Some("secret".to_string()),
+                scopes: Vec::new(),
+                issuer: None,
+                authorization_url: Some(format!("{}/authorize", base_url)),
+                token_url: Some(format!("{}/token", base_url)),
+                userinfo_url: Some(format!("{}/userinfo", base_url)),
+            },
+        );
+        settings
+    }
+
+    fn service() -> OAuthService<InMemoryOAuthStateRepository> {
+        OAuthService::new(InMemoryOAuthStateRepository::default(), &Config::for_tests())
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow_with_pkce() {
+        let captured = Captured::default();
+        let base_url = fake_provider(captured.clone()).await;
+        let settings = settings(&base_url);
+        let service = service();
+
+        let url = service
+            .authorize(Uuid::new_v4(), &settings, "acme", Some("https://app.example.com/welcome"), None)
+            .await
+            .unwrap();
+        let url = url::Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert!(url.as_str().starts_with(&format!("{}/authorize?", base_url)));
+        assert_eq!(params["redirect_uri"], "http://localhost:3000/auth/oauth/acme/callback");
+        assert_eq!(params["scope"], "openid email profile");
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let stored = service.take_state("acme", &params["state"]).await.unwrap();
+        assert_eq!(stored.redirect_to, "https://app.example.com/welcome");
+        assert_eq!(pkce_challenge(&stored.code_verifier), params["code_challenge"]);
+
+        // The state is single use
+        assert!(matches!(
+            service.take_state("acme", &params["state"]).await,
+            Err(AuthError::InvalidToken)
+        ));
+
+        let profile = service
+            .exchange_code(&settings, "acme", &params["redirect_uri"], "code_1", Some(&stored.code_verifier), None)
+            .await
+            .unwrap();
+        assert_eq!(profile.provider_id, "42");
+        assert_eq!(profile.email.as_deref(), Some("ada@example.com"));
+        assert!(profile.email_verified);
+
+        let form = captured.lock().unwrap().take().unwrap();
+        assert_eq!(form["grant_type"], "authorization_code");
+        assert_eq!(form["code"], "code_1");
+        assert_eq!(form["code_verifier"], stored.code_verifier);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unconfigured_providers_and_foreign_redirects() {
+        let mut settings = settings("http://127.0.0.1:9");
+        let service = service();
+        let project_id = Uuid::new_v4();
+
+        let result = service.authorize(project_id, &settings, "acme", Some("https://evil.example.com"), None).await;
+        assert!(matches!(result, Err(AuthError::RedirectNotAllowed)));
+
+        // A custom provider has no endpoints of its own
+        let mut custom = settings.oauth["acme"].clone();
+        custom.authorization_url = None;
+        settings.oauth.insert("other".to_string(), custom);
+        assert!(matches!(
+            service.authorize(project_id, &settings, "other", None, None).await,
+            Err(AuthError::OAuth(_))
+        ));
+
+        settings.oauth.get_mut("acme").unwrap().enabled = false;
+        assert!(matches!(
+            service.authorize(project_id, &settings, "acme", None, None).await,
+            Err(AuthError::OAuth(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_state_is_bound_to_its_provider() {
+        let settings = settings("http://127.0.0.1:9");
+        let service = service();
+
+        let url = service.authorize(Uuid::new_v4(), &settings, "acme", None, None).await.unwrap();
+        let state = url::Url::parse(&url)
+            .unwrap()
+            .query_pairs()
+            .find(|(name, _)| name == "state")
+            .unwrap()
+            .1
+            .into_owned();
+
+        assert!(matches!(service.take_state("google", &state).await, Err(AuthError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_link_state_is_bound_to_the_user_who_started_it() {
+        let settings = settings("http://127.0.0.1:9");
+        let service = service();
+        let (project_id, owner, visitor) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let state_of = |url: String| {
+            url::Url::parse(&url)
+                .unwrap()
+                .query_pairs()
+                .find(|(name, _)| name == "state")
+                .unwrap()
+                .1
+                .into_owned()
+        };
+
+        let state = state_of(service.authorize(project_id, &settings, "acme", None, Some(owner)).await.unwrap());
+        // The callback only looks; the signed-in app finishes the link
+        assert_eq!(service.find_state("acme", &state).await.unwrap().user_id, Some(owner));
+        assert!(matches!(
+            service.take_link_state(project_id, visitor, "acme", &state).await,
+            Err(AuthError::InvalidToken)
+        ));
+
+        let state = state_of(service.authorize(project_id, &settings, "acme", None, Some(owner)).await.unwrap());
+        let stored = service.take_link_state(project_id, owner, "acme", &state).await.unwrap();
+        assert_eq!(stored.user_id, Some(owner));
+        assert!(service.find_state("acme", &state).await.is_err());
+
+        // A sign-in request cannot be used to link
+        let state = state_of(service.authorize(project_id, &settings, "acme", None, None).await.unwrap());
+        assert!(service.take_link_state(project_id, owner, "acme", &state).await.is_err());
+    }
+
+    /// An OpenID Connect issuer whose id_tokens carry the nonce it was last sent, and
+    /// no email; that is left to userinfo
+    async fn fake_issuer(nonce: Arc<Mutex<String>>) -> String {
+        let server = FakeServer::bind().await;
+        let issuer = server.base_url.clone();
+        let (key, _) = crate::services::SigningKey::generate(jsonwebtoken::Algorithm::ES256, None).unwrap();
+        let key = Arc::new(key);
+
+        let document = json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "userinfo_endpoint": format!("{}/userinfo", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+        });
+        let jwks = json!({ "keys": [key.jwk().unwrap()] });
+        let token_issuer = issuer.clone();
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(move || async move { Json(document) }))
+            .route("/jwks", get(move || async move { Json(jwks) }))
+            .route(
+                "/token",
+                post(move || async move {
+                    let now = Utc::now().timestamp();
+                    let claims = json!({
+                        "iss": token_issuer,
+                        "aud": "client",
+                        "sub": "f:3c9a:ada",
+                        "nonce": *nonce.lock().unwrap(),
+                        "iat": now,
+                        "exp": now + 300,
+                    });
+                    let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::ES256);
+                    header.kid = Some(key.kid().to_string());
+                    let id_token = jsonwebtoken::encode(&header, &claims, key.encoding_key()).unwrap();
+                    Json(json!({ "access_token": "at_123", "token_type": "bearer", "id_token": id_token }))
+                }),
+            )
+            .route(
+                "/userinfo",
+                get(|| async { Json(json!({ "sub": "f:3c9a:ada", "email": "ada@example.com", "email_verified": true })) }),
+            );
+        server.serve(router)
+    }
+
+    #[tokio::test]
+    async fn test_openid_connect_provider_from_discovery() {
+        let nonce = Arc::new(Mutex::new(String::new()));
+        let issuer = fake_issuer(nonce.clone()).await;
+        let mut settings = settings("http://127.0.0.1:9");
+        settings.oauth.insert(
+            "keycloak".to_string(),
+            OAuthProviderSettings {
+                enabled: true,
+                client_id: "client".to_string(),
+                client_secret: Some("secret".to_string()),
+                scopes: Vec::new(),
+                issuer: Some(issuer.clone()),
+                authorization_url: None,
+                token_url: None,
+                userinfo_url: None,
+            },
+        );
+        let service = service();
+
+        let url = service.authorize(Uuid::new_v4(), &settings, "keycloak", None, None).await.unwrap();
+        let url = url::Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert!(url.as_str().starts_with(&format!("{}/authorize?", issuer)));
+
+        let stored = service.take_state("keycloak", &params["state"]).await.unwrap();
+        assert_eq!(stored.nonce.as_deref(), Some(params["nonce"].as_str()));
+        *nonce.lock().unwrap() = params["nonce"].clone();
+
+        let profile = service
+            .exchange_code(&settings, "keycloak", &params["redirect_uri"], "code_1", None, stored.nonce.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(profile.provider_id, "f:3c9a:ada");
+        assert_eq!(profile.email.as_deref(), Some("ada@example.com"));
+        assert!(profile.email_verified);
+
+        // An id_token from another sign-in is refused
+        let result = service
+            .exchange_code(&settings, "keycloak", &params["redirect_uri"], "code_2", None, Some("other"))
+            .await;
+        assert!(matches!(result, Err(AuthError::OAuth(_))));
+    }
 }
@@ -0,0 +1,339 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::AuthError;
+use crate::services::oauth_service::OAuthProvider;
+
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Some providers (GitHub among them) reject API calls without a user agent
+const USER_AGENT: &str = "merco-auth";
+
+/// Who a provider says signed in, normalised across providers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OAuthUserInfo {
+    /// The provider's stable id for the user
+    pub provider_id: String,
+    pub email: Option<String>,
+    /// Whether the provider vouches that the user controls `email`
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// Fetch and normalise the profile behind an access token. Apple has no userinfo
+/// endpoint; its profile is read from the `id_token` of the same token response.
+pub async fn fetch(
+    http: &reqwest::Client,
+    provider: &OAuthProvider,
+    userinfo_url: Option<&str>,
+    access_token: &str,
+    id_token: Option<&str>,
+) -> Result<OAuthUserInfo, AuthError> {
+    if *provider == OAuthProvider::Apple {
+        let id_token = id_token
+            .ok_or_else(|| AuthError::OAuth("Apple did not return an id_token".to_string()))?;
+        return apple(id_token).ok_or_else(|| AuthError::OAuth("Apple id_token is malformed".to_string()));
+    }
+
+    let url = userinfo_url.ok_or_else(|| {
+        AuthError::OAuth(format!("{} has no userinfo URL configured", provider.display_name()))
+    })?;
+    let claims: Value = get(http, provider, url, access_token).await?;
+
+    let profile = match provider {
+        OAuthProvider::GitHub => {
+            let emails: Vec<GitHubEmail> = get(http, provider, &format!("{}/emails", url), access_token).await?;
+            github(&claims, &emails)
+        }
+        OAuthProvider::Microsoft => microsoft(&claims),
+        OAuthProvider::Discord => discord(&claims),
+        OAuthProvider::Facebook => facebook(&claims),
+        OAuthProvider::Twitter => twitter(&claims),
+        OAuthProvider::Google | OAuthProvider::LinkedIn | OAuthProvider::CustomOidc(_) => standard(&claims),
+        OAuthProvider::Apple => unreachable!("Apple profiles come from the id_token"),
+    };
+
+    profile.ok_or_else(|| AuthError::OAuth(format!("{} profile has no user id", provider.display_name())))
+}
+
+pub(crate) async fn get<T: DeserializeOwned>(
+    http: &reqwest::Client,
+    provider: &OAuthProvider,
+    url: &str,
+    access_token: &str,
+) -> Result<T, AuthError> {
+    let failed = |e: reqwest::Error| {
+        AuthError::OAuth(format!("fetching the {} profile failed: {}", provider.display_name(), e))
+    };
+
+    http.get(url)
+        .bearer_auth(access_token)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(failed)?
+        .error_for_status()
+        .map_err(failed)?
+        .json()
+        .await
+        .map_err(failed)
+}
+
+fn string(claims: &Value, name: &str) -> Option<String> {
+    claims
+        .get(name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// A string id, or a numeric one as GitHub sends
+fn id(claims: &Value, name: &str) -> Option<String> {
+    match claims.get(name)? {
+        Value::String(id) if !id.is_empty() => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// `true`, or `"true"` as Apple and some OIDC providers send it
+fn flag(claims: &Value, name: &str) -> bool {
+    match claims.get(name) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => value == "true",
+        _ => false,
+    }
+}
+
+/// Standard OpenID Connect claims, as Google, LinkedIn and most OIDC providers send them
+pub(crate) fn standard(claims: &Value) -> Option<OAuthUserInfo> {
+    Some(OAuthUserInfo {
+        provider_id: id(claims, "sub")?,
+        email: string(claims, "email"),
+        email_verified: flag(claims, "email_verified"),
+        name: string(claims, "name"),
+        avatar_url: string(claims, "picture"),
+    })
+}
+
+/// Work and school accounts can have any address set by their tenant's admin, so
+/// Microsoft addresses are never treated as verified. The `picture` claim needs the
+/// access token to load, which is no use to an app.
+fn microsoft(claims: &Value) -> Option<OAuthUserInfo> {
+    Some(OAuthUserInfo {
+        email_verified: false,
+        avatar_url: None,
+        ..standard(claims)?
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// The primary address from `/user/emails` when it is verified, since the public
+/// profile address is optional and may never have been confirmed
+fn github(user: &Value, emails: &[GitHubEmail]) -> Option<OAuthUserInfo> {
+    let primary = emails.iter().find(|e| e.primary && e.verified);
+
+    Some(OAuthUserInfo {
+        provider_id: id(user, "id")?,
+        email: primary.map(|e| e.email.clone()).or_else(|| string(user, "email")),
+        email_verified: primary.is_some(),
+        name: string(user, "name").or_else(|| string(user, "login")),
+        avatar_url: string(user, "avatar_url"),
+    })
+}
+
+fn discord(user: &Value) -> Option<OAuthUserInfo> {
+    let provider_id = id(user, "id")?;
+    let avatar_url = string(user, "avatar")
+        .map(|hash| format!("https://cdn.discordapp.com/avatars/{}/{}.png", provider_id, hash));
+
+    Some(OAuthUserInfo {
+        email: string(user, "email"),
+        email_verified: flag(user, "verified"),
+        name: string(user, "global_name").or_else(|| string(user, "username")),
+        avatar_url,
+        provider_id,
+    })
+}
+
+/// Facebook only shares addresses its users have confirmed
+fn facebook(user: &Value) -> Option<OAuthUserInfo> {
+    let email = string(user, "email");
+
+    Some(OAuthUserInfo {
+        provider_id: id(user, "id")?,
+        email_verified: email.is_some(),
+        email,
+        name: string(user, "name"),
+        avatar_url: user.pointer("/picture/data/url").and_then(Value::as_str).map(str::to_string),
+    })
+}
+
+/// Twitter wraps the user in `data` and shares no email address
+fn twitter(response: &Value) -> Option<OAuthUserInfo> {
+    let user = response.get("data")?;
+
+    Some(OAuthUserInfo {
+        provider_id: id(user, "id")?,
+        email: None,
+        email_verified: false,
+        name: string(user, "name").or_else(|| string(user, "username")),
+        avatar_url: string(user, "profile_image_url"),
+    })
+}
+
+/// Claims of an id_token received straight from Apple's token endpoint. Its
+/// signature is not checked: the token came over TLS from the issuer in exchange
+/// for our client credentials, which OpenID Connect accepts in its place.
+/// Apple sends the user's name only to the app, on first sign-in.
+fn apple(id_token: &str) -> Option<OAuthUserInfo> {
+    let payload = id_token.split('.').nth(1)?;
+    let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+
+    Some(OAuthUserInfo {
+        name: None,
+        avatar_url: None,
+        ..standard(&claims)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::serve;
+    use axum::{http::HeaderMap, routing::get, Json, Router};
+    use serde_json::json;
+
+    #[test]
+    fn test_normalises_provider_profiles() {
+        let discord = discord(&json!({
+            "id": "80351110224678912",
+            "username": "nelly",
+            "global_name": "Nelly",
+            "avatar": "8342729096ea3675442027381ff50dfe",
+            "email": "nelly@example.com",
+            "verified": true
+        }))
+        .unwrap();
+        assert_eq!(discord.name.as_deref(), Some("Nelly"));
+        assert_eq!(
+            discord.avatar_url.as_deref(),
+            Some("https://cdn.discordapp.com/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png")
+        );
+        assert!(discord.email_verified);
+
+        let facebook = facebook(&json!({
+            "id": "10158",
+            "name": "Ada Lovelace",
+            "email": "ada@example.com",
+            "picture": { "data": { "url": "https://graph.facebook.com/10158/picture" } }
+        }))
+        .unwrap();
+        assert!(facebook.email_verified);
+        assert_eq!(facebook.avatar_url.as_deref(), Some("https://graph.facebook.com/10158/picture"));
+
+        let twitter = twitter(&json!({
+            "data": { "id": "2244994945", "name": "", "username": "TwitterDev", "profile_image_url": "https://pbs.twimg.com/a.jpg" }
+        }))
+        .unwrap();
+        assert_eq!(twitter.provider_id, "2244994945");
+        assert_eq!(twitter.name.as_deref(), Some("TwitterDev"));
+        assert_eq!(twitter.email, None);
+
+        let claims = json!({
+            "sub": "AAAAAAAAAAAAAAAAAAAAAIkzWdf",
+            "email": "ada@contoso.com",
+            "email_verified": true,
+            "name": "Ada",
+            "picture": "https://graph.microsoft.com/v1.0/me/photo/$value"
+        });
+        assert!(standard(&claims).unwrap().email_verified);
+        let microsoft = microsoft(&claims).unwrap();
+        assert!(!microsoft.email_verified);
+        assert_eq!(microsoft.avatar_url, None);
+    }
+
+    #[test]
+    fn test_reads_apple_id_token() {
+        let claims = json!({
+            "iss": "https://appleid.apple.com",
+            "sub": "001234.abcdef",
+            "email": "x7k2@privaterelay.appleid.com",
+            "email_verified": "true",
+            "is_private_email": "true"
+        });
+        let id_token = format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","kid":"k1"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let profile = apple(&id_token).unwrap();
+        assert_eq!(profile.provider_id, "001234.abcdef");
+        assert_eq!(profile.email.as_deref(), Some("x7k2@privaterelay.appleid.com"));
+        assert!(profile.email_verified);
+
+        assert_eq!(apple("not-a-jwt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_github_uses_primary_verified_email() {
+        let router = Router::new()
+            .route(
+                "/user",
+                get(|headers: HeaderMap| async move {
+                    assert_eq!(headers.get("user-agent").unwrap(), USER_AGENT);
+                    Json(json!({
+                        "id": 583231,
+                        "login": "octocat",
+                        "name": null,
+                        "email": "public@example.com",
+                        "avatar_url": "https://avatars.githubusercontent.com/u/583231"
+                    }))
+                }),
+            )
+            .route(
+                "/user/emails",
+                get(|| async {
+                    Json(json!([
+                        { "email": "public@example.com", "primary": false, "verified": true },
+                        { "email": "octocat@example.com", "primary": true, "verified": true }
+                    ]))
+                }),
+            );
+        let base_url = serve(router).await;
+
+        let profile = fetch(
+            &reqwest::Client::new(),
+            &OAuthProvider::GitHub,
+            Some(&format!("{}/user", base_url)),
+            "gho_123",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(profile.provider_id, "583231");
+        assert_eq!(profile.email.as_deref(), Some("octocat@example.com"));
+        assert!(profile.email_verified);
+        assert_eq!(profile.name.as_deref(), Some("octocat"));
+
+        // An unverified primary address is not trusted, and this user has no public one
+        let unverified: Vec<GitHubEmail> =
+            serde_json::from_value(json!([{ "email": "new@example.com", "primary": true, "verified": false }])).unwrap();
+        let profile = github(&json!({ "id": 1, "login": "new" }), &unverified).unwrap();
+        assert_eq!(profile.email, None);
+        assert!(!profile.email_verified);
+    }
+}
@@ -0,0 +1,352 @@
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::error::AuthError;
+use crate::utils::crypto::constant_time_eq;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long discovery documents and key sets are trusted before being fetched again
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Shortest gap between key set fetches prompted by an unknown `kid`, so tokens
+/// naming made-up keys cannot make us hammer the issuer
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// Clock skew tolerated on `exp`, `nbf` and `iat`
+const LEEWAY_SECONDS: u64 = 60;
+
+/// The parts of an issuer's `/.well-known/openid-configuration` we use
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+struct Cached<T> {
+    value: Arc<T>,
+    fetched_at: Instant,
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value: Arc::new(value),
+            fetched_at: Instant::now(),
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.fetched_at.elapsed() < CACHE_TTL
+    }
+}
+
+/// Discovers OpenID Connect issuers and checks the id_tokens they sign. Discovery
+/// documents and key sets are cached per issuer; a token signed with a key we have
+/// not seen refreshes the key set, so issuers can rotate keys without a restart.
+pub struct OidcClient {
+    http: reqwest::Client,
+    metadata: RwLock<HashMap<String, Cached<ProviderMetadata>>>,
+    keys: RwLock<HashMap<String, Cached<Vec<Jwk>>>>,
+    jwks_min_refresh: Duration,
+}
+
+impl Default for OidcClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OidcClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            metadata: RwLock::new(HashMap::new()),
+            keys: RwLock::new(HashMap::new()),
+            jwks_min_refresh: JWKS_MIN_REFRESH,
+        }
+    }
+
+    /// The issuer's discovery document, which must name the issuer it was fetched for
+    pub async fn discover(&self, issuer: &str) -> Result<Arc<ProviderMetadata>, AuthError> {
+        let issuer = issuer.trim_end_matches('/');
+        if let Some(cached) = self.metadata.read().unwrap().get(issuer).filter(|c| c.is_fresh()) {
+            return Ok(cached.value.clone());
+        }
+
+        let metadata: ProviderMetadata =
+            self.get(&format!("{}/.well-known/openid-configuration", issuer)).await?;
+        if metadata.issuer.trim_end_matches('/') != issuer {
+            return Err(AuthError::OAuth(format!(
+                "discovery document for {} names issuer {}",
+                issuer, metadata.issuer
+            )));
+        }
+
+        let cached = Cached::new(metadata);
+        let metadata = cached.value.clone();
+        self.metadata.write().unwrap().insert(issuer.to_string(), cached);
+        Ok(metadata)
+    }
+
+    /// Check an id_token's signature against the issuer's published keys, and that it
+    /// was issued by that issuer, for our client, for this sign-in and is still valid.
+    /// Returns its claims.
+    pub async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        client_id: &str,
+        id_token: &str,
+        nonce: Option<&str>,
+    ) -> Result<Value, AuthError> {
+        let header = jsonwebtoken::decode_header(id_token).map_err(rejected)?;
+        if !is_asymmetric(header.alg) {
+            return Err(rejected(format!("{:?} signatures are not accepted", header.alg)));
+        }
+
+        let jwk = self.find_key(&metadata.jwks_uri, header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(rejected)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.leeway = LEEWAY_SECONDS;
+
+        let claims = jsonwebtoken::decode::<Value>(id_token, &key, &validation)
+            .map_err(rejected)?
+            .claims;
+
+        // A token minted for several audiences must name us as the party it was issued to
+        if let Some(azp) = claims.get("azp").and_then(Value::as_str) {
+            if azp != client_id {
+                return Err(rejected("issued to another client"));
+            }
+        }
+
+        if let Some(expected) = nonce {
+            let actual = claims.get("nonce").and_then(Value::as_str).unwrap_or_default();
+            if !constant_time_eq(actual.as_bytes(), expected.as_bytes()) {
+                return Err(rejected("nonce mismatch"));
+            }
+        }
+
+        Ok(claims)
+    }
+
+    /// The key a token names, refreshing the key set when the key is unknown
+    async fn find_key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<Jwk, AuthError> {
+        let recently_fetched = {
+            let keys = self.keys.read().unwrap();
+            match keys.get(jwks_uri) {
+                Some(cached) if cached.is_fresh() => {
+                    if let Some(jwk) = select_key(&cached.value, kid) {
+                        return Ok(jwk.clone());
+                    }
+                    cached.fetched_at.elapsed() < self.jwks_min_refresh
+                }
+                _ => false,
+            }
+        };
+        if recently_fetched {
+            return Err(rejected("signed with an unknown key"));
+        }
+
+        // Keys of types we cannot use are skipped rather than failing the whole set
+        let set: JwksDocument = self.get(jwks_uri).await?;
+        let keys: Vec<Jwk> = set
+            .keys
+            .into_iter()
+            .filter_map(|key| serde_json::from_value(key).ok())
+            .collect();
+
+        let jwk = select_key(&keys, kid).cloned();
+        self.keys.write().unwrap().insert(jwks_uri.to_string(), Cached::new(keys));
+        jwk.ok_or_else(|| rejected("signed with an unknown key"))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, AuthError> {
+        let failed = |e: reqwest::Error| AuthError::OAuth(format!("fetching {} failed: {}", url, e));
+
+        self.http
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .map_err(failed)?
+            .error_for_status()
+            .map_err(failed)?
+            .json()
+            .await
+            .map_err(failed)
+    }
+}
+
+#[derive(Deserialize)]
+struct JwksDocument {
+    keys: Vec<Value>,
+}
+
+/// The key named by `kid`; tokens without one are accepted only from issuers
+/// publishing a single key
+fn select_key<'a>(keys: &'a [Jwk], kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.iter().find(|key| key.common.key_id.as_deref() == Some(kid)),
+        None if keys.len() == 1 => keys.first(),
+        None => None,
+    }
+}
+
+/// Only public-key signatures; an HMAC-signed id_token would be checked with our
+/// own client secret, which proves nothing about the issuer
+fn is_asymmetric(algorithm: Algorithm) -> bool {
+    !matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn rejected(reason: impl std::fmt::Display) -> AuthError {
+    AuthError::OAuth(format!("id_token rejected: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SigningKey;
+    use crate::utils::test_server::FakeServer;
+    use axum::{extract::State, routing::get, Json, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    type PublishedKeys = Arc<Mutex<Vec<Jwk>>>;
+
+    /// An issuer serving its discovery document and whatever keys are published
+    async fn fake_issuer(keys: PublishedKeys) -> String {
+        let server = FakeServer::bind().await;
+        let issuer = server.base_url.clone();
+        let document = json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "userinfo_endpoint": format!("{}/userinfo", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+        });
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(move || async move { Json(document) }))
+            .route(
+                "/jwks",
+                get(|State(keys): State<PublishedKeys>| async move {
+                    Json(json!({ "keys": *keys.lock().unwrap() }))
+                }),
+            )
+            .with_state(keys);
+        server.serve(router)
+    }
+
+    fn key(kid: &str) -> (Jwk, EncodingKey) {
+        let (key, _) = SigningKey::generate(Algorithm::ES256, Some(kid.to_string())).unwrap();
+        (key.jwk().unwrap().clone(), key.encoding_key().clone())
+    }
+
+    fn sign(kid: &str, key: &EncodingKey, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(kid.to_string());
+        jsonwebtoken::encode(&header, claims, key).unwrap()
+    }
+
+    fn claims(issuer: &str) -> Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "iss": issuer,
+            "aud": "client",
+            "sub": "248289761001",
+            "email": "ada@example.com",
+            "email_verified": true,
+            "nonce": "n-0S6_WzA2Mj",
+            "iat": now,
+            "exp": now + 300,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_validates_id_tokens_against_discovered_keys() {
+        let (jwk, signer) = key("k1");
+        let issuer = fake_issuer(Arc::new(Mutex::new(vec![jwk]))).await;
+        let oidc = OidcClient::new();
+
+        let metadata = oidc.discover(&format!("{}/", issuer)).await.unwrap();
+        assert_eq!(metadata.token_endpoint, format!("{}/token", issuer));
+
+        let claims = claims(&issuer);
+        let token = sign("k1", &signer, &claims);
+        let validated = oidc
+            .validate_id_token(&metadata, "client", &token, Some("n-0S6_WzA2Mj"))
+            .await
+            .unwrap();
+        assert_eq!(validated["sub"], "248289761001");
+
+        let cases = [
+            ("aud", json!("someone-else")),
+            ("iss", json!("https://evil.example.com")),
+            ("nonce", json!("replayed")),
+            ("exp", json!(chrono::Utc::now().timestamp() - 600)),
+            ("azp", json!("someone-else")),
+        ];
+        for (claim, value) in cases {
+            let mut tampered = claims.clone();
+            tampered[claim] = value;
+            let result = oidc
+                .validate_id_token(&metadata, "client", &sign("k1", &signer, &tampered), Some("n-0S6_WzA2Mj"))
+                .await;
+            assert!(matches!(result, Err(AuthError::OAuth(_))), "{} was not checked", claim);
+        }
+
+        // Someone else's key, and a token signed with a shared secret
+        let (_, stranger) = key("k1");
+        assert!(oidc
+            .validate_id_token(&metadata, "client", &sign("k1", &stranger, &claims), None)
+            .await
+            .is_err());
+        let hmac = jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+        assert!(oidc.validate_id_token(&metadata, "client", &hmac, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refetches_keys_after_rotation() {
+        let (old, _) = key("k1");
+        let published = PublishedKeys::new(Mutex::new(vec![old]));
+        let issuer = fake_issuer(published.clone()).await;
+        let mut oidc = OidcClient::new();
+        let metadata = oidc.discover(&issuer).await.unwrap();
+
+        let (new, signer) = key("k2");
+        let token = sign("k2", &signer, &claims(&issuer));
+
+        // Caches the old set; the new key is not published yet
+        assert!(oidc.validate_id_token(&metadata, "client", &token, None).await.is_err());
+
+        published.lock().unwrap().push(new);
+        // Too soon after the last fetch to try again
+        assert!(oidc.validate_id_token(&metadata, "client", &token, None).await.is_err());
+
+        oidc.jwks_min_refresh = Duration::ZERO;
+        assert!(oidc.validate_id_token(&metadata, "client", &token, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_discovery_for_another_issuer() {
+        let issuer = fake_issuer(PublishedKeys::default()).await;
+        let other = issuer.replace("127.0.0.1", "localhost");
+
+        assert!(matches!(OidcClient::new().discover(&other).await, Err(AuthError::OAuth(_))));
+    }
+}
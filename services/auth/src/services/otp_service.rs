@@ -1,8 +1,8 @@
 use chrono::{Duration, Utc};
-use uuid::Uuid;
+use ring::hmac;
 
 use crate::error::AuthError;
-use crate::utils::crypto::generate_otp_code;
+use crate::utils::crypto::{constant_time_eq, generate_otp_code};
 
 pub struct OtpService;
 
@@ -19,12 +19,28 @@ impl OtpService {
         Utc::now() > *expires_at
     }
 
-    pub fn verify_code(code: &str, expected_code: &str, expires_at: &chrono::DateTime<Utc>) -> Result<(), AuthError> {
+    /// Stored form of a code: an HMAC keyed with the server-side pepper, since a plain
+    /// hash of a six digit code is reversed by trying all of them. Salted with the
+    /// address or number it was sent to.
+    pub fn hash_code(pepper: &str, identifier: &str, code: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, pepper.as_bytes());
+        let tag = hmac::sign(&key, format!("{}:{}", identifier, code).as_bytes());
+        tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn verify_code(
+        pepper: &str,
+        identifier: &str,
+        code: &str,
+        expected_hash: &str,
+        expires_at: &chrono::DateTime<Utc>,
+    ) -> Result<(), AuthError> {
         if Self::is_expired(expires_at) {
             return Err(AuthError::OtpInvalid);
         }
 
-        if code == expected_code {
+        let hash = Self::hash_code(pepper, identifier, code);
+        if constant_time_eq(hash.as_bytes(), expected_hash.as_bytes()) {
             Ok(())
         } else {
             Err(AuthError::OtpInvalid)
@@ -55,9 +71,19 @@ mod tests {
     #[test]
     fn test_otp_verification() {
         let code = "123456";
+        let identifier = "a@example.com";
+        let hash = OtpService::hash_code("pepper", identifier, code);
         let expires_at = Utc::now() + Duration::minutes(10);
         
-        assert!(OtpService::verify_code(code, code, &expires_at).is_ok());
-        assert!(OtpService::verify_code("wrong", code, &expires_at).is_err());
+        assert_ne!(hash, code);
+        assert!(OtpService::verify_code("pepper", identifier, code, &hash, &expires_at).is_ok());
+        assert!(OtpService::verify_code("pepper", identifier, "wrong", &hash, &expires_at).is_err());
+        assert!(OtpService::verify_code("pepper", "b@example.com", code, &hash, &expires_at).is_err());
+        // Without the pepper the stored hash cannot be reproduced
+        assert!(OtpService::verify_code("other", identifier, code, &hash, &expires_at).is_err());
+        assert_ne!(hash, crate::utils::crypto::hash_token(&format!("{}:{}", identifier, code)));
+
+        let expired = Utc::now() - Duration::minutes(1);
+        assert!(OtpService::verify_code("pepper", identifier, code, &hash, &expired).is_err());
     }
 }
@@ -0,0 +1,637 @@
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::domain::{MagicLink, OtpChannel, OtpCode, RedirectSettings, SmsSettings};
+use crate::error::AuthError;
+use crate::repository::traits::{MagicLinkRepository, OtpRepository};
+use crate::services::email_service::EmailSender;
+use crate::services::{OtpService, SmsService};
+use crate::utils::crypto::{
+    constant_time_eq, generate_magic_link_poll_token, generate_magic_link_token, hash_token, pkce_challenge,
+};
+use crate::utils::validation::{validate_email, validate_phone};
+
+const MAGIC_LINK_EXPIRY_MINUTES: i64 = 10;
+
+/// How often a long poll rechecks whether its link was approved
+const MAGIC_LINK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Handed to the client that requested a cross-device magic link
+#[derive(Debug)]
+pub struct CrossDeviceLink {
+    /// Collects the session once the link is approved
+    pub poll_token: String,
+    /// Shown on the requesting device and entered wherever the link is opened
+    pub match_code: String,
+}
+
+/// Outcome of polling a cross-device magic link
+#[derive(Debug, PartialEq, Eq)]
+pub enum MagicLinkPoll {
+    /// Not opened yet; poll again
+    Pending,
+    /// Opened and now used up; sign in the address it was sent to
+    Approved(String),
+}
+
+/// Issues and checks one-time sign-in codes and magic links. Signing the user in
+/// once one checks out is left to `AuthService::signin_passwordless`.
+pub struct PasswordlessService<OR: OtpRepository, MR: MagicLinkRepository> {
+    otp_repo: OR,
+    link_repo: MR,
+    email: Arc<dyn EmailSender>,
+    sms: Arc<SmsService>,
+    otp_pepper: String,
+    max_attempts: i32,
+    resend_cooldown: Duration,
+    max_poll_wait: std::time::Duration,
+    poll_interval: std::time::Duration,
+}
+
+impl<OR: OtpRepository, MR: MagicLinkRepository> PasswordlessService<OR, MR> {
+    pub fn new(
+        otp_repo: OR,
+        link_repo: MR,
+        email: Arc<dyn EmailSender>,
+        sms: Arc<SmsService>,
+        config: &Config,
+    ) -> Self {
+        Self {
+            otp_repo,
+            link_repo,
+            email,
+            sms,
+            otp_pepper: config.otp_pepper.clone(),
+            max_attempts: config.otp_max_attempts.max(1),
+            resend_cooldown: Duration::seconds(config.otp_resend_cooldown_seconds as i64),
+            max_poll_wait: std::time::Duration::from_secs(config.magic_link_max_poll_wait_seconds),
+            poll_interval: MAGIC_LINK_POLL_INTERVAL,
+        }
+    }
+
+    /// Store a fresh code for the address or number and deliver it over `channel`,
+    /// through the project's SMS providers for text messages. The new code supersedes
+    /// any earlier one.
+    pub async fn send_otp(
+        &self,
+        project_id: Uuid,
+        sms: &SmsSettings,
+        channel: OtpChannel,
+        identifier: &str,
+    ) -> Result<(), AuthError> {
+        let identifier = normalize_identifier(channel, identifier)?;
+
+        if let Some(previous) = self.otp_repo.find_latest(project_id, &identifier).await? {
+            if Utc::now() < previous.created_at + self.resend_cooldown {
+                return Err(AuthError::RateLimitExceeded);
+            }
+        }
+
+        let code = OtpService::generate_code();
+        let otp = OtpCode::new(
+            project_id,
+            channel,
+            identifier.clone(),
+            OtpService::hash_code(&self.otp_pepper, &identifier, &code),
+            OtpService::generate_expiry(),
+        );
+        self.otp_repo.create(&otp).await?;
+
+        match channel {
+            OtpChannel::Email => self.email.send_otp(&identifier, &code).await,
+            OtpChannel::Sms => self.sms.send_otp(sms, &identifier, &code).await,
+        }
+    }
+
+    /// Use up a code sent to the address or number, returning the identifier in the
+    /// normalized form it was stored under
+    pub async fn verify_otp(
+        &self,
+        project_id: Uuid,
+        channel: OtpChannel,
+        identifier: &str,
+        code: &str,
+    ) -> Result<String, AuthError> {
+        let identifier = normalize_identifier(channel, identifier)?;
+        let otp = self.otp_repo
+            .find_latest(project_id, &identifier)
+            .await?
+            .filter(|otp| otp.channel == channel && otp.is_usable())
+            .ok_or(AuthError::OtpInvalid)?;
+
+        // The attempt is counted before the guess is checked, so parallel guesses share the limit
+        let attempts = self.otp_repo
+            .record_attempt(otp.id, self.max_attempts)
+            .await?
+            .ok_or(AuthError::OtpInvalid)?;
+
+        if OtpService::verify_code(&self.otp_pepper, &identifier, code, &otp.code_hash, &otp.expires_at).is_err() {
+            if attempts >= self.max_attempts {
+                self.otp_repo.mark_used(otp.id).await?;
+            }
+            return Err(AuthError::OtpInvalid);
+        }
+
+        // Two requests racing with the same code must not both sign in
+        if !self.otp_repo.mark_used(otp.id).await? {
+            return Err(AuthError::OtpInvalid);
+        }
+
+        Ok(identifier)
+    }
+
+    /// Email a sign-in link pointing at `redirect_to`, if the project allows it, or at
+    /// the project's site URL. A `code_challenge` (S256) binds the link to the browser
+    /// that asked for it: only a request presenting the verifier can use it.
+    ///
+    /// A `cross_device` link can be opened anywhere, which only approves the request,
+    /// and only with the returned match code; the returned poll token is how the
+    /// requesting client collects its session.
+    pub async fn send_magic_link(
+        &self,
+        project_id: Uuid,
+        redirects: &RedirectSettings,
+        email: &str,
+        redirect_to: Option<&str>,
+        code_challenge: Option<&str>,
+        cross_device: bool,
+    ) -> Result<Option<CrossDeviceLink>, AuthError> {
+        let email = normalize_identifier(OtpChannel::Email, email)?;
+        if let Some(challenge) = code_challenge {
+            validate_code_challenge(challenge)?;
+        }
+        let mut url = redirects.resolve(redirect_to)?;
+
+        let token = generate_magic_link_token();
+        let mut link = MagicLink::new(
+            project_id,
+            email.clone(),
+            hash_token(&token),
+            code_challenge.map(str::to_string),
+            redirect_to.map(|_| url.to_string()),
+            Utc::now() + Duration::minutes(MAGIC_LINK_EXPIRY_MINUTES),
+        );
+        let cross_device = cross_device.then(|| CrossDeviceLink {
+            poll_token: generate_magic_link_poll_token(),
+            match_code: OtpService::generate_code(),
+        });
+        if let Some(request) = &cross_device {
+            link = link.with_poll_token_hash(
+                hash_token(&request.poll_token),
+                OtpService::hash_code(&self.otp_pepper, &email, &request.match_code),
+            );
+        }
+        self.link_repo.create(&link).await?;
+
+        let link_type = if cross_device.is_some() { "magiclink_approval" } else { "magiclink" };
+        url.query_pairs_mut()
+            .append_pair("token", &token)
+            .append_pair("type", link_type);
+        self.email.send_magic_link(&email, url.as_str()).await?;
+
+        Ok(cross_device)
+    }
+
+    /// Use up a magic link, returning the address it was sent to
+    pub async fn verify_magic_link(
+        &self,
+        project_id: Uuid,
+        token: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<String, AuthError> {
+        let link = self.find_link(project_id, token).await?;
+
+        // Cross-device links sign in the poller, never the device that opened them
+        if link.is_cross_device() {
+            return Err(AuthError::InvalidToken);
+        }
+        check_code_verifier(&link, code_verifier)?;
+
+        if !self.link_repo.mark_used(link.id).await? {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(link.email)
+    }
+
+    /// Approve the request behind a cross-device magic link from whichever device opened
+    /// it, given the code the requesting device shows. A wrong code leaves the link
+    /// alone, so the user can correct a typo.
+    pub async fn approve_magic_link(&self, project_id: Uuid, token: &str, match_code: &str) -> Result<(), AuthError> {
+        let link = self.find_link(project_id, token).await?;
+        let expected = link.match_code_hash.as_deref().ok_or(AuthError::InvalidToken)?;
+
+        // As with OTP codes, the attempt is counted first so parallel guesses share the limit
+        let attempts = self.link_repo
+            .record_approval_attempt(link.id, self.max_attempts)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+
+        let matches = constant_time_eq(
+            OtpService::hash_code(&self.otp_pepper, &link.email, match_code).as_bytes(),
+            expected.as_bytes(),
+        );
+        if !matches {
+            // Whoever holds the link could otherwise try every code before it expires
+            if attempts >= self.max_attempts {
+                self.link_repo.mark_used(link.id).await?;
+            }
+            return Err(AuthError::MagicLinkCodeMismatch);
+        }
+
+        if !self.link_repo.approve(link.id).await? {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(())
+    }
+
+    /// Check on a cross-device link, waiting up to `wait` (capped by
+    /// `MAGIC_LINK_MAX_POLL_WAIT_SECONDS`) for it to be approved. Approval is handed
+    /// out once; later polls fail.
+    pub async fn poll_magic_link(
+        &self,
+        project_id: Uuid,
+        poll_token: &str,
+        code_verifier: Option<&str>,
+        wait: std::time::Duration,
+    ) -> Result<MagicLinkPoll, AuthError> {
+        let deadline = tokio::time::Instant::now() + wait.min(self.max_poll_wait);
+        let poll_token_hash = hash_token(poll_token);
+
+        loop {
+            let link = self.link_repo
+                .find_by_poll_token_hash(&poll_token_hash)
+                .await?
+                .filter(|link| link.project_id == project_id && !link.used)
+                .ok_or(AuthError::InvalidToken)?;
+
+            if !link.is_usable() {
+                return Err(AuthError::TokenExpired);
+            }
+
+            if link.approved_at.is_some() {
+                check_code_verifier(&link, code_verifier)?;
+                if !self.link_repo.mark_used(link.id).await? {
+                    return Err(AuthError::InvalidToken);
+                }
+                return Ok(MagicLinkPoll::Approved(link.email));
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Ok(MagicLinkPoll::Pending);
+            }
+            tokio::time::sleep(self.poll_interval.min(deadline - now)).await;
+        }
+    }
+
+    /// An unused, unexpired link of this project
+    async fn find_link(&self, project_id: Uuid, token: &str) -> Result<MagicLink, AuthError> {
+        self.link_repo
+            .find_by_token_hash(&hash_token(token))
+            .await?
+            .filter(|link| link.project_id == project_id && link.is_usable())
+            .ok_or(AuthError::InvalidToken)
+    }
+}
+
+/// A wrong verifier leaves the link alone; it cannot be guessed, and burning the
+/// link would let whoever intercepted it lock the user out
+fn check_code_verifier(link: &MagicLink, code_verifier: Option<&str>) -> Result<(), AuthError> {
+    let Some(challenge) = &link.code_challenge else {
+        return Ok(());
+    };
+    let verifier = code_verifier.ok_or(AuthError::InvalidToken)?;
+    if constant_time_eq(pkce_challenge(verifier).as_bytes(), challenge.as_bytes()) {
+        Ok(())
+    } else {
+        Err(AuthError::InvalidToken)
+    }
+}
+
+/// An S256 challenge is the unpadded base64url encoding of a SHA-256 digest
+fn validate_code_challenge(challenge: &str) -> Result<(), AuthError> {
+    let well_formed = challenge.len() == 43
+        && challenge.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if well_formed {
+        Ok(())
+    } else {
+        Err(AuthError::InvalidInput("code_challenge must be an S256 challenge".to_string()))
+    }
+}
+
+/// Lowercased email address, or phone number without spaces and dashes
+pub fn normalize_identifier(channel: OtpChannel, identifier: &str) -> Result<String, AuthError> {
+    let identifier = identifier.trim();
+    match channel {
+        OtpChannel::Email => {
+            validate_email(identifier, "email").map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+            Ok(identifier.to_lowercase())
+        }
+        OtpChannel::Sms => {
+            validate_phone(identifier, "phone").map_err(|e| AuthError::InvalidInput(e.to_string()))?;
+            Ok(identifier.chars().filter(|c| !matches!(c, ' ' | '-')).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::{InMemoryMagicLinkRepository, InMemoryOtpRepository};
+    use crate::services::sms_service::SmsSender;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Keeps the last message per recipient so tests can read the code back
+    #[derive(Default)]
+    struct Outbox {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl Outbox {
+        fn last_code(&self, to: &str) -> String {
+            let sent = self.sent.lock().unwrap();
+            let (_, message) = sent.iter().rev().find(|(recipient, _)| recipient == to).unwrap();
+            message.chars().filter(|c| c.is_ascii_digit()).take(6).collect()
+        }
+
+        fn last_link(&self, to: &str) -> url::Url {
+            let sent = self.sent.lock().unwrap();
+            let (_, message) = sent.iter().rev().find(|(recipient, _)| recipient == to).unwrap();
+            let link = message.split_whitespace().find(|word| word.starts_with("http")).unwrap();
+            url::Url::parse(link).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl EmailSender for Outbox {
+        async fn send(&self, to: &str, _subject: &str, body: &str) -> Result<(), AuthError> {
+            self.sent.lock().unwrap().push((to.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl SmsSender for Outbox {
+        async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+            self.sent.lock().unwrap().push((to.to_string(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    type TestService = PasswordlessService<InMemoryOtpRepository, InMemoryMagicLinkRepository>;
+
+    fn service() -> (TestService, Arc<Outbox>) {
+        let mut config = Config::for_tests();
+        config.otp_max_attempts = 3;
+        config.otp_resend_cooldown_seconds = 0;
+
+        let outbox = Arc::new(Outbox::default());
+        let service = PasswordlessService::new(
+            InMemoryOtpRepository::default(),
+            InMemoryMagicLinkRepository::default(),
+            outbox.clone(),
+            Arc::new(SmsService::new().with_provider("outbox", outbox.clone())),
+            &config,
+        );
+        (service, outbox)
+    }
+
+    #[tokio::test]
+    async fn test_code_is_single_use() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "Ann@Example.com").await.unwrap();
+        let code = outbox.last_code("ann@example.com");
+
+        let verified = service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", &code).await.unwrap();
+        assert_eq!(verified, "ann@example.com");
+
+        let reused = service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", &code).await;
+        assert!(matches!(reused, Err(AuthError::OtpInvalid)));
+    }
+
+    #[tokio::test]
+    async fn test_code_is_bound_to_project_and_channel() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Sms, "+1 555-010-0199").await.unwrap();
+        let code = outbox.last_code("+15550100199");
+
+        let other_project = service.verify_otp(Uuid::new_v4(), OtpChannel::Sms, "+15550100199", &code).await;
+        assert!(matches!(other_project, Err(AuthError::OtpInvalid)));
+
+        let verified = service.verify_otp(project_id, OtpChannel::Sms, "+1 555 010 0199", &code).await.unwrap();
+        assert_eq!(verified, "+15550100199");
+    }
+
+    #[tokio::test]
+    async fn test_code_is_burned_after_max_attempts() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "ann@example.com").await.unwrap();
+        let code = outbox.last_code("ann@example.com");
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+
+        for _ in 0..3 {
+            let guess = service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", wrong).await;
+            assert!(matches!(guess, Err(AuthError::OtpInvalid)));
+        }
+
+        let stored = service.otp_repo.find_latest(project_id, "ann@example.com").await.unwrap().unwrap();
+        assert!(stored.used);
+        assert_ne!(stored.code_hash, code);
+
+        let late = service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", &code).await;
+        assert!(matches!(late, Err(AuthError::OtpInvalid)));
+    }
+
+    #[tokio::test]
+    async fn test_resend_cooldown_and_superseded_codes() {
+        let (mut service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "ann@example.com").await.unwrap();
+        let first = outbox.last_code("ann@example.com");
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "ann@example.com").await.unwrap();
+        let second = outbox.last_code("ann@example.com");
+
+        if first != second {
+            let stale = service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", &first).await;
+            assert!(matches!(stale, Err(AuthError::OtpInvalid)));
+        }
+        service.verify_otp(project_id, OtpChannel::Email, "ann@example.com", &second).await.unwrap();
+
+        service.resend_cooldown = Duration::seconds(60);
+        service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "bob@example.com").await.unwrap();
+        let again = service.send_otp(project_id, &SmsSettings::default(), OtpChannel::Email, "bob@example.com").await;
+        assert!(matches!(again, Err(AuthError::RateLimitExceeded)));
+    }
+
+    fn redirects() -> RedirectSettings {
+        RedirectSettings {
+            site_url: Some("https://app.example.com".to_string()),
+            allowed_urls: vec!["https://admin.example.com/*".to_string()],
+        }
+    }
+
+    fn token(link: &url::Url) -> String {
+        link.query_pairs().find(|(key, _)| key == "token").unwrap().1.into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_goes_to_site_or_allowed_redirect() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        service.send_magic_link(project_id, &redirects(), "Ann@Example.com", None, None, false).await.unwrap();
+        let link = outbox.last_link("ann@example.com");
+        assert_eq!(link.origin().ascii_serialization(), "https://app.example.com");
+
+        service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", Some("https://admin.example.com/done?x=1"), None, false)
+            .await
+            .unwrap();
+        let link = outbox.last_link("ann@example.com");
+        assert_eq!(link.path(), "/done");
+        assert!(link.query_pairs().any(|(key, value)| key == "x" && value == "1"));
+
+        let email = service.verify_magic_link(project_id, &token(&link), None).await.unwrap();
+        assert_eq!(email, "ann@example.com");
+        let reused = service.verify_magic_link(project_id, &token(&link), None).await;
+        assert!(matches!(reused, Err(AuthError::InvalidToken)));
+
+        let rejected = service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", Some("https://evil.test/"), None, false)
+            .await;
+        assert!(matches!(rejected, Err(AuthError::RedirectNotAllowed)));
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_with_challenge_needs_verifier() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        // RFC 7636 appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = pkce_challenge(verifier);
+        assert_eq!(challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", None, Some(&challenge), false)
+            .await
+            .unwrap();
+        let token = token(&outbox.last_link("ann@example.com"));
+
+        for wrong in [None, Some("not-the-verifier")] {
+            let result = service.verify_magic_link(project_id, &token, wrong).await;
+            assert!(matches!(result, Err(AuthError::InvalidToken)));
+        }
+        let other_project = service.verify_magic_link(Uuid::new_v4(), &token, Some(verifier)).await;
+        assert!(matches!(other_project, Err(AuthError::InvalidToken)));
+
+        let email = service.verify_magic_link(project_id, &token, Some(verifier)).await.unwrap();
+        assert_eq!(email, "ann@example.com");
+
+        let malformed = service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", None, Some("plain"), false)
+            .await;
+        assert!(matches!(malformed, Err(AuthError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cross_device_link_is_approved_elsewhere_and_collected_by_poller() {
+        let (mut service, outbox) = service();
+        service.poll_interval = std::time::Duration::from_millis(10);
+        let service = Arc::new(service);
+        let project_id = Uuid::new_v4();
+        let no_wait = std::time::Duration::ZERO;
+
+        let CrossDeviceLink { poll_token, match_code } = service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", None, None, true)
+            .await
+            .unwrap()
+            .unwrap();
+        let link = outbox.last_link("ann@example.com");
+        // The code is shown on the requesting device only, never emailed
+        assert!(!link.as_str().contains(&match_code));
+        assert!(link.query_pairs().any(|(key, value)| key == "type" && value == "magiclink_approval"));
+        let token = token(&link);
+
+        let pending = service.poll_magic_link(project_id, &poll_token, None, no_wait).await.unwrap();
+        assert_eq!(pending, MagicLinkPoll::Pending);
+
+        // Opening the link does not sign that device in
+        let direct = service.verify_magic_link(project_id, &token, None).await;
+        assert!(matches!(direct, Err(AuthError::InvalidToken)));
+
+        // Approving someone else's request fails without the code their device shows
+        let wrong = if match_code == "000000" { "111111" } else { "000000" };
+        let mismatch = service.approve_magic_link(project_id, &token, wrong).await;
+        assert!(matches!(mismatch, Err(AuthError::MagicLinkCodeMismatch)));
+        let still_pending = service.poll_magic_link(project_id, &poll_token, None, no_wait).await.unwrap();
+        assert_eq!(still_pending, MagicLinkPoll::Pending);
+
+        let approver = service.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+            approver.approve_magic_link(project_id, &token, &match_code).await.unwrap();
+        });
+
+        let approved = service
+            .poll_magic_link(project_id, &poll_token, None, std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(approved, MagicLinkPoll::Approved("ann@example.com".to_string()));
+
+        let again = service.poll_magic_link(project_id, &poll_token, None, no_wait).await;
+        assert!(matches!(again, Err(AuthError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_cross_device_link_is_burned_after_max_wrong_codes() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        let CrossDeviceLink { poll_token, match_code } = service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", None, None, true)
+            .await
+            .unwrap()
+            .unwrap();
+        let token = token(&outbox.last_link("ann@example.com"));
+        let wrong = if match_code == "000000" { "111111" } else { "000000" };
+
+        for _ in 0..3 {
+            let guess = service.approve_magic_link(project_id, &token, wrong).await;
+            assert!(matches!(guess, Err(AuthError::MagicLinkCodeMismatch)));
+        }
+
+        let late = service.approve_magic_link(project_id, &token, &match_code).await;
+        assert!(matches!(late, Err(AuthError::InvalidToken)));
+        let poll = service.poll_magic_link(project_id, &poll_token, None, std::time::Duration::ZERO).await;
+        assert!(matches!(poll, Err(AuthError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_same_browser_link_cannot_be_approved() {
+        let (service, outbox) = service();
+        let project_id = Uuid::new_v4();
+
+        let cross_device = service
+            .send_magic_link(project_id, &redirects(), "ann@example.com", None, None, false)
+            .await
+            .unwrap();
+        assert!(cross_device.is_none());
+
+        let token = token(&outbox.last_link("ann@example.com"));
+        let approved = service.approve_magic_link(project_id, &token, "123456").await;
+        assert!(matches!(approved, Err(AuthError::InvalidToken)));
+    }
+}
@@ -0,0 +1,371 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rand::RngCore;
+use ring::signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::domain::SigningKeyRecord;
+use crate::error::AuthError;
+use crate::utils::crypto::generate_random_token;
+
+/// Key id advertised for shared-secret keys when none is configured
+const DEFAULT_SECRET_KEY_ID: &str = "default";
+
+/// A key used to sign and verify access tokens
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    /// Load the key selected by `JWT_ALGORITHM`, reading asymmetric keys from `JWT_PRIVATE_KEY_PATH`
+    pub fn from_config(config: &Config) -> Result<Self, AuthError> {
+        let algorithm = parse_algorithm(&config.jwt_algorithm)?;
+
+        if is_hmac(algorithm) {
+            return Ok(Self::from_secret(
+                algorithm,
+                config.jwt_secret.as_bytes(),
+                config.jwt_key_id.clone(),
+            ));
+        }
+
+        let path = config.jwt_private_key_path.as_ref().ok_or_else(|| {
+            AuthError::KeyConfiguration(format!("JWT_PRIVATE_KEY_PATH is required for {:?}", algorithm))
+        })?;
+        let pem = std::fs::read(path)
+            .map_err(|e| AuthError::KeyConfiguration(format!("failed to read {}: {}", path, e)))?;
+
+        Self::from_pem(algorithm, &pem, config.jwt_key_id.clone())
+    }
+
+    /// Rebuild a key created through rotation
+    pub fn from_record(record: &SigningKeyRecord) -> Result<Self, AuthError> {
+        let algorithm = parse_algorithm(&record.algorithm)?;
+        if is_hmac(algorithm) {
+            let secret = STANDARD
+                .decode(&record.private_key)
+                .map_err(|e| AuthError::KeyConfiguration(format!("invalid secret for {}: {}", record.kid, e)))?;
+            return Ok(Self::from_secret(algorithm, &secret, Some(record.kid.clone())));
+        }
+
+        Self::from_pem(algorithm, record.private_key.as_bytes(), Some(record.kid.clone()))
+    }
+
+    /// Generate a fresh key, returning it with the private material to persist.
+    /// RSA keys cannot be generated here and have to be supplied as PEM.
+    pub fn generate(algorithm: Algorithm, kid: Option<String>) -> Result<(Self, String), AuthError> {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let mut secret = [0u8; 64];
+                rand::thread_rng().fill_bytes(&mut secret);
+                let kid = kid.unwrap_or_else(|| format!("hs_{}", generate_random_token(16)));
+                return Ok((Self::from_secret(algorithm, &secret, Some(kid)), STANDARD.encode(secret)));
+            }
+            Algorithm::ES256 => EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng),
+            Algorithm::ES384 => EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P384_SHA384_FIXED_SIGNING, &rng),
+            Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&rng),
+            _ => {
+                return Err(AuthError::KeyConfiguration(format!(
+                    "{:?} keys cannot be generated, supply a private key",
+                    algorithm
+                )))
+            }
+        }
+        .map_err(|_| AuthError::KeyConfiguration("key generation failed".to_string()))?;
+
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        Ok((Self::from_pem(algorithm, pem.as_bytes(), kid)?, pem))
+    }
+
+    /// HMAC key from a shared secret; it has no public form and is never published
+    pub fn from_secret(algorithm: Algorithm, secret: &[u8], kid: Option<String>) -> Self {
+        Self {
+            kid: kid.unwrap_or_else(|| DEFAULT_SECRET_KEY_ID.to_string()),
+            algorithm,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// Asymmetric key from a PEM encoded private key.
+    /// RSA keys may be PKCS#1 or PKCS#8, EC and Ed25519 keys must be PKCS#8.
+    /// Without an explicit `kid` the RFC 7638 thumbprint of the public key is used.
+    pub fn from_pem(algorithm: Algorithm, pem: &[u8], kid: Option<String>) -> Result<Self, AuthError> {
+        let parsed = pem::parse(pem)
+            .map_err(|e| AuthError::KeyConfiguration(format!("invalid PEM: {}", e)))?;
+        let der = parsed.contents();
+
+        let (encoding_key, params) = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => {
+                let key_pair = match parsed.tag() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(der),
+                    _ => RsaKeyPair::from_pkcs8(der),
+                }
+                .map_err(key_rejected)?;
+                let public = ring::rsa::PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+
+                (
+                    EncodingKey::from_rsa_pem(pem)?,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n: URL_SAFE_NO_PAD.encode(&public.n),
+                        e: URL_SAFE_NO_PAD.encode(&public.e),
+                    }),
+                )
+            }
+            Algorithm::ES256 | Algorithm::ES384 => {
+                let (signing_alg, curve) = if algorithm == Algorithm::ES256 {
+                    (&signature::ECDSA_P256_SHA256_FIXED_SIGNING, EllipticCurve::P256)
+                } else {
+                    (&signature::ECDSA_P384_SHA384_FIXED_SIGNING, EllipticCurve::P384)
+                };
+                let key_pair = EcdsaKeyPair::from_pkcs8(signing_alg, der, &ring::rand::SystemRandom::new())
+                    .map_err(key_rejected)?;
+
+                // Uncompressed point: 0x04 || x || y
+                let point = &key_pair.public_key().as_ref()[1..];
+                let (x, y) = point.split_at(point.len() / 2);
+
+                (
+                    EncodingKey::from_ec_pem(pem)?,
+                    AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                        key_type: EllipticCurveKeyType::EC,
+                        curve,
+                        x: URL_SAFE_NO_PAD.encode(x),
+                        y: URL_SAFE_NO_PAD.encode(y),
+                    }),
+                )
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).map_err(key_rejected)?;
+
+                (
+                    EncodingKey::from_ed_pem(pem)?,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                    }),
+                )
+            }
+            _ => {
+                return Err(AuthError::KeyConfiguration(format!(
+                    "{:?} is not an asymmetric algorithm",
+                    algorithm
+                )))
+            }
+        };
+
+        let kid = kid.unwrap_or_else(|| thumbprint(&params));
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: KeyAlgorithm::from_str(&format!("{:?}", algorithm)).ok(),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: params,
+        };
+
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding_key,
+            decoding_key: DecodingKey::from_jwk(&jwk)?,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    /// Public key to publish in the JWKS, `None` for shared-secret keys
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+pub fn parse_algorithm(name: &str) -> Result<Algorithm, AuthError> {
+    Algorithm::from_str(name)
+        .map_err(|_| AuthError::KeyConfiguration(format!("unsupported JWT algorithm {}", name)))
+}
+
+fn is_hmac(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn key_rejected(e: ring::error::KeyRejected) -> AuthError {
+    AuthError::KeyConfiguration(format!("invalid private key: {}", e))
+}
+
+/// RFC 7638 JWK thumbprint: SHA-256 over the required members in lexicographic order
+fn thumbprint(params: &AlgorithmParameters) -> String {
+    let canonical = match params {
+        AlgorithmParameters::RSA(p) => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, p.e, p.n),
+        AlgorithmParameters::EllipticCurve(p) => format!(
+            r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+            curve_name(&p.curve),
+            p.x,
+            p.y
+        ),
+        AlgorithmParameters::OctetKeyPair(p) => format!(
+            r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#,
+            curve_name(&p.curve),
+            p.x
+        ),
+        AlgorithmParameters::OctetKey(p) => format!(r#"{{"k":"{}","kty":"oct"}}"#, p.value),
+    };
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+fn curve_name(curve: &EllipticCurve) -> &'static str {
+    match curve {
+        EllipticCurve::P256 => "P-256",
+        EllipticCurve::P384 => "P-384",
+        EllipticCurve::P521 => "P-521",
+        EllipticCurve::Ed25519 => "Ed25519",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use serde::{Deserialize, Serialize};
+
+    const RSA_PEM: &[u8] = include_bytes!("testdata/rsa_private.pem");
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn pkcs8_pem(der: &[u8]) -> Vec<u8> {
+        pem::encode(&pem::Pem::new("PRIVATE KEY", der)).into_bytes()
+    }
+
+    /// Sign with the private key, verify with only the published JWK
+    fn assert_round_trip(key: &SigningKey) {
+        let mut header = Header::new(key.algorithm());
+        header.kid = Some(key.kid().to_string());
+        let claims = TestClaims { sub: "user".to_string(), exp: chrono::Utc::now().timestamp() + 60 };
+        let token = encode(&header, &claims, key.encoding_key()).unwrap();
+
+        let jwk = key.jwk().expect("asymmetric keys are published");
+        assert_eq!(jwk.common.key_id.as_deref(), Some(key.kid()));
+
+        let decoded = decode::<TestClaims>(
+            &token,
+            &DecodingKey::from_jwk(jwk).unwrap(),
+            &Validation::new(key.algorithm()),
+        )
+        .unwrap();
+        assert_eq!(decoded.claims.sub, "user");
+    }
+
+    #[test]
+    fn test_rs256_key_round_trip() {
+        let key = SigningKey::from_pem(Algorithm::RS256, RSA_PEM, None).unwrap();
+        assert!(matches!(key.jwk().unwrap().algorithm, AlgorithmParameters::RSA(_)));
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_es256_key_round_trip() {
+        let rng = ring::rand::SystemRandom::new();
+        let der = EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = SigningKey::from_pem(Algorithm::ES256, &pkcs8_pem(der.as_ref()), Some("ec-1".to_string())).unwrap();
+
+        assert_eq!(key.kid(), "ec-1");
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_eddsa_key_round_trip() {
+        let rng = ring::rand::SystemRandom::new();
+        let der = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key = SigningKey::from_pem(Algorithm::EdDSA, &pkcs8_pem(der.as_ref()), None).unwrap();
+
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_default_kid_is_stable_thumbprint() {
+        let first = SigningKey::from_pem(Algorithm::RS256, RSA_PEM, None).unwrap();
+        let second = SigningKey::from_pem(Algorithm::RS256, RSA_PEM, None).unwrap();
+
+        assert_eq!(first.kid(), second.kid());
+        assert_eq!(first.kid().len(), 43); // unpadded base64url of a SHA-256 digest
+    }
+
+    #[test]
+    fn test_secret_key_is_not_published() {
+        let key = SigningKey::from_secret(Algorithm::HS256, b"secret", None);
+
+        assert_eq!(key.kid(), DEFAULT_SECRET_KEY_ID);
+        assert!(key.jwk().is_none());
+    }
+
+    #[test]
+    fn test_generated_keys_reload_from_record() {
+        for algorithm in [Algorithm::HS256, Algorithm::ES256, Algorithm::EdDSA] {
+            let (key, private_key) = SigningKey::generate(algorithm, None).unwrap();
+            let record = SigningKeyRecord {
+                kid: key.kid().to_string(),
+                algorithm: format!("{:?}", algorithm),
+                private_key,
+                created_at: chrono::Utc::now(),
+                activates_at: chrono::Utc::now(),
+                retired_at: None,
+            };
+
+            let reloaded = SigningKey::from_record(&record).unwrap();
+            assert_eq!(reloaded.kid(), key.kid());
+            assert_eq!(reloaded.algorithm(), algorithm);
+        }
+
+        assert!(SigningKey::generate(Algorithm::RS256, None).is_err());
+    }
+
+    #[test]
+    fn test_mismatched_key_type_is_rejected() {
+        assert!(SigningKey::from_pem(Algorithm::ES256, RSA_PEM, None).is_err());
+    }
+}
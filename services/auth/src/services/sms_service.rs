@@ -1,39 +1,179 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 
-use crate::config::Config;
+use crate::domain::SmsSettings;
 use crate::error::AuthError;
 
 #[async_trait]
 pub trait SmsSender: Send + Sync {
     async fn send(&self, to: &str, message: &str) -> Result<(), AuthError>;
+
+    async fn send_otp(&self, to: &str, code: &str) -> Result<(), AuthError> {
+        self.send(to, &otp_message(code)).await
+    }
+}
+
+fn otp_message(code: &str) -> String {
+    format!("Your verification code is: {}. This code expires in 10 minutes.", code)
+}
+
+/// Provider name of `LogSmsSender`
+pub const LOG: &str = "log";
+
+/// Logs messages instead of sending them. Messages carry sign-in codes, so this is only
+/// used in development (`SMS_DEV_LOG`) when no SMS provider is configured.
+#[derive(Default)]
+pub struct LogSmsSender;
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+        tracing::info!("SMS would be sent to {}: {}", to, message);
+        Ok(())
+    }
 }
 
+/// The configured SMS providers by name. Each message goes through the providers the
+/// project picked for its destination, falling over to the next one when a provider
+/// fails.
+#[derive(Default)]
 pub struct SmsService {
-    config: Config,
+    providers: Vec<(String, Arc<dyn SmsSender>)>,
 }
 
 impl SmsService {
-    pub fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    pub async fn send_otp(&self, to: &str, code: &str) -> Result<(), AuthError> {
-        let message = format!("Your verification code is: {}. This code expires in 10 minutes.", code);
-        self.send(to, &message).await
+    /// Register a provider. Projects that do not pick providers use them all, in the
+    /// order they were registered.
+    pub fn with_provider(mut self, name: &str, sender: Arc<dyn SmsSender>) -> Self {
+        self.providers.push((name.to_string(), sender));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    pub async fn send(&self, settings: &SmsSettings, to: &str, message: &str) -> Result<(), AuthError> {
+        let mut last_error = AuthError::Sms("no SMS provider available".to_string());
+
+        for (name, sender) in self.senders_for(settings, to) {
+            match sender.send(to, message).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!("SMS provider {} failed, trying the next one: {}", name, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    pub async fn send_otp(&self, settings: &SmsSettings, to: &str, code: &str) -> Result<(), AuthError> {
+        self.send(settings, to, &otp_message(code)).await
+    }
+
+    fn senders_for<'a>(&'a self, settings: &'a SmsSettings, to: &str) -> Vec<(&'a str, &'a Arc<dyn SmsSender>)> {
+        let chosen = settings.providers_for(to);
+        if chosen.is_empty() {
+            return self.providers.iter().map(|(name, sender)| (name.as_str(), sender)).collect();
+        }
+
+        chosen
+            .iter()
+            .filter_map(|name| {
+                let found = self.providers.iter().find(|(registered, _)| registered == name);
+                if found.is_none() {
+                    tracing::warn!("Skipping SMS provider {}, which is not configured", name);
+                }
+                found.map(|(name, sender)| (name.as_str(), sender))
+            })
+            .collect()
     }
 }
 
-#[async_trait]
-impl SmsSender for SmsService {
-    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
-        // TODO: Implement Twilio integration
-        // For now, just log in development
-        if self.config.twilio_account_sid.is_some() {
-            tracing::info!("SMS would be sent to {}: {}", to, message);
-            // TODO: Use Twilio SDK here
-        } else {
-            tracing::info!("SMS would be sent to {}: {}", to, message);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::SmsRoute;
+    use std::sync::Mutex;
+
+    /// Records who it was asked to send to, failing if told to
+    struct Provider {
+        fail: bool,
+        sent: Mutex<Vec<String>>,
+    }
+
+    impl Provider {
+        fn new(fail: bool) -> Arc<Self> {
+            Arc::new(Self { fail, sent: Mutex::new(Vec::new()) })
         }
-        Ok(())
+
+        fn sent(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl SmsSender for Provider {
+        async fn send(&self, to: &str, _message: &str) -> Result<(), AuthError> {
+            self.sent.lock().unwrap().push(to.to_string());
+            if self.fail {
+                Err(AuthError::Sms("carrier rejected the message".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn settings(providers: &[&str], routes: Vec<SmsRoute>) -> SmsSettings {
+        SmsSettings { providers: providers.iter().map(|p| p.to_string()).collect(), routes }
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_next_provider() {
+        let (twilio, vonage) = (Provider::new(true), Provider::new(false));
+        let service = SmsService::new()
+            .with_provider("twilio", twilio.clone())
+            .with_provider("vonage", vonage.clone());
+
+        service.send(&SmsSettings::default(), "+15550100199", "hi").await.unwrap();
+        assert_eq!(twilio.sent(), ["+15550100199"]);
+        assert_eq!(vonage.sent(), ["+15550100199"]);
+
+        let only_twilio = settings(&["twilio"], Vec::new());
+        let failed = service.send(&only_twilio, "+15550100199", "hi").await;
+        assert!(matches!(failed, Err(AuthError::Sms(message)) if message == "carrier rejected the message"));
+    }
+
+    #[tokio::test]
+    async fn test_project_routes_pick_providers() {
+        let (twilio, vonage, messagebird) = (Provider::new(false), Provider::new(false), Provider::new(false));
+        let service = SmsService::new()
+            .with_provider("twilio", twilio.clone())
+            .with_provider("vonage", vonage.clone())
+            .with_provider("messagebird", messagebird.clone());
+
+        let routed = settings(
+            &["unknown", "messagebird"],
+            vec![SmsRoute { prefix: "+90".to_string(), providers: vec!["vonage".to_string()] }],
+        );
+        service.send(&routed, "+905551234567", "hi").await.unwrap();
+        service.send(&routed, "+4915112345678", "hi").await.unwrap();
+
+        assert!(twilio.sent().is_empty());
+        assert_eq!(vonage.sent(), ["+905551234567"]);
+        assert_eq!(messagebird.sent(), ["+4915112345678"]);
+
+        let unconfigured = service.send(&settings(&["sinch"], Vec::new()), "+15550100199", "hi").await;
+        assert!(matches!(unconfigured, Err(AuthError::Sms(_))));
+
+        // Without any provider nothing is sent, rather than silently dropped
+        let none = SmsService::new().send(&SmsSettings::default(), "+15550100199", "hi").await;
+        assert!(matches!(none, Err(AuthError::Sms(_))));
     }
 }
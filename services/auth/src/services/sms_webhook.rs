@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::AuthError;
+use crate::services::claims_hook::{sign, SIGNATURE_HEADER};
+use crate::services::sms_service::SmsSender;
+
+/// Provider name used in project SMS settings
+pub const WEBHOOK: &str = "webhook";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct WebhookMessage<'a> {
+    to: &'a str,
+    message: &'a str,
+}
+
+/// Hands messages to an HTTP endpoint of our own, signed like claims hook callouts.
+/// Any 2xx response counts as accepted.
+pub struct WebhookSmsSender {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+impl WebhookSmsSender {
+    /// `None` unless both the URL and signing secret are configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            client: reqwest::Client::new(),
+            url: config.sms_webhook_url.clone()?,
+            secret: config.sms_webhook_secret.clone()?,
+        })
+    }
+}
+
+#[async_trait]
+impl SmsSender for WebhookSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+        let body = serde_json::to_vec(&WebhookMessage { to, message }).map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        let response = self
+            .client
+            .post(&self.url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, sign(&self.secret, chrono::Utc::now().timestamp(), &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(AuthError::Sms(format!("SMS webhook returned {}", response.status())));
+        }
+
+        Ok(())
+    }
+}
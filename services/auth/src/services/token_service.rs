@@ -1,75 +1,230 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, Validation};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::config::Config;
-use crate::domain::{AccessToken, RefreshToken};
+use crate::domain::{permissions_version, AccessToken, Project, RefreshToken, RetiringJwtSecret, TokenSettings};
 use crate::error::AuthError;
+use crate::services::{Denylist, Keyring, SecretCipher, SigningKey};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    pub iss: String,
+    pub aud: String,
     pub sub: Uuid,           // user_id
     pub project_id: Uuid,
+    pub sid: String,         // session_id
+    /// Unique token id; empty for tokens minted before revocation support
+    #[serde(default)]
+    pub jti: String,
     pub roles: Vec<String>,
+    #[serde(default)]
     pub permissions: Vec<String>,
+    /// Set instead of `permissions` when the user has too many to embed; fetch them
+    /// from `/permissions` and cache by this version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perms_ver: Option<String>,
     pub exp: i64,
     pub iat: i64,
+    /// Project-specific claims from the claims hook
+    #[serde(flatten)]
+    pub custom: Map<String, Value>,
 }
 
+/// Claims the service sets itself; the claims hook cannot override them
+const RESERVED_CLAIMS: &[&str] = &[
+    "iss", "aud", "sub", "exp", "iat", "nbf", "jti", "project_id", "sid", "roles", "permissions", "perms_ver",
+];
+
+#[derive(Clone)]
 pub struct TokenService {
     config: Config,
+    cipher: SecretCipher,
+    keyring: Arc<Keyring>,
+    denylist: Arc<Denylist>,
 }
 
 impl TokenService {
-    pub fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new(config: Config) -> Result<Self, AuthError> {
+        let signing_key = SigningKey::from_config(&config)?;
+        Ok(Self::with_signing_key(config, signing_key))
+    }
+
+    pub fn with_signing_key(config: Config, signing_key: SigningKey) -> Self {
+        Self {
+            cipher: SecretCipher::new(&config.key_encryption_key),
+            config,
+            keyring: Arc::new(Keyring::new(signing_key)),
+            denylist: Arc::new(Denylist::new()),
+        }
+    }
+
+    /// Keys shared with the keyring service, which swaps in rotated keys
+    pub fn keyring(&self) -> Arc<Keyring> {
+        self.keyring.clone()
+    }
+
+    /// Revoked token ids shared with the denylist service, which keeps them in sync
+    pub fn denylist(&self) -> Arc<Denylist> {
+        self.denylist.clone()
+    }
+
+    /// Encrypt a project secret for storage; `settings_for` decrypts it
+    pub fn encrypt_secret(&self, secret: &str) -> Result<String, AuthError> {
+        self.cipher.encrypt(secret)
+    }
+
+    /// Resolve a project's token parameters against the service defaults
+    pub fn settings_for(&self, project: &Project) -> Result<TokenSettings, AuthError> {
+        let jwt = &project.settings.jwt;
+        let max_access_ttl = self.config.jwt_expiry_seconds;
+        let decrypt = |secret: &Option<String>| {
+            secret.as_deref().map(|secret| self.cipher.decrypt(secret)).transpose()
+        };
+        let retiring_secret = match project
+            .retiring_jwt_secret
+            .as_ref()
+            .filter(|retiring| retiring.expires_at > Utc::now())
+        {
+            Some(retiring) => Some(RetiringJwtSecret {
+                secret: decrypt(&retiring.secret)?,
+                expires_at: retiring.expires_at,
+            }),
+            None => None,
+        };
+
+        Ok(TokenSettings {
+            project_id: project.id,
+            issuer: jwt.issuer.clone().unwrap_or_else(|| self.config.jwt_issuer.clone()),
+            audience: jwt.audience.clone().unwrap_or_else(|| project.id.to_string()),
+            // Keys are retired assuming no token outlives JWT_EXPIRY_SECONDS
+            access_token_ttl_seconds: jwt
+                .access_token_ttl_seconds
+                .map_or(max_access_ttl, |ttl| ttl.min(max_access_ttl)),
+            refresh_token_ttl_seconds: jwt
+                .refresh_token_ttl_seconds
+                .unwrap_or(self.config.refresh_token_expiry_seconds),
+            signing_secret: decrypt(&project.jwt_secret)?,
+            retiring_secret,
+            claims_hook: project.settings.claims_hook.clone(),
+            sessions: project.settings.sessions.clone(),
+        })
     }
 
     pub fn generate_access_token(
         &self,
+        settings: &TokenSettings,
         user_id: Uuid,
-        project_id: Uuid,
+        session_id: &str,
         roles: Vec<String>,
         permissions: Vec<String>,
+        mut custom: Map<String, Value>,
     ) -> Result<AccessToken, AuthError> {
         let now = Utc::now();
-        let exp = now + Duration::seconds(self.config.jwt_expiry_seconds as i64);
+        let exp = now + Duration::seconds(settings.access_token_ttl_seconds as i64);
+
+        // Keep tokens small enough for headers and cookies
+        let (permissions, perms_ver) = if permissions.len() > self.config.jwt_max_permissions {
+            (vec![], Some(permissions_version(&permissions)))
+        } else {
+            (permissions, None)
+        };
+
+        custom.retain(|name, _| {
+            let reserved = RESERVED_CLAIMS.contains(&name.as_str());
+            if reserved {
+                tracing::warn!("Ignoring reserved claim {} from project {}", name, settings.project_id);
+            }
+            !reserved
+        });
 
         let claims = Claims {
+            iss: settings.issuer.clone(),
+            aud: settings.audience.clone(),
             sub: user_id,
-            project_id,
+            project_id: settings.project_id,
+            sid: session_id.to_string(),
+            jti: Uuid::new_v4().simple().to_string(),
             roles,
             permissions,
+            perms_ver,
             exp: exp.timestamp(),
             iat: now.timestamp(),
+            custom,
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.config.jwt_secret.as_ref()),
-        )?;
+        let signing_key = project_key(settings)
+            .map(Arc::new)
+            .unwrap_or_else(|| self.keyring.signing_key());
+        let mut header = Header::new(signing_key.algorithm());
+        header.kid = Some(signing_key.kid().to_string());
+
+        let token = encode(&header, &claims, signing_key.encoding_key())?;
 
-        Ok(AccessToken::new(token, self.config.jwt_expiry_seconds))
+        Ok(AccessToken::new(token, claims.jti, settings.access_token_ttl_seconds))
     }
 
-    pub fn generate_refresh_token(&self) -> RefreshToken {
+    pub fn generate_refresh_token(&self, settings: &TokenSettings) -> RefreshToken {
         RefreshToken::new(
             crate::utils::crypto::generate_refresh_token(),
-            self.config.refresh_token_expiry_seconds,
+            settings.refresh_token_ttl_seconds,
         )
     }
 
-    pub fn verify_access_token(&self, token: &str) -> Result<Claims, AuthError> {
-        let validation = Validation::default();
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.config.jwt_secret.as_ref()),
-            &validation,
-        )?;
+    /// Longest lifetime of any access token, and so how long a replaced signer must
+    /// keep verifying
+    pub fn max_access_token_ttl(&self) -> Duration {
+        Duration::seconds(self.config.jwt_expiry_seconds as i64)
+    }
+
+    /// Verify a token for the given project, enforcing its issuer and audience
+    pub fn verify_access_token(&self, token: &str, settings: &TokenSettings) -> Result<Claims, AuthError> {
+        let header = decode_header(token)?;
+        let keys = match header.kid {
+            // The current and retiring project secrets share a kid, so try both
+            Some(kid) if kid == project_kid(settings.project_id) => project_keys(settings),
+            // Projects with their own secret never accept keyring-signed tokens, except
+            // while the keyring is the signer being retired
+            _ if !accepts_keyring(settings) => return Err(AuthError::InvalidToken),
+            Some(kid) => vec![self.keyring.find(&kid).ok_or(AuthError::InvalidToken)?],
+            // Tokens without a kid predate key rotation and were signed by the configured key
+            None => vec![self.keyring.configured()],
+        };
+
+        let mut verified = Err(AuthError::InvalidToken);
+        for key in keys {
+            let mut validation = Validation::new(key.algorithm());
+            validation.set_issuer(&[&settings.issuer]);
+            validation.set_audience(&[&settings.audience]);
+            validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+            verified = decode::<Claims>(token, key.decoding_key(), &validation)
+                .map(|data| data.claims)
+                .map_err(AuthError::from);
+            if verified.is_ok() {
+                break;
+            }
+        }
+        let claims = verified?;
+        if claims.project_id != settings.project_id {
+            return Err(AuthError::InvalidToken);
+        }
 
-        Ok(token_data.claims)
+        // Revoked before expiry, e.g. on signout or ban
+        if !claims.jti.is_empty() && self.denylist.contains(&claims.jti) {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+
+    /// Public keys downstream services use to verify access tokens
+    pub fn jwks(&self) -> JwkSet {
+        self.keyring.jwks()
     }
 
     pub fn verify_refresh_token(&self, _token: &str) -> Result<(), AuthError> {
@@ -83,48 +238,269 @@ impl TokenService {
     }
 }
 
+fn project_kid(project_id: Uuid) -> String {
+    format!("project_{}", project_id)
+}
+
+/// The project's own HMAC key, if it has a secret configured
+fn project_key(settings: &TokenSettings) -> Option<SigningKey> {
+    settings
+        .signing_secret
+        .as_ref()
+        .map(|secret| SigningKey::from_secret(Algorithm::HS256, secret.as_bytes(), Some(project_kid(settings.project_id))))
+}
+
+/// Project HMAC keys accepted for verification, current first
+fn project_keys(settings: &TokenSettings) -> Vec<Arc<SigningKey>> {
+    let retiring = settings
+        .retiring_secret
+        .as_ref()
+        .and_then(|retiring| retiring.secret.as_ref())
+        .map(|secret| SigningKey::from_secret(Algorithm::HS256, secret.as_bytes(), Some(project_kid(settings.project_id))));
+
+    project_key(settings).into_iter().chain(retiring).map(Arc::new).collect()
+}
+
+/// Whether tokens signed by the service keyring are accepted for the project
+fn accepts_keyring(settings: &TokenSettings) -> bool {
+    settings.signing_secret.is_none()
+        || settings.retiring_secret.as_ref().is_some_and(|retiring| retiring.secret.is_none())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn test_config() -> Config {
-        Config {
-            database_url: "postgres://test".to_string(),
-            jwt_secret: "test-secret-key-for-testing-purposes-only".to_string(),
-            jwt_expiry_seconds: 3600,
-            refresh_token_expiry_seconds: 2592000,
-            smtp_host: None,
-            smtp_port: None,
-            smtp_username: None,
-            smtp_password: None,
-            smtp_from: None,
-            twilio_account_sid: None,
-            twilio_auth_token: None,
-            twilio_from: None,
-            allowed_origins: vec!["*".to_string()],
-            rate_limit_per_minute: 60,
-        }
+    fn settings(service: &TokenService, project: Project) -> TokenSettings {
+        service.settings_for(&project).unwrap()
+    }
+
+    fn default_settings(service: &TokenService) -> TokenSettings {
+        settings(service, Project::new(Uuid::new_v4(), "test".to_string()))
     }
 
     #[test]
     fn test_generate_and_verify_token() {
-        let service = TokenService::new(test_config());
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let settings = default_settings(&service);
         let user_id = Uuid::new_v4();
-        let project_id = Uuid::new_v4();
 
         let access_token = service
-            .generate_access_token(user_id, project_id, vec![], vec![])
+            .generate_access_token(&settings, user_id, "sess_test", vec![], vec![], Map::new())
             .unwrap();
 
-        let claims = service.verify_access_token(&access_token.token).unwrap();
+        let claims = service.verify_access_token(&access_token.token, &settings).unwrap();
         assert_eq!(claims.sub, user_id);
-        assert_eq!(claims.project_id, project_id);
+        assert_eq!(claims.project_id, settings.project_id);
+        assert_eq!(claims.sid, "sess_test");
+        assert_eq!(claims.iss, "merco-auth");
+        assert_eq!(claims.aud, settings.project_id.to_string());
+    }
+
+    #[test]
+    fn test_large_permission_sets_fall_back_to_version() {
+        let mut config = Config::for_tests();
+        config.jwt_max_permissions = 2;
+        let service = TokenService::new(config).unwrap();
+        let settings = default_settings(&service);
+        let roles = vec!["admin".to_string()];
+        let few = vec!["posts:read".to_string(), "posts:write".to_string()];
+        let many = vec!["a:read".to_string(), "b:read".to_string(), "c:read".to_string()];
+
+        let token = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", roles.clone(), few.clone(), Map::new())
+            .unwrap();
+        let claims = service.verify_access_token(&token.token, &settings).unwrap();
+        assert_eq!(claims.permissions, few);
+        assert!(claims.perms_ver.is_none());
+
+        let token = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", roles.clone(), many.clone(), Map::new())
+            .unwrap();
+        let claims = service.verify_access_token(&token.token, &settings).unwrap();
+        assert_eq!(claims.roles, roles);
+        assert!(claims.permissions.is_empty());
+        assert_eq!(claims.perms_ver, Some(permissions_version(&many)));
+    }
+
+    #[test]
+    fn test_custom_claims_cannot_override_reserved_claims() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let settings = default_settings(&service);
+        let user_id = Uuid::new_v4();
+        let custom = serde_json::json!({ "plan": "pro", "sub": "someone-else", "roles": ["admin"] });
+
+        let token = service
+            .generate_access_token(&settings, user_id, "sess_test", vec![], vec![], custom.as_object().unwrap().clone())
+            .unwrap();
+        let claims = service.verify_access_token(&token.token, &settings).unwrap();
+
+        assert_eq!(claims.sub, user_id);
+        assert!(claims.roles.is_empty());
+        assert_eq!(claims.custom.len(), 1);
+        assert_eq!(claims.custom["plan"], "pro");
+    }
+
+    #[test]
+    fn test_denied_jti_is_rejected() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let settings = default_settings(&service);
+        let first = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+        let second = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+        assert_ne!(first.jti, second.jti);
+
+        service.denylist().insert(first.jti.clone(), Utc::now() + Duration::hours(1));
+
+        assert!(matches!(
+            service.verify_access_token(&first.token, &settings),
+            Err(AuthError::InvalidToken)
+        ));
+        assert_eq!(service.verify_access_token(&second.token, &settings).unwrap().jti, second.jti);
+    }
+
+    #[test]
+    fn test_token_is_rejected_by_other_project() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let project_a = default_settings(&service);
+        let project_b = default_settings(&service);
+
+        let access_token = service
+            .generate_access_token(&project_a, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+
+        assert!(service.verify_access_token(&access_token.token, &project_b).is_err());
+    }
+
+    #[test]
+    fn test_project_settings_override_defaults() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let mut project = Project::new(Uuid::new_v4(), "test".to_string());
+        project.settings.jwt.issuer = Some("https://auth.example.com".to_string());
+        project.settings.jwt.audience = Some("example-api".to_string());
+        project.settings.jwt.access_token_ttl_seconds = Some(300);
+        project.settings.jwt.refresh_token_ttl_seconds = Some(86400);
+        let settings = settings(&service, project.clone());
+
+        let access_token = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+        let claims = service.verify_access_token(&access_token.token, &settings).unwrap();
+
+        assert_eq!(claims.iss, "https://auth.example.com");
+        assert_eq!(claims.aud, "example-api");
+        assert_eq!(access_token.expires_in, 300);
+        assert_eq!(claims.exp - claims.iat, 300);
+        assert_eq!(service.generate_refresh_token(&settings).expires_in, 86400);
+
+        // Lifetimes can only be shortened
+        project.settings.jwt.access_token_ttl_seconds = Some(7 * 86400);
+        assert_eq!(service.settings_for(&project).unwrap().access_token_ttl_seconds, 3600);
+    }
+
+    #[test]
+    fn test_project_secret_isolates_signing() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let mut project = Project::new(Uuid::new_v4(), "test".to_string());
+        let keyring_settings = settings(&service, project.clone());
+        project.jwt_secret = Some("project-secret".to_string());
+        let project_settings = settings(&service, project);
+
+        let project_token = service
+            .generate_access_token(&project_settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+        let keyring_token = service
+            .generate_access_token(&keyring_settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+
+        let header = decode_header(&project_token.token).unwrap();
+        assert_eq!(header.kid, Some(format!("project_{}", project_settings.project_id)));
+        assert!(service.verify_access_token(&project_token.token, &project_settings).is_ok());
+        assert!(service.verify_access_token(&keyring_token.token, &project_settings).is_err());
+    }
+
+    #[test]
+    fn test_replaced_project_signer_verifies_until_it_expires() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let mut project = Project::new(Uuid::new_v4(), "test".to_string());
+        let keyring_token = service
+            .generate_access_token(&settings(&service, project.clone()), Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+
+        // Switching from the keyring to a project secret, stored encrypted
+        let first_secret = service.encrypt_secret("first-secret").unwrap();
+        project.jwt_secret = Some(first_secret.clone());
+        project.retiring_jwt_secret = Some(RetiringJwtSecret {
+            secret: None,
+            expires_at: Utc::now() + Duration::hours(1),
+        });
+        let first_settings = settings(&service, project.clone());
+        assert!(service.verify_access_token(&keyring_token.token, &first_settings).is_ok());
+        let first_token = service
+            .generate_access_token(&first_settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+
+        // Rotating the project secret
+        project.jwt_secret = Some(service.encrypt_secret("second-secret").unwrap());
+        project.retiring_jwt_secret = Some(RetiringJwtSecret {
+            secret: Some(first_secret),
+            expires_at: Utc::now() + Duration::hours(1),
+        });
+        let second_settings = settings(&service, project.clone());
+        assert!(service.verify_access_token(&first_token.token, &second_settings).is_ok());
+        assert!(service.verify_access_token(&keyring_token.token, &second_settings).is_err());
+
+        // Once the overlap has passed only the current secret verifies
+        project.retiring_jwt_secret.as_mut().unwrap().expires_at = Utc::now() - Duration::seconds(1);
+        let expired_settings = settings(&service, project);
+        assert!(service.verify_access_token(&first_token.token, &expired_settings).is_err());
+    }
+
+    #[test]
+    fn test_asymmetric_token_verifies_against_jwks() {
+        let key = SigningKey::from_pem(
+            jsonwebtoken::Algorithm::RS256,
+            include_bytes!("testdata/rsa_private.pem"),
+            Some("rsa-1".to_string()),
+        )
+        .unwrap();
+        let service = TokenService::with_signing_key(Config::for_tests(), key);
+        let settings = default_settings(&service);
+
+        let access_token = service
+            .generate_access_token(&settings, Uuid::new_v4(), "sess_test", vec![], vec![], Map::new())
+            .unwrap();
+
+        let header = decode_header(&access_token.token).unwrap();
+        assert_eq!(header.alg, jsonwebtoken::Algorithm::RS256);
+        assert_eq!(header.kid.as_deref(), Some("rsa-1"));
+
+        let jwks = service.jwks();
+        let jwk = jwks.find("rsa-1").unwrap();
+        let mut validation = Validation::new(jsonwebtoken::Algorithm::RS256);
+        validation.set_audience(&[&settings.audience]);
+        let decoded = decode::<Claims>(
+            &access_token.token,
+            &jsonwebtoken::DecodingKey::from_jwk(jwk).unwrap(),
+            &validation,
+        )
+        .unwrap();
+        assert_eq!(decoded.claims.sid, "sess_test");
+    }
+
+    #[test]
+    fn test_hs256_keys_are_not_published() {
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        assert!(service.jwks().keys.is_empty());
     }
 
     #[test]
     fn test_refresh_token_format() {
-        let service = TokenService::new(test_config());
-        let refresh_token = service.generate_refresh_token();
+        let service = TokenService::new(Config::for_tests()).unwrap();
+        let refresh_token = service.generate_refresh_token(&default_settings(&service));
 
         assert!(refresh_token.token.starts_with("rt_"));
         assert!(service.verify_refresh_token(&refresh_token.token).is_ok());
@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::hmac;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::domain::SmsDelivery;
+use crate::error::AuthError;
+use crate::repository::traits::SmsDeliveryRepository;
+use crate::services::sms_service::SmsSender;
+use crate::utils::crypto::constant_time_eq;
+
+/// Provider name recorded on deliveries
+pub const TWILIO: &str = "twilio";
+
+/// Header carrying Twilio's signature of a callback
+pub const SIGNATURE_HEADER: &str = "x-twilio-signature";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct MessageResponse {
+    sid: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    code: Option<i64>,
+    message: String,
+}
+
+/// Sends SMS through the Twilio Messages API and records delivery status callbacks
+pub struct TwilioSmsSender<DR: SmsDeliveryRepository> {
+    client: reqwest::Client,
+    base_url: String,
+    account_sid: String,
+    auth_token: String,
+    from: String,
+    status_callback_url: Option<String>,
+    deliveries: DR,
+}
+
+impl<DR: SmsDeliveryRepository> TwilioSmsSender<DR> {
+    /// `None` unless the account SID, auth token and sender are all configured
+    pub fn from_config(config: &Config, deliveries: DR) -> Option<Self> {
+        Some(Self {
+            client: reqwest::Client::new(),
+            base_url: config.twilio_base_url.trim_end_matches('/').to_string(),
+            account_sid: config.twilio_account_sid.clone()?,
+            auth_token: config.twilio_auth_token.clone()?,
+            from: config.twilio_from.clone()?,
+            status_callback_url: config.twilio_status_callback_url.clone(),
+            deliveries,
+        })
+    }
+
+    /// Apply a delivery status callback after checking Twilio signed it for our callback URL
+    pub async fn record_status(&self, signature: Option<&str>, params: &[(String, String)]) -> Result<(), AuthError> {
+        let url = self.status_callback_url.as_deref().ok_or(AuthError::Unauthorized)?;
+        let signature = signature.ok_or(AuthError::Unauthorized)?;
+        if !constant_time_eq(sign(&self.auth_token, url, params).as_bytes(), signature.as_bytes()) {
+            return Err(AuthError::Unauthorized);
+        }
+
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .filter(|value| !value.is_empty())
+        };
+        let sid = param("MessageSid").ok_or_else(|| AuthError::InvalidInput("MessageSid missing".to_string()))?;
+        let status = param("MessageStatus")
+            .ok_or_else(|| AuthError::InvalidInput("MessageStatus missing".to_string()))?;
+        let error_code = param("ErrorCode");
+
+        if matches!(status, "failed" | "undelivered") {
+            tracing::warn!(
+                "Twilio could not deliver message {}: {} (error {})",
+                sid,
+                status,
+                error_code.unwrap_or("none")
+            );
+        }
+
+        if !self.deliveries.update_status(TWILIO, sid, status, error_code).await? {
+            tracing::debug!("Ignoring status {} for unknown or finished Twilio message {}", status, sid);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<DR: SmsDeliveryRepository> SmsSender for TwilioSmsSender<DR> {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+        let url = format!("{}/2010-04-01/Accounts/{}/Messages.json", self.base_url, self.account_sid);
+        let sender = if self.from.starts_with("MG") { "MessagingServiceSid" } else { "From" };
+
+        let mut form = vec![("To", to), (sender, self.from.as_str()), ("Body", message)];
+        if let Some(callback) = &self.status_callback_url {
+            form.push(("StatusCallback", callback));
+        }
+
+        let response = self
+            .client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .timeout(REQUEST_TIMEOUT)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AuthError::Sms(match response.json::<ErrorResponse>().await {
+                Ok(ErrorResponse { code: Some(code), message }) => format!("Twilio error {}: {}", code, message),
+                Ok(ErrorResponse { code: None, message }) => format!("Twilio error: {}", message),
+                Err(_) => format!("Twilio returned {}", status),
+            }));
+        }
+
+        let sent: MessageResponse = response.json().await.map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        // The message is on its way; failing to record it only loses its status updates
+        let delivery = SmsDelivery::new(TWILIO, sent.sid.clone(), to.to_string(), sent.status);
+        if let Err(e) = self.deliveries.create(&delivery).await {
+            tracing::warn!("Failed to record Twilio message {}: {}", sent.sid, e);
+        }
+
+        Ok(())
+    }
+}
+
+/// Base64 HMAC-SHA1 of the URL followed by each parameter's name and value, sorted by name
+pub fn sign(auth_token: &str, url: &str, params: &[(String, String)]) -> String {
+    let mut sorted: Vec<_> = params.iter().collect();
+    sorted.sort();
+
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, auth_token.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(url.as_bytes());
+    for (name, value) in sorted {
+        context.update(name.as_bytes());
+        context.update(value.as_bytes());
+    }
+
+    STANDARD.encode(context.sign().as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemorySmsDeliveryRepository;
+    use crate::utils::test_server::serve;
+    use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Form, Json, Router};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const CALLBACK_URL: &str = "https://auth.example.com/auth/sms/status/twilio";
+
+    type Captured = Arc<Mutex<Option<(HeaderMap, HashMap<String, String>)>>>;
+
+    fn sender(base_url: &str) -> TwilioSmsSender<InMemorySmsDeliveryRepository> {
+        let mut config = Config::for_tests();
+        config.twilio_account_sid = Some("AC123".to_string());
+        config.twilio_auth_token = Some("secret".to_string());
+        config.twilio_from = Some("+15550100000".to_string());
+        config.twilio_base_url = base_url.to_string();
+        config.twilio_status_callback_url = Some(CALLBACK_URL.to_string());
+
+        TwilioSmsSender::from_config(&config, InMemorySmsDeliveryRepository::default()).unwrap()
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sends_message_and_records_delivery() {
+        let captured = Captured::default();
+        let base_url = serve(
+            Router::new()
+                .route(
+                    "/2010-04-01/Accounts/AC123/Messages.json",
+                    post(|State(captured): State<Captured>, headers: HeaderMap, Form(form): Form<HashMap<String, String>>| async move {
+                        *captured.lock().unwrap() = Some((headers, form));
+                        (StatusCode::CREATED, Json(json!({ "sid": "SM1", "status": "queued" })))
+                    }),
+                )
+                .with_state(captured.clone()),
+        )
+        .await;
+
+        let sender = sender(&base_url);
+        sender.send("+15550100199", "Your code is 123456").await.unwrap();
+
+        let (headers, form) = captured.lock().unwrap().take().unwrap();
+        assert_eq!(
+            headers.get("authorization").unwrap(),
+            &format!("Basic {}", STANDARD.encode("AC123:secret"))
+        );
+        assert_eq!(form["To"], "+15550100199");
+        assert_eq!(form["From"], "+15550100000");
+        assert_eq!(form["Body"], "Your code is 123456");
+        assert_eq!(form["StatusCallback"], CALLBACK_URL);
+
+        let delivery = sender.deliveries.find(TWILIO, "SM1").unwrap();
+        assert_eq!(delivery.recipient, "+15550100199");
+        assert_eq!(delivery.status, "queued");
+    }
+
+    #[tokio::test]
+    async fn test_maps_twilio_errors() {
+        let base_url = serve(Router::new().route(
+            "/2010-04-01/Accounts/AC123/Messages.json",
+            post(|| async {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "code": 21211, "message": "Invalid 'To' Phone Number", "status": 400 })),
+                )
+            }),
+        ))
+        .await;
+
+        match sender(&base_url).send("+1", "hi").await {
+            Err(AuthError::Sms(message)) => assert_eq!(message, "Twilio error 21211: Invalid 'To' Phone Number"),
+            other => panic!("expected an SMS error, got {:?}", other.err()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_callbacks_are_signed_and_never_regress() {
+        let sender = sender("http://127.0.0.1:9");
+        sender
+            .deliveries
+            .create(&SmsDelivery::new(TWILIO, "SM1".to_string(), "+15550100199".to_string(), "queued".to_string()))
+            .await
+            .unwrap();
+
+        let delivered = params(&[("MessageSid", "SM1"), ("MessageStatus", "delivered"), ("AccountSid", "AC123")]);
+        let signature = sign("secret", CALLBACK_URL, &delivered);
+
+        let forged = params(&[("MessageSid", "SM1"), ("MessageStatus", "failed"), ("AccountSid", "AC123")]);
+        for (signature, params) in [(None, &delivered), (Some(signature.as_str()), &forged)] {
+            let result = sender.record_status(signature, params).await;
+            assert!(matches!(result, Err(AuthError::Unauthorized)));
+        }
+
+        sender.record_status(Some(&signature), &delivered).await.unwrap();
+        assert_eq!(sender.deliveries.find(TWILIO, "SM1").unwrap().status, "delivered");
+
+        // A late "sent" callback must not undo the final status
+        let sent = params(&[("MessageSid", "SM1"), ("MessageStatus", "sent"), ("AccountSid", "AC123")]);
+        sender.record_status(Some(&sign("secret", CALLBACK_URL, &sent)), &sent).await.unwrap();
+        assert_eq!(sender.deliveries.find(TWILIO, "SM1").unwrap().status, "delivered");
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::error::AuthError;
+use crate::services::sms_service::SmsSender;
+
+/// Provider name used in project SMS settings
+pub const VONAGE: &str = "vonage";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The SMS API answers 200 whatever happened; each message carries its own status
+#[derive(Deserialize)]
+struct SmsResponse {
+    messages: Vec<MessageStatus>,
+}
+
+#[derive(Deserialize)]
+struct MessageStatus {
+    status: String,
+    #[serde(rename = "error-text")]
+    error_text: Option<String>,
+}
+
+/// Sends SMS through the Vonage (Nexmo) SMS API
+pub struct VonageSmsSender {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+    from: String,
+}
+
+impl VonageSmsSender {
+    /// `None` unless the API key, secret and sender are all configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            client: reqwest::Client::new(),
+            base_url: config.vonage_base_url.trim_end_matches('/').to_string(),
+            api_key: config.vonage_api_key.clone()?,
+            api_secret: config.vonage_api_secret.clone()?,
+            from: config.vonage_from.clone()?,
+        })
+    }
+}
+
+#[async_trait]
+impl SmsSender for VonageSmsSender {
+    async fn send(&self, to: &str, message: &str) -> Result<(), AuthError> {
+        // Vonage takes numbers in international format without the leading +
+        let to = to.trim_start_matches('+');
+        let mut form = vec![
+            ("api_key", self.api_key.as_str()),
+            ("api_secret", self.api_secret.as_str()),
+            ("from", self.from.as_str()),
+            ("to", to),
+            ("text", message),
+        ];
+        if !message.is_ascii() {
+            form.push(("type", "unicode"));
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/sms/json", self.base_url))
+            .timeout(REQUEST_TIMEOUT)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| AuthError::Sms(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AuthError::Sms(format!("Vonage returned {}", status)));
+        }
+
+        let response: SmsResponse = response.json().await.map_err(|e| AuthError::Sms(e.to_string()))?;
+        match response.messages.into_iter().find(|message| message.status != "0") {
+            Some(failed) => Err(AuthError::Sms(format!(
+                "Vonage error {}: {}",
+                failed.status,
+                failed.error_text.unwrap_or_default()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::serve;
+    use axum::{routing::post, Form, Json, Router};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_reports_per_message_status() {
+        let base_url = serve(Router::new().route(
+            "/sms/json",
+            post(|Form(form): Form<HashMap<String, String>>| async move {
+                assert_eq!(form["api_key"], "key");
+                assert_eq!(form["from"], "Merco");
+                let message = if form["to"] == "905551234567" {
+                    json!({ "status": "0", "message-id": "0A0000001" })
+                } else {
+                    json!({ "status": "3", "error-text": "Invalid Message" })
+                };
+                Json(json!({ "message-count": "1", "messages": [message] }))
+            }),
+        ))
+        .await;
+
+        let mut config = Config::for_tests();
+        config.vonage_api_key = Some("key".to_string());
+        config.vonage_api_secret = Some("secret".to_string());
+        config.vonage_from = Some("Merco".to_string());
+        config.vonage_base_url = base_url;
+        let sender = VonageSmsSender::from_config(&config).unwrap();
+
+        sender.send("+905551234567", "Your code is 123456").await.unwrap();
+        match sender.send("+15550100199", "Your code is 123456").await {
+            Err(AuthError::Sms(message)) => assert_eq!(message, "Vonage error 3: Invalid Message"),
+            other => panic!("expected an SMS error, got {:?}", other.err()),
+        }
+    }
+}
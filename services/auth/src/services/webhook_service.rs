@@ -1,7 +1,13 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::AuthError;
+use common::Webhook;
+
+/// Emitted when a superseded refresh token is presented again
+pub const EVENT_REFRESH_TOKEN_REUSED: &str = "security.refresh_token_reused";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookEvent {
@@ -11,6 +17,11 @@ pub struct WebhookEvent {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, event: WebhookEvent);
+}
+
 pub struct WebhookService;
 
 impl WebhookService {
@@ -30,3 +41,33 @@ impl WebhookService {
         Ok(())
     }
 }
+
+/// Publishes events to the project's webhooks subscribed to them
+pub struct WebhookPublisher {
+    pool: PgPool,
+}
+
+impl WebhookPublisher {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for WebhookPublisher {
+    async fn publish(&self, event: WebhookEvent) {
+        let webhooks = match Webhook::list_by_event(&self.pool, event.project_id, &event.event).await {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                tracing::error!("Failed to load webhooks for {}: {}", event.event, e);
+                return;
+            }
+        };
+
+        for webhook in webhooks {
+            if let Err(e) = WebhookService::dispatch(&webhook.url, &event).await {
+                tracing::error!("Failed to dispatch {} to {}: {}", event.event, webhook.url, e);
+            }
+        }
+    }
+}
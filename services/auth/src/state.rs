@@ -0,0 +1,157 @@
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::error::AuthError;
+use crate::repository::postgres::identity::PostgresIdentityRepository;
+use crate::repository::postgres::magic_link::PostgresMagicLinkRepository;
+use crate::repository::postgres::oauth_state::PostgresOAuthStateRepository;
+use crate::repository::postgres::otp::PostgresOtpRepository;
+use crate::repository::postgres::project::PostgresProjectRepository;
+use crate::repository::postgres::revoked_token::PostgresRevokedTokenRepository;
+use crate::repository::postgres::session::PostgresSessionRepository;
+use crate::repository::postgres::signing_key::PostgresSigningKeyRepository;
+use crate::repository::postgres::sms_delivery::PostgresSmsDeliveryRepository;
+use crate::repository::postgres::user::PostgresUserRepository;
+use crate::repository::postgres::user_role::PostgresUserRoleRepository;
+use crate::repository::PostgresRepositories;
+use crate::services::{
+    messagebird, sms_service, sms_webhook, twilio, vonage, AuthService, DenylistService, EmailService, KeyringService,
+    LogSmsSender, MessageBirdSmsSender, OAuthService, PasswordlessService, SmsService, TokenService, TwilioSmsSender,
+    VonageSmsSender, WebhookPublisher, WebhookSmsSender,
+};
+
+/// AuthService backed by the Postgres repositories
+pub type PgAuthService = AuthService<
+    PostgresUserRepository,
+    PostgresSessionRepository,
+    PostgresProjectRepository,
+    PostgresUserRoleRepository,
+    PostgresRevokedTokenRepository,
+    PostgresIdentityRepository,
+>;
+
+/// KeyringService backed by the Postgres signing key table
+pub type PgKeyringService = KeyringService<PostgresSigningKeyRepository>;
+
+/// DenylistService backed by the Postgres revoked token table
+pub type PgDenylistService = DenylistService<PostgresRevokedTokenRepository>;
+
+/// PasswordlessService backed by the Postgres OTP and magic link tables
+pub type PgPasswordlessService = PasswordlessService<PostgresOtpRepository, PostgresMagicLinkRepository>;
+
+/// OAuthService keeping pending sign-ins in Postgres
+pub type PgOAuthService = OAuthService<PostgresOAuthStateRepository>;
+
+/// TwilioSmsSender recording deliveries in Postgres
+pub type PgTwilioSmsSender = TwilioSmsSender<PostgresSmsDeliveryRepository>;
+
+/// Shared state handed to every auth handler and middleware
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+    pub auth_service: Arc<PgAuthService>,
+    pub token_service: Arc<TokenService>,
+    pub keyring_service: Arc<PgKeyringService>,
+    pub denylist_service: Arc<PgDenylistService>,
+    pub passwordless_service: Arc<PgPasswordlessService>,
+    pub oauth_service: Arc<PgOAuthService>,
+    /// Set when Twilio credentials are configured; receives its status callbacks
+    pub twilio: Option<Arc<PgTwilioSmsSender>>,
+    pub repos: Arc<PostgresRepositories>,
+}
+
+impl AppState {
+    pub fn new(pool: PgPool, config: Config) -> Result<Self, AuthError> {
+        let token_service = TokenService::new(config.clone())?;
+        let keyring_service = KeyringService::new(
+            PostgresSigningKeyRepository::new(pool.clone()),
+            token_service.keyring(),
+            &config,
+        );
+        let denylist_service = Arc::new(DenylistService::new(
+            PostgresRevokedTokenRepository::new(pool.clone()),
+            token_service.denylist(),
+            &config,
+        ));
+        let auth_service = AuthService::new(
+            PostgresUserRepository::new(pool.clone()),
+            PostgresSessionRepository::new(pool.clone()),
+            PostgresProjectRepository::new(pool.clone()),
+            PostgresUserRoleRepository::new(pool.clone()),
+            PostgresIdentityRepository::new(pool.clone()),
+            token_service.clone(),
+            denylist_service.clone(),
+        )
+        .with_events(Arc::new(WebhookPublisher::new(pool.clone())));
+        let twilio = TwilioSmsSender::from_config(&config, PostgresSmsDeliveryRepository::new(pool.clone())).map(Arc::new);
+        let sms = sms_providers(&config, twilio.clone());
+        let passwordless_service = PasswordlessService::new(
+            PostgresOtpRepository::new(pool.clone()),
+            PostgresMagicLinkRepository::new(pool.clone()),
+            Arc::new(EmailService::new(config.clone())),
+            Arc::new(sms),
+            &config,
+        );
+        let oauth_service = OAuthService::new(PostgresOAuthStateRepository::new(pool.clone()), &config);
+
+        Ok(Self {
+            repos: Arc::new(PostgresRepositories::new(pool.clone())),
+            pool,
+            config: Arc::new(config),
+            auth_service: Arc::new(auth_service),
+            token_service: Arc::new(token_service),
+            keyring_service: Arc::new(keyring_service),
+            denylist_service,
+            passwordless_service: Arc::new(passwordless_service),
+            oauth_service: Arc::new(oauth_service),
+            twilio,
+        })
+    }
+
+    /// Build the state from environment configuration, loading rotated signing keys and
+    /// revoked tokens and keeping them in sync for the lifetime of the process
+    pub async fn from_env(pool: PgPool) -> Result<Self, Box<dyn std::error::Error>> {
+        let state = Self::new(pool, Config::from_env()?)?;
+        state.keyring_service.reload().await?;
+        state.keyring_service.clone().spawn_refresh();
+        state.denylist_service.reload().await?;
+        state.denylist_service.clone().spawn_refresh();
+        Ok(state)
+    }
+}
+
+/// Every SMS provider with credentials configured, in the default order of preference
+fn sms_providers(config: &Config, twilio: Option<Arc<PgTwilioSmsSender>>) -> SmsService {
+    let mut sms = SmsService::new();
+    if let Some(twilio) = twilio {
+        sms = sms.with_provider(twilio::TWILIO, twilio);
+    }
+    if let Some(vonage) = VonageSmsSender::from_config(config) {
+        sms = sms.with_provider(vonage::VONAGE, Arc::new(vonage));
+    }
+    if let Some(messagebird) = MessageBirdSmsSender::from_config(config) {
+        sms = sms.with_provider(messagebird::MESSAGEBIRD, Arc::new(messagebird));
+    }
+    if let Some(webhook) = WebhookSmsSender::from_config(config) {
+        sms = sms.with_provider(sms_webhook::WEBHOOK, Arc::new(webhook));
+    }
+
+    if sms.is_empty() {
+        if config.sms_dev_log {
+            tracing::warn!("No SMS provider configured; logging SMS messages, codes included (SMS_DEV_LOG)");
+            sms = sms.with_provider(sms_service::LOG, Arc::new(LogSmsSender));
+        } else {
+            tracing::warn!("No SMS provider configured; SMS sign-in will fail");
+        }
+    }
+    sms
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
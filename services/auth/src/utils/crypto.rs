@@ -1,3 +1,4 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::Rng;
 use sha2::{Sha256, Digest};
 
@@ -30,6 +31,11 @@ pub fn generate_magic_link_token() -> String {
     format!("ml_{}", generate_random_token(48))
 }
 
+/// Handle a client polls with while its cross-device magic link is opened elsewhere
+pub fn generate_magic_link_poll_token() -> String {
+    format!("mlp_{}", generate_random_token(48))
+}
+
 pub fn generate_reset_token() -> String {
     format!("reset_{}", generate_random_token(48))
 }
@@ -39,9 +45,30 @@ pub fn generate_api_key() -> String {
     format!("mk_{}", generate_random_token(32))
 }
 
+/// Hex SHA-256 of a random secret, for storing it without the secret itself
+pub fn sha256_hex(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+/// Hash session access/refresh token for storage (SHA-256)
+pub fn hash_token(token: &str) -> String {
+    sha256_hex(token)
+}
+
 /// Hash API key for storage (SHA-256)
 pub fn hash_api_key(key: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(key.as_bytes());
-    format!("{:x}", hasher.finalize())
+    sha256_hex(key)
+}
+
+/// PKCE S256 challenge for a code verifier (RFC 7636): unpadded base64url of its SHA-256
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Compare secrets without leaking where they differ through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
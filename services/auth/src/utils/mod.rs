@@ -1,2 +1,4 @@
 pub mod crypto;
+#[cfg(test)]
+pub mod test_server;
 pub mod validation;
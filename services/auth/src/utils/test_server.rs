@@ -0,0 +1,30 @@
+//! Local HTTP fakes of third-party APIs for tests
+
+use axum::Router;
+use tokio::net::TcpListener;
+
+/// A bound ephemeral port, for fakes that need their own URL before building their router
+pub struct FakeServer {
+    listener: TcpListener,
+    pub base_url: String,
+}
+
+impl FakeServer {
+    pub async fn bind() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        Self { listener, base_url }
+    }
+
+    /// Spawn `router` on the bound port and return its base URL
+    pub fn serve(self, router: Router) -> String {
+        let listener = self.listener;
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        self.base_url
+    }
+}
+
+/// Serve `router` on an ephemeral port and return its base URL
+pub async fn serve(router: Router) -> String {
+    FakeServer::bind().await.serve(router)
+}